@@ -0,0 +1,602 @@
+extern crate rust_tracer;
+use crate::rust_tracer::ray_tracer::scene::loader::Scene;
+use crate::rust_tracer::ray_tracer::scene::error::SceneError;
+use crate::rust_tracer::ray_tracer::image_format::ImageFormat;
+use crate::rust_tracer::ray_tracer::tone_mapping::{ExportSettings, ToneMapOperator, TransferFunction};
+use crate::rust_tracer::ray_tracer::progress::{RenderOptions, Progress, CancellationToken};
+use crate::rust_tracer::ray_tracer::accumulation::{Accumulator, CheckpointSchedule};
+use crate::rust_tracer::ray_tracer::camera::Camera;
+use crate::rust_tracer::ray_tracer::world::World;
+use crate::rust_tracer::ray_tracer::canvas::Canvas;
+use crate::rust_tracer::ray_tracer::integrator::{Integrator, PathTracerSettings};
+use crate::rust_tracer::ray_tracer::animation::{FrameRange, frame_path};
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+Usage: render <scene> [options]
+
+Renders a scene file (.yml, .yaml or .json) to an image.
+
+Options:
+  -o, --output <path>      Where to write the image. Missing directories are created.
+                           Defaults to the scene's name with the format's extension.
+  -f, --format <format>    ppm, png, pfm or hdr. Defaults to the output's extension, or ppm.
+      --width <pixels>     Overrides the camera's width.
+      --height <pixels>    Overrides the camera's height.
+  -s, --samples <count>    Overrides the camera's samples per pixel.
+      --integrator <name>  phong or path. Overrides the camera's integrator.
+      --max-depth <count>  Overrides the path tracer's maximum path length. An error with phong.
+  -j, --threads <count>    Number of render threads. Defaults to the number of CPUs.
+      --tone-map <op>      clamp, reinhard or aces. Defaults to clamp.
+      --exposure <stops>   Exposure adjustment in stops. Defaults to 0.
+      --transfer <curve>   linear, srgb or a gamma value like 2.2. Defaults to linear.
+      --time-limit <secs>  Stops rendering after this many seconds and writes the unfinished image.
+  -q, --quiet              Doesn't show the progress bar.
+
+Animation:
+      --frames <first>-<last>
+                           Renders these frames of an animated scene, each to its own image. A
+                           run of # in the output's name is replaced by the frame number, otherwise
+                           the number is added to the end. --time-limit applies to each frame.
+      --fps <rate>         Frames per second, so frame n shows the scene at time n / rate.
+                           Defaults to 24.
+
+Progressive rendering:
+      --passes <count>     Renders progressively, adding one sample per pixel per pass until
+                           this many passes are done. --samples is ignored.
+      --checkpoint-passes <count>
+                           Writes the image and the accumulation file every this many passes.
+      --checkpoint-seconds <secs>
+                           Writes the image and the accumulation file every this many seconds.
+      --accumulation <path>
+                           Where to save the accumulation buffer. Defaults to the output path
+                           with .accum added.
+      --resume             Continues from the accumulation file if it exists.
+      --help               Prints this message.";
+
+struct Options {
+    scene: PathBuf,
+    output: PathBuf,
+    format: ImageFormat,
+    width: Option<usize>,
+    height: Option<usize>,
+    samples: Option<usize>,
+    integrator: Option<Integrator>,
+    max_depth: Option<usize>,
+    threads: usize,
+    export: ExportSettings,
+    time_limit: Option<Duration>,
+    quiet: bool,
+    progressive: Option<Progressive>,
+    frames: Option<FrameRange>
+}
+
+struct Progressive {
+    passes: usize,
+    checkpoint_passes: Option<usize>,
+    checkpoint_interval: Option<Duration>,
+    accumulation: PathBuf,
+    resume: bool
+}
+
+enum CliError {
+    // Bad command line arguments. Exits with 2 and prints the usage.
+    Usage(String),
+    Scene(PathBuf, SceneError),
+    Write(PathBuf, io::Error),
+    Resume(PathBuf, String)
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Scene(path, e) => write!(f, "couldn't load {}: {}", path.display(), e),
+            CliError::Write(path, e) => write!(f, "couldn't write {}: {}", path.display(), e),
+            CliError::Resume(path, message) => write!(f, "couldn't resume from {}: {}", path.display(), message)
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    if let Err(e) = parse_args(&args).and_then(|options| run(&options)) {
+        eprintln!("error: {}", e);
+        match e {
+            CliError::Usage(_) => {
+                eprintln!("\n{}", USAGE);
+                process::exit(2);
+            },
+            _ => process::exit(1)
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<(), CliError> {
+    if let Some(range) = options.frames {
+        return run_sequence(options, range);
+    }
+    let scene = load(options, 0.0)?;
+    if let Some(progressive) = &options.progressive {
+        return run_progressive(options, progressive, &scene.camera, &scene.world);
+    }
+    render(options, &scene, &options.output, "")
+}
+
+// Renders every frame of the range to its own numbered image.
+fn run_sequence(options: &Options, range: FrameRange) -> Result<(), CliError> {
+    let start = Instant::now();
+    for frame in range.frames() {
+        let scene = load(options, range.time(frame))?;
+        render(options, &scene, &frame_path(&options.output, frame), &format!("frame {} ", frame))?;
+    }
+    eprintln!("Rendered {} frames in {:.2}s", range.len(), start.elapsed().as_secs_f32());
+    Ok(())
+}
+
+// Loads the scene at time and applies the overrides from the command line.
+fn load(options: &Options, time: f32) -> Result<Scene, CliError> {
+    let mut scene = load_scene(&options.scene, time).map_err(|e| CliError::Scene(options.scene.clone(), e))?;
+    if let Some(width) = options.width {
+        scene.camera.hsize = width;
+    }
+    if let Some(height) = options.height {
+        scene.camera.vsize = height;
+    }
+    if let Some(samples) = options.samples {
+        scene.camera.samples_per_pixel = samples;
+    }
+    if let Some(integrator) = options.integrator {
+        scene.camera.integrator = integrator;
+    }
+    if let Some(depth) = options.max_depth {
+        match &mut scene.camera.integrator {
+            Integrator::PathTracer(settings) => settings.max_depth = depth,
+            Integrator::Phong => return Err(CliError::Usage("--max-depth needs the path integrator".to_owned()))
+        }
+    }
+    Ok(scene)
+}
+
+fn render(options: &Options, scene: &Scene, output: &Path, label: &str) -> Result<(), CliError> {
+    let render_options = RenderOptions { threads: options.threads, ..Default::default() };
+    let cancel = CancellationToken::new();
+    let (canvas, progress) = scene.camera.render_tiles(&scene.world, &render_options, &cancel, |p| {
+        if options.time_limit.is_some_and(|limit| p.elapsed >= limit) {
+            cancel.cancel();
+        }
+        if !options.quiet {
+            eprint!("\r{}", progress_bar(label, p));
+        }
+    });
+    if !options.quiet {
+        eprintln!();
+    }
+
+    write_image(options, output, &canvas)?;
+
+    if progress.is_complete() {
+        eprintln!(
+            "Rendered {}x{} in {:.2}s to {}",
+            canvas.width, canvas.height, progress.elapsed.as_secs_f32(), output.display()
+        );
+    } else {
+        eprintln!(
+            "Stopped after {:.2}s with {} of {} tiles done, wrote the unfinished image to {}",
+            progress.elapsed.as_secs_f32(), progress.tiles_done, progress.tiles_total, output.display()
+        );
+    }
+    Ok(())
+}
+
+fn run_progressive(options: &Options, progressive: &Progressive, camera: &Camera, world: &World) -> Result<(), CliError> {
+    let path = &progressive.accumulation;
+    let mut accumulator = if progressive.resume && path.exists() {
+        let accumulator = Accumulator::load(path).map_err(|e| CliError::Resume(path.clone(), e.to_string()))?;
+        if accumulator.width != camera.hsize || accumulator.height != camera.vsize {
+            return Err(CliError::Resume(path.clone(), format!(
+                "it holds a {}x{} image, but the camera is {}x{}",
+                accumulator.width, accumulator.height, camera.hsize, camera.vsize
+            )));
+        }
+        eprintln!("Resuming after pass {}", accumulator.passes);
+        accumulator
+    } else {
+        Accumulator::new(camera.hsize, camera.vsize)
+    };
+
+    let start = Instant::now();
+    let render_options = RenderOptions { threads: options.threads, ..Default::default() };
+    let cancel = CancellationToken::new();
+    let mut schedule = CheckpointSchedule::new(progressive.checkpoint_passes, progressive.checkpoint_interval)
+        .resumed_at(accumulator.passes);
+    while accumulator.passes < progressive.passes && !cancel.is_cancelled() {
+        let label = format!("pass {}/{} ", accumulator.passes + 1, progressive.passes);
+        camera.render_pass(world, &mut accumulator, &render_options, &cancel, |p| {
+            if options.time_limit.is_some_and(|limit| start.elapsed() >= limit) {
+                cancel.cancel();
+            }
+            if !options.quiet {
+                eprint!("\r{}", progress_bar(&label, p));
+            }
+        });
+        if schedule.is_due(accumulator.passes) && accumulator.passes < progressive.passes {
+            checkpoint(options, progressive, &accumulator)?;
+        }
+    }
+    if !options.quiet {
+        eprintln!();
+    }
+
+    checkpoint(options, progressive, &accumulator)?;
+    eprintln!(
+        "Rendered {} of {} passes in {:.2}s to {}",
+        accumulator.passes, progressive.passes, start.elapsed().as_secs_f32(), options.output.display()
+    );
+    Ok(())
+}
+
+// Saves the accumulation buffer and the image it currently averages to.
+fn checkpoint(options: &Options, progressive: &Progressive, accumulator: &Accumulator) -> Result<(), CliError> {
+    let path = &progressive.accumulation;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| CliError::Write(path.clone(), e))?;
+        }
+    }
+    accumulator.save(path).map_err(|e| CliError::Write(path.clone(), e))?;
+    write_image(options, &options.output, &accumulator.average())
+}
+
+fn write_image(options: &Options, path: &Path, canvas: &Canvas) -> Result<(), CliError> {
+    let bytes = options.format.encode(canvas, &options.export);
+    write(path, &bytes).map_err(|e| CliError::Write(path.to_path_buf(), e))
+}
+
+// pass 3/10 [#########...........]  45%  120/400 tiles  3.2s elapsed  4.1s left
+fn progress_bar(label: &str, p: &Progress) -> String {
+    let width = 30;
+    let filled = ((p.fraction() * width as f32) as usize).min(width);
+    let eta = match p.eta() {
+        Some(eta) => format!("{:.1}s left", eta.as_secs_f32()),
+        None => "estimating".to_owned()
+    };
+    format!(
+        "{}[{}{}] {:3.0}%  {}/{} tiles  {:.1}s elapsed  {}  ",
+        label, "#".repeat(filled), ".".repeat(width - filled), p.fraction() * 100.0,
+        p.tiles_done, p.tiles_total, p.elapsed.as_secs_f32(), eta
+    )
+}
+
+// JSON scenes aren't animated, so they look the same at every time.
+fn load_scene(path: &Path, time: f32) -> Result<Scene, SceneError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Scene::load_json(path),
+        _ => Scene::load_at(path, time)
+    }
+}
+
+fn write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(path, bytes)
+}
+
+fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let mut scene = None;
+    let mut output = None;
+    let mut format = None;
+    let mut width = None;
+    let mut height = None;
+    let mut samples = None;
+    let mut integrator = None;
+    let mut max_depth = None;
+    let mut threads = None;
+    let mut export = ExportSettings::default();
+    let mut time_limit = None;
+    let mut quiet = false;
+    let mut passes = None;
+    let mut checkpoint_passes = None;
+    let mut checkpoint_interval = None;
+    let mut accumulation = None;
+    let mut resume = false;
+    let mut frames = None;
+    let mut fps = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        // Flags that don't take a value.
+        match arg {
+            "-q" | "--quiet" => quiet = true,
+            "--resume" => resume = true,
+            _ => {}
+        }
+        if arg == "-q" || arg == "--quiet" || arg == "--resume" {
+            i += 1;
+            continue;
+        }
+        if !arg.starts_with('-') {
+            if scene.is_some() {
+                return Err(CliError::Usage(format!("unexpected argument '{}'", arg)));
+            }
+            scene = Some(PathBuf::from(arg));
+            i += 1;
+            continue;
+        }
+
+        let value = match args.get(i + 1) {
+            Some(value) => value.as_str(),
+            None => return Err(CliError::Usage(format!("'{}' needs a value", arg)))
+        };
+        match arg {
+            "-o" | "--output" => output = Some(PathBuf::from(value)),
+            "-f" | "--format" => {
+                format = Some(ImageFormat::from_name(value)
+                    .ok_or_else(|| CliError::Usage(format!("unknown image format '{}'", value)))?);
+            },
+            "--width" => width = Some(positive(arg, value)?),
+            "--height" => height = Some(positive(arg, value)?),
+            "-s" | "--samples" => samples = Some(positive(arg, value)?),
+            "-j" | "--threads" => threads = Some(positive(arg, value)?),
+            "--integrator" => {
+                integrator = match value {
+                    "phong" => Some(Integrator::Phong),
+                    "path" => Some(Integrator::PathTracer(PathTracerSettings::default())),
+                    _ => return Err(CliError::Usage(format!("unknown integrator '{}'", value)))
+                };
+            },
+            "--max-depth" => max_depth = Some(positive(arg, value)?),
+            "--tone-map" => {
+                export.operator = match value {
+                    "clamp" => ToneMapOperator::Clamp,
+                    "reinhard" => ToneMapOperator::Reinhard,
+                    "aces" => ToneMapOperator::AcesFilmic,
+                    _ => return Err(CliError::Usage(format!("unknown tone mapping operator '{}'", value)))
+                };
+            },
+            "--exposure" => {
+                export.exposure = match value.parse::<f32>() {
+                    Ok(stops) if stops.is_finite() => stops,
+                    _ => return Err(CliError::Usage(format!("'{}' expects a number, found '{}'", arg, value)))
+                };
+            },
+            "--transfer" => {
+                export.transfer = match value {
+                    "linear" => TransferFunction::Linear,
+                    "srgb" => TransferFunction::Srgb,
+                    _ => match value.parse::<f32>() {
+                        Ok(gamma) if gamma > 0.0 => TransferFunction::Gamma(gamma),
+                        _ => return Err(CliError::Usage(format!("unknown transfer function '{}'", value)))
+                    }
+                };
+            },
+            "--time-limit" => time_limit = Some(seconds(arg, value)?),
+            "--passes" => passes = Some(positive(arg, value)?),
+            "--checkpoint-passes" => checkpoint_passes = Some(positive(arg, value)?),
+            "--checkpoint-seconds" => checkpoint_interval = Some(seconds(arg, value)?),
+            "--accumulation" => accumulation = Some(PathBuf::from(value)),
+            "--frames" => frames = Some(frame_range(arg, value)?),
+            "--fps" => {
+                fps = match value.parse::<f32>() {
+                    Ok(rate) if rate > 0.0 && rate.is_finite() => Some(rate),
+                    _ => return Err(CliError::Usage(format!("'{}' expects a number above 0, found '{}'", arg, value)))
+                };
+            },
+            _ => return Err(CliError::Usage(format!("unknown option '{}'", arg)))
+        }
+        i += 2;
+    }
+
+    let scene = scene.ok_or_else(|| CliError::Usage("no scene file given".to_owned()))?;
+    // A scene that picks the path tracer itself is only known once it's loaded, see load.
+    if max_depth.is_some() && integrator == Some(Integrator::Phong) {
+        return Err(CliError::Usage("--max-depth needs the path integrator".to_owned()));
+    }
+    // An explicit format wins, then the output's extension, then PPM.
+    let format = format
+        .or_else(|| output.as_ref().and_then(ImageFormat::from_path))
+        .unwrap_or(ImageFormat::Ppm);
+    let output = output.unwrap_or_else(|| {
+        let stem = scene.file_stem().map_or_else(|| "render".into(), |s| s.to_os_string());
+        PathBuf::from(stem).with_extension(format.extension())
+    });
+    let threads = threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let progressive = match passes {
+        Some(passes) => Some(Progressive {
+            passes,
+            checkpoint_passes,
+            checkpoint_interval,
+            accumulation: accumulation.unwrap_or_else(|| {
+                let mut path = output.clone().into_os_string();
+                path.push(".accum");
+                PathBuf::from(path)
+            }),
+            resume
+        }),
+        None if checkpoint_passes.is_some() || checkpoint_interval.is_some() || accumulation.is_some() || resume => {
+            return Err(CliError::Usage("the checkpoint and resume options need --passes".to_owned()));
+        },
+        None => None
+    };
+
+    let frames = match frames {
+        Some((first, last)) => Some(FrameRange::new(first, last, fps.unwrap_or(24.0))),
+        None if fps.is_some() => return Err(CliError::Usage("--fps needs --frames".to_owned())),
+        None => None
+    };
+    if frames.is_some() && progressive.is_some() {
+        return Err(CliError::Usage("--frames can't be combined with --passes".to_owned()));
+    }
+
+    Ok(Options {
+        scene, output, format, width, height, samples, integrator, max_depth, threads, export, time_limit, quiet,
+        progressive, frames
+    })
+}
+
+// A single frame like 12, or a range like 1-48.
+fn frame_range(arg: &str, value: &str) -> Result<(usize, usize), CliError> {
+    let error = || CliError::Usage(format!("'{}' expects frames like 1-48, found '{}'", arg, value));
+    let (first, last) = value.split_once('-').unwrap_or((value, value));
+    let first = first.parse::<usize>().map_err(|_| error())?;
+    let last = last.parse::<usize>().map_err(|_| error())?;
+    if last < first {
+        return Err(CliError::Usage("the last frame can't come before the first".to_owned()));
+    }
+    Ok((first, last))
+}
+
+fn seconds(arg: &str, value: &str) -> Result<Duration, CliError> {
+    match value.parse::<f32>() {
+        Ok(secs) if secs >= 0.0 && secs.is_finite() => Ok(Duration::from_secs_f32(secs)),
+        _ => Err(CliError::Usage(format!("'{}' expects a number of seconds, found '{}'", arg, value)))
+    }
+}
+
+fn positive(arg: &str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(CliError::Usage(format!("'{}' expects a whole number above 0, found '{}'", arg, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn defaults() {
+        let options = parse_args(&args("scenes/three_spheres.yml")).ok().unwrap();
+        assert!(
+            options.output == Path::new("three_spheres.ppm") && options.format == ImageFormat::Ppm &&
+            options.width.is_none() && options.samples.is_none() && options.threads >= 1 &&
+            options.export == ExportSettings::default(),
+            "The default options were not correct."
+        )
+    }
+
+    #[test]
+    fn format_from_output_extension() {
+        let options = parse_args(&args("scene.yml -o out/images/a.png --tone-map aces --transfer 2.2")).ok().unwrap();
+        assert!(
+            options.format == ImageFormat::Png && options.output == Path::new("out/images/a.png") &&
+            options.export.operator == ToneMapOperator::AcesFilmic &&
+            options.export.transfer == TransferFunction::Gamma(2.2),
+            "The options were not parsed correctly."
+        )
+    }
+
+    #[test]
+    fn overrides() {
+        let options = parse_args(&args("--width 640 --height 480 -s 16 -j 3 -f pfm scene.json")).ok().unwrap();
+        assert!(
+            options.width == Some(640) && options.height == Some(480) && options.samples == Some(16) &&
+            options.threads == 3 && options.format == ImageFormat::Pfm && options.output == Path::new("scene.pfm"),
+            "The overrides were not parsed correctly."
+        )
+    }
+
+    #[test]
+    fn usage_errors() {
+        for line in ["", "scene.yml --samples 0", "scene.yml --width", "scene.yml --format gif",
+                     "scene.yml --bogus 1", "a.yml b.yml", "scene.yml --transfer -1", "scene.yml --time-limit -2",
+                     "scene.yml --resume", "scene.yml --integrator whitted", "scene.yml --frames 5-2",
+                     "scene.yml --frames a-b", "scene.yml --fps 30", "scene.yml --frames 1-3 --fps 0",
+                     "scene.yml --frames 1-3 --passes 4", "scene.yml --exposure inf",
+                     "scene.yml --exposure nan", "scene.yml --integrator phong --max-depth 4"].iter() {
+            assert!(
+                matches!(parse_args(&args(line)), Err(CliError::Usage(_))),
+                "'{}' should be a usage error.", line
+            )
+        }
+    }
+
+    #[test]
+    fn quiet_and_time_limit() {
+        let options = parse_args(&args("scene.yml -q --time-limit 1.5")).ok().unwrap();
+        assert!(
+            options.quiet && options.time_limit == Some(Duration::from_millis(1500)),
+            "The quiet and time limit options were not parsed correctly."
+        )
+    }
+
+    #[test]
+    fn progress_bar_layout() {
+        let p = Progress {
+            tiles_done: 1, tiles_total: 4, pixels_done: 50, pixels_total: 100, elapsed: Duration::from_secs(2)
+        };
+        let result = progress_bar("pass 2/5 ", &p);
+        assert!(
+            result == format!("pass 2/5 [{}{}]  50%  1/4 tiles  2.0s elapsed  2.0s left  ", "#".repeat(15), ".".repeat(15)),
+            "The progress bar was not drawn correctly. Result: {}", result
+        )
+    }
+
+    #[test]
+    fn progressive_options() {
+        let options = parse_args(&args("scene.yml -o out/a.png --passes 64 --checkpoint-passes 8 --resume")).ok().unwrap();
+        let progressive = options.progressive.unwrap();
+        assert!(
+            progressive.passes == 64 && progressive.checkpoint_passes == Some(8) && progressive.resume &&
+            progressive.checkpoint_interval.is_none() && progressive.accumulation == Path::new("out/a.png.accum"),
+            "The progressive options were not parsed correctly."
+        )
+    }
+
+    #[test]
+    fn integrator_options() {
+        let options = parse_args(&args("scene.yml --integrator path --max-depth 4")).ok().unwrap();
+        assert!(
+            options.integrator == Some(Integrator::PathTracer(PathTracerSettings::default())) &&
+            options.max_depth == Some(4),
+            "The integrator options were not parsed correctly."
+        )
+    }
+
+    #[test]
+    fn max_depth_needs_the_path_integrator() {
+        let phong = parse_args(&args("scenes/three_spheres.yml --max-depth 4")).ok().unwrap();
+        let path = parse_args(&args("scenes/materials.yml --max-depth 4")).ok().unwrap();
+        let depth = match load(&path, 0.0).ok().unwrap().camera.integrator {
+            Integrator::PathTracer(settings) => settings.max_depth,
+            Integrator::Phong => 0
+        };
+        assert!(
+            matches!(load(&phong, 0.0), Err(CliError::Usage(_))) && depth == 4,
+            "--max-depth should only be accepted for scenes that use the path tracer."
+        )
+    }
+
+    #[test]
+    fn frame_options() {
+        let options = parse_args(&args("scene.yml --frames 0-47 --fps 12 -o out/spin_###.png")).ok().unwrap();
+        let single = parse_args(&args("scene.yml --frames 7")).ok().unwrap();
+        assert!(
+            options.frames == Some(FrameRange::new(0, 47, 12.0)) && options.output == Path::new("out/spin_###.png") &&
+            single.frames == Some(FrameRange::new(7, 7, 24.0)) && parse_args(&args("scene.yml")).ok().unwrap().frames.is_none(),
+            "The frame options were not parsed correctly."
+        )
+    }
+}
@@ -0,0 +1,218 @@
+use crate::ray_tracer::canvas::Canvas;
+use crate::ray_tracer::tuple::Tuple;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Running sums for a progressive render. Every pass adds one filter weighted sample to each pixel,
+// and the image at any point is the weighted average of everything added so far. The sums are
+// kept in floats so that they can be saved and picked up again by a later run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    pub width: usize,
+    pub height: usize,
+    // Number of complete passes that have been added.
+    pub passes: usize,
+    // Sum of weight * color for every pixel.
+    pub sums: Vec<Tuple>,
+    pub weights: Vec<f32>
+}
+
+const MAGIC: &[u8; 8] = b"RTACCUM1";
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Accumulator {
+            width,
+            height,
+            passes: 0,
+            sums: vec![Tuple::color(0.0, 0.0, 0.0); width * height],
+            weights: vec![0.0; width * height]
+        }
+    }
+
+    pub fn add_sample(&mut self, x: usize, y: usize, color: Tuple, weight: f32) {
+        let i = y * self.width + x;
+        self.sums[i] = &self.sums[i] + &(&color * weight);
+        self.weights[i] += weight;
+    }
+
+    // The current estimate of the image. Pixels without any weight yet are black.
+    pub fn average(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for (i, (sum, weight)) in self.sums.iter().zip(self.weights.iter()).enumerate() {
+            if *weight != 0.0 {
+                canvas.pixels[i] = sum * (1.0 / weight);
+            }
+        }
+        canvas
+    }
+
+    // Layout: the magic bytes, then width, height and passes as little endian u64s, then red,
+    // green, blue and weight as little endian f32s for every pixel, row by row.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 24 + self.sums.len() * 16);
+        bytes.extend_from_slice(MAGIC);
+        for n in [self.width, self.height, self.passes].iter() {
+            bytes.extend_from_slice(&(*n as u64).to_le_bytes());
+        }
+        for (sum, weight) in self.sums.iter().zip(self.weights.iter()) {
+            for c in [sum.x, sum.y, sum.z, *weight].iter() {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Accumulator> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+        if bytes.len() < MAGIC.len() + 24 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid("not an accumulation file"));
+        }
+        let header: Vec<usize> = bytes[MAGIC.len()..MAGIC.len() + 24]
+            .chunks(8)
+            .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as usize)
+            .collect();
+        let (width, height, passes) = (header[0], header[1], header[2]);
+        let data = &bytes[MAGIC.len() + 24..];
+        if width.checked_mul(height).and_then(|n| n.checked_mul(16)) != Some(data.len()) {
+            return Err(invalid("the accumulation file is truncated"));
+        }
+
+        let mut accumulator = Accumulator::new(width, height);
+        accumulator.passes = passes;
+        for (i, pixel) in data.chunks(16).enumerate() {
+            let c: Vec<f32> = pixel.chunks(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            accumulator.sums[i] = Tuple::color(c[0], c[1], c[2]);
+            accumulator.weights[i] = c[3];
+        }
+        Ok(accumulator)
+    }
+
+    // Writes to a temporary file first and renames it over path, so that a render killed in the
+    // middle of saving doesn't lose the previous checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, self.to_bytes())?;
+        fs::rename(&temp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Accumulator> {
+        Accumulator::from_bytes(&fs::read(path)?)
+    }
+}
+
+// Decides when a progressive render should write out its current state: every `passes` passes,
+// every `interval`, or both, whichever comes first.
+#[derive(Clone, Debug)]
+pub struct CheckpointSchedule {
+    pub passes: Option<usize>,
+    pub interval: Option<Duration>,
+    last_time: Instant,
+    last_pass: usize
+}
+
+impl CheckpointSchedule {
+    pub fn new(passes: Option<usize>, interval: Option<Duration>) -> Self {
+        CheckpointSchedule {
+            passes,
+            interval,
+            last_time: Instant::now(),
+            last_pass: 0
+        }
+    }
+
+    // For a render picked up after `passes` passes, so the first checkpoint waits for new passes.
+    pub fn resumed_at(mut self, passes: usize) -> Self {
+        self.last_pass = passes;
+        self
+    }
+
+    // Call after every pass with the number of passes done. Returns true, and restarts the clock,
+    // when a checkpoint is due.
+    pub fn is_due(&mut self, passes_done: usize) -> bool {
+        let by_passes = self.passes.is_some_and(|n| n > 0 && passes_done >= self.last_pass + n);
+        let by_time = self.interval.is_some_and(|t| self.last_time.elapsed() >= t);
+        if by_passes || by_time {
+            self.last_pass = passes_done;
+            self.last_time = Instant::now();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_is_weighted() {
+        let mut a = Accumulator::new(2, 1);
+        a.add_sample(0, 0, Tuple::color(1.0, 0.0, 0.0), 1.0);
+        a.add_sample(0, 0, Tuple::color(0.0, 1.0, 0.0), 3.0);
+        let result = a.average();
+        assert!(
+            result.pixel_at(0, 0) == Tuple::color(0.25, 0.75, 0.0) && result.pixel_at(1, 0) == Tuple::color(0.0, 0.0, 0.0),
+            "The average was not weighted correctly. Result: {:?}", result.pixels
+        )
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut a = Accumulator::new(3, 2);
+        a.passes = 17;
+        a.add_sample(2, 1, Tuple::color(0.1, 2.5, 1.0e-7), 0.3);
+        let result = Accumulator::from_bytes(&a.to_bytes()).unwrap();
+        assert!(
+            result.passes == 17 && result.width == 3 && result.height == 2 &&
+            result.sums[5].x.to_bits() == a.sums[5].x.to_bits() && result.weights == a.weights,
+            "The accumulation buffer did not survive being saved."
+        )
+    }
+
+    #[test]
+    fn truncated_bytes_are_rejected() {
+        let bytes = Accumulator::new(2, 2).to_bytes();
+        let truncated = Accumulator::from_bytes(&bytes[..bytes.len() - 1]);
+        let wrong_magic = Accumulator::from_bytes(b"NOTACCUMxxxxxxxxxxxxxxxxxxxxxxxx");
+        assert!(
+            truncated.map_err(|e| e.kind()).err() == Some(io::ErrorKind::InvalidData) && wrong_magic.is_err(),
+            "Damaged accumulation files should be rejected."
+        )
+    }
+
+    #[test]
+    fn checkpoint_every_n_passes() {
+        let mut schedule = CheckpointSchedule::new(Some(3), None);
+        let due: Vec<bool> = (1..=7).map(|pass| schedule.is_due(pass)).collect();
+        assert!(
+            due == vec![false, false, true, false, false, true, false],
+            "Checkpoints should be due every 3 passes. Result: {:?}", due
+        )
+    }
+
+    #[test]
+    fn resumed_checkpoints_count_from_the_resume() {
+        let mut schedule = CheckpointSchedule::new(Some(3), None).resumed_at(10);
+        let due: Vec<bool> = (11..=13).map(|pass| schedule.is_due(pass)).collect();
+        assert!(
+            due == vec![false, false, true],
+            "A resumed render should wait 3 new passes for its first checkpoint. Result: {:?}", due
+        )
+    }
+
+    #[test]
+    fn checkpoint_after_interval() {
+        let mut schedule = CheckpointSchedule::new(None, Some(Duration::from_secs(0)));
+        let mut never = CheckpointSchedule::new(None, Some(Duration::from_secs(3600)));
+        assert!(
+            schedule.is_due(1) && !never.is_due(1),
+            "Checkpoints should be due once the interval has passed."
+        )
+    }
+}
@@ -0,0 +1,278 @@
+use crate::ray_tracer::decomposition::Decomposition;
+use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::quaternion::Quaternion;
+use crate::ray_tracer::tuple::Tuple;
+use std::path::{Path, PathBuf};
+
+// How a value gets from one keyframe to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Interpolation {
+    // At a constant speed.
+    #[default]
+    Linear,
+    // Speeds up away from the keyframe and slows down into the next one (smoothstep).
+    Smooth
+}
+
+impl Interpolation {
+    // Maps how far along a segment the time is, from 0 to 1, to how far along the value is.
+    pub fn ease(&self, f: f32) -> f32 {
+        let f = f.clamp(0.0, 1.0);
+        match self {
+            Interpolation::Linear => f,
+            Interpolation::Smooth => f * f * (3.0 - 2.0 * f)
+        }
+    }
+}
+
+// Values that can be blended, t = 0 giving self and t = 1 giving other.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+// Keeps the kind (point, vector or color) of self.
+impl Lerp for Tuple {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Tuple { x: self.x.lerp(&other.x, t), y: self.y.lerp(&other.y, t), z: self.z.lerp(&other.z, t), w: self.w }
+    }
+}
+
+// Element by element, which is exact for translations and scaling but not for rotations. Animate
+// the angle of a rotation, a Quaternion or a Decomposition instead to turn something around.
+impl Lerp for Matrix {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut m = *self;
+        for (row, other_row) in m.value.iter_mut().zip(other.value.iter()) {
+            for (a, b) in row.iter_mut().zip(other_row.iter()) {
+                *a = a.lerp(b, t);
+            }
+        }
+        m
+    }
+}
+
+impl Lerp for Quaternion {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+// Each step separately, so that a transform turns instead of shrinking through the middle.
+impl Lerp for Decomposition {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Decomposition {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: self.rotation.lerp(&other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+            shear: self.shear.lerp(&other.shear, t)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    // How the value moves on to the next keyframe.
+    pub interpolation: Interpolation
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Keyframe { time, value, interpolation: Interpolation::Linear }
+    }
+
+    pub fn smooth(time: f32, value: T) -> Self {
+        Keyframe { time, value, interpolation: Interpolation::Smooth }
+    }
+}
+
+// A value that changes over time. Before the first keyframe and after the last one it holds still.
+#[derive(Clone, PartialEq)]
+pub struct Track<T> {
+    // Sorted by time.
+    pub keyframes: Vec<Keyframe<T>>
+}
+
+impl<T> Track<T> {
+    // The keyframes don't need to be in order. Panics if there aren't any.
+    pub fn new(mut keyframes: Vec<Keyframe<T>>) -> Self {
+        assert!(!keyframes.is_empty(), "A track needs at least one keyframe.");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Track { keyframes }
+    }
+
+    // A value that never changes.
+    pub fn constant(value: T) -> Self {
+        Track { keyframes: vec![Keyframe::new(0.0, value)] }
+    }
+
+    // The keyframes on either side of time and how far the value has moved from the first to the
+    // second, with the first keyframe's easing applied. Outside of the keyframes both are the
+    // nearest one.
+    pub fn segment(&self, time: f32) -> (&Keyframe<T>, &Keyframe<T>, f32) {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return (&self.keyframes[0], &self.keyframes[0], 0.0);
+        }
+        if next == self.keyframes.len() {
+            return (&self.keyframes[next - 1], &self.keyframes[next - 1], 0.0);
+        }
+        let (k0, k1) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        (k0, k1, k0.interpolation.ease((time - k0.time) / (k1.time - k0.time)))
+    }
+}
+
+impl<T: Lerp> Track<T> {
+    pub fn value_at(&self, time: f32) -> T {
+        let (k0, k1, f) = self.segment(time);
+        k0.value.lerp(&k1.value, f)
+    }
+}
+
+// The frames first to last, inclusive, of an animation played at fps frames per second. Frame n
+// shows the scene at time n / fps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameRange {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32
+}
+
+impl FrameRange {
+    pub fn new(first: usize, last: usize, fps: f32) -> Self {
+        FrameRange { first, last, fps }
+    }
+
+    pub fn time(&self, frame: usize) -> f32 {
+        frame as f32 / self.fps
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = usize> {
+        self.first..=self.last
+    }
+
+    pub fn len(&self) -> usize {
+        (self.last + 1).saturating_sub(self.first)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Numbers pattern for one frame. A run of '#' in the file name is replaced by the frame number,
+// padded with zeros to the length of the run, like render_####.png becoming render_0012.png.
+// Without one, the number is added to the end of the name with four digits.
+pub fn frame_path(pattern: &Path, frame: usize) -> PathBuf {
+    let name = pattern.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+    let numbered = match name.find('#') {
+        Some(start) => {
+            let width = name[start..].chars().take_while(|c| *c == '#').count();
+            format!("{}{:0width$}{}", &name[..start], frame, &name[start + width..], width = width)
+        },
+        None => {
+            let stem = pattern.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+            match pattern.extension() {
+                Some(extension) => format!("{}_{:04}.{}", stem, frame, extension.to_string_lossy()),
+                None => format!("{}_{:04}", stem, frame)
+            }
+        }
+    };
+    pattern.with_file_name(numbered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    #[test]
+    fn smooth_interpolation_eases_in_and_out() {
+        let smooth = Interpolation::Smooth;
+        assert!(
+            f_equal(smooth.ease(0.0), 0.0) && f_equal(smooth.ease(0.5), 0.5) && f_equal(smooth.ease(1.0), 1.0) &&
+            smooth.ease(0.1) < 0.1 && smooth.ease(0.9) > 0.9 && f_equal(Interpolation::Linear.ease(0.1), 0.1),
+            "Smooth interpolation should start and end slower than linear interpolation."
+        )
+    }
+
+    #[test]
+    fn track_values() {
+        let track = Track::new(vec![
+            Keyframe::new(2.0, Tuple::point(4.0, 0.0, 0.0)),
+            Keyframe::smooth(1.0, Tuple::point(2.0, 0.0, 0.0)),
+            Keyframe::new(0.0, Tuple::point(0.0, 0.0, 0.0))
+        ]);
+        assert!(
+            track.value_at(-1.0) == Tuple::point(0.0, 0.0, 0.0) &&
+            track.value_at(0.25) == Tuple::point(0.5, 0.0, 0.0) &&
+            track.value_at(1.25) == Tuple::point(2.0 + 2.0 * Interpolation::Smooth.ease(0.25), 0.0, 0.0) &&
+            track.value_at(5.0) == Tuple::point(4.0, 0.0, 0.0),
+            "The track was not interpolated between its keyframes correctly."
+        )
+    }
+
+    #[test]
+    fn rotation_track_turns_the_short_way() {
+        let axis = Tuple::vector(0.0, 0.0, 1.0);
+        let track = Track::new(vec![
+            Keyframe::new(0.0, Quaternion::from_axis_angle(axis, 0.0)),
+            Keyframe::new(1.0, Quaternion::from_axis_angle(axis, 5.0))
+        ]);
+        // 5 radians one way is about 1.28 the other.
+        let expected = Quaternion::from_axis_angle(axis, (5.0 - 2.0 * std::f32::consts::PI) / 2.0);
+        let result = track.value_at(0.5);
+        assert!(
+            result == expected || result == Quaternion::new(-expected.w, -expected.x, -expected.y, -expected.z),
+            "A rotation track should turn the short way between its keyframes. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn decomposed_transforms_turn_through_the_middle() {
+        let start = Matrix::identity().scale(2.0, 2.0, 2.0).translate(1.0, 0.0, 0.0).decompose().unwrap();
+        let end = Matrix::identity().scale(4.0, 4.0, 4.0).rotate_y(2.0).translate(3.0, 0.0, 0.0).decompose().unwrap();
+        let expected = Matrix::identity().scale(3.0, 3.0, 3.0).rotate_y(1.0).translate(2.0, 0.0, 0.0);
+        let result = start.lerp(&end, 0.5).to_matrix();
+        assert!(
+            result == expected,
+            "The decomposed transform should scale, turn and move halfway. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn constant_track() {
+        let track = Track::constant(0.5);
+        assert!(
+            f_equal(track.value_at(-3.0), 0.5) && f_equal(track.value_at(10.0), 0.5),
+            "A constant track should have the same value at every time."
+        )
+    }
+
+    #[test]
+    fn frame_times() {
+        let range = FrameRange::new(12, 15, 24.0);
+        assert!(
+            range.len() == 4 && range.frames().collect::<Vec<usize>>() == vec![12, 13, 14, 15] &&
+            f_equal(range.time(12), 0.5) && FrameRange::new(3, 2, 24.0).is_empty(),
+            "The frames of the range were not correct."
+        )
+    }
+
+    #[test]
+    fn numbered_frame_paths() {
+        assert!(
+            frame_path(Path::new("out/turntable_###.png"), 7) == Path::new("out/turntable_007.png") &&
+            frame_path(Path::new("out/turntable.png"), 42) == Path::new("out/turntable_0042.png") &&
+            frame_path(Path::new("frame"), 12345) == Path::new("frame_12345"),
+            "The frames were not numbered correctly."
+        )
+    }
+}
@@ -0,0 +1,527 @@
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::sampling::{cosine_hemisphere, orthonormal_basis};
+use std::f32::consts::PI;
+
+// Below this roughness a surface is perfectly smooth. Its specular lobe becomes a delta function
+// that only sample can return, evaluate and pdf leave it out.
+const SMOOTH_ROUGHNESS: f32 = 0.01;
+
+// Reflectance at normal incidence of non-metals in the metal/rough workflow.
+const DIELECTRIC_F0: f32 = 0.04;
+
+// Physically based scattering models for the path tracer. All directions point away from the
+// surface: wo towards the viewer and wi towards the light. n is the surface normal pointing out
+// of the object. The dielectric uses it to tell whether wo is inside or outside, the opaque models
+// scatter on whichever side wo is on.
+//
+// Roughness is the perceptual roughness of PBR assets, the GGX alpha is its square.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bsdf {
+    // Ideal diffuse reflection.
+    Lambert { albedo: Tuple },
+    // The metal/rough workflow. A GGX (Trowbridge-Reitz) specular lobe with Schlick's Fresnel
+    // over a Lambertian base. Metalness 1 is a bare metal whose reflectance at normal incidence is
+    // color. Metalness 0 is a colored diffuse surface under a clear 4% reflective coat.
+    Conductor { color: Tuple, roughness: f32, metalness: f32 },
+    // Glass, water and the like. Light is either reflected or refracted, with the exact Fresnel
+    // equations deciding how much of each. Refracted light is multiplied by tint. A roughness of 0
+    // is a smooth interface, anything above that scatters through GGX microfacets.
+    Dielectric { ior: f32, roughness: f32, tint: Tuple }
+}
+
+// A direction picked by Bsdf::sample.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BsdfSample {
+    pub wi: Tuple,
+    // f * |cos| / pdf, the factor a path's throughput is multiplied by.
+    pub weight: Tuple,
+    pub pdf: f32,
+    // A perfectly smooth lobe was picked. pdf is then a probability rather than a density.
+    pub delta: bool
+}
+
+impl Bsdf {
+    // The fraction of light coming in from wi that leaves towards wo, per unit of solid angle.
+    pub fn evaluate(&self, wo: Tuple, wi: Tuple, n: Tuple) -> Tuple {
+        let black = Tuple::color(0.0, 0.0, 0.0);
+        match *self {
+            Bsdf::Lambert { albedo } => {
+                if same_side(wo, wi, n) { &albedo * (1.0 / PI) } else { black }
+            },
+            Bsdf::Conductor { color, roughness, metalness } => {
+                let n = facing(n, wo);
+                let (cos_o, cos_i) = (&wo * &n, &wi * &n);
+                if cos_o <= 0.0 || cos_i <= 0.0 {
+                    return black;
+                }
+                // The coat lets through what it doesn't reflect.
+                let diffuse = &color * ((1.0 - metalness) * (1.0 - schlick(DIELECTRIC_F0, cos_o)) / PI);
+                if is_smooth(roughness) {
+                    return diffuse;
+                }
+                let alpha = roughness * roughness;
+                let h = (&wo + &wi).norm();
+                let fresnel = schlick_color(base_reflectance(color, metalness), &wo * &h);
+                let specular = ggx_d(&h * &n, alpha) * smith_g(cos_o, cos_i, alpha) / (4.0 * cos_o * cos_i);
+                &diffuse + &(&fresnel * specular)
+            },
+            Bsdf::Dielectric { ior, roughness, tint } => {
+                if is_smooth(roughness) {
+                    black
+                } else {
+                    rough_dielectric(wo, wi, n, ior, roughness * roughness, tint).0
+                }
+            }
+        }
+    }
+
+    // The density, over solid angle, with which sample returns wi. Zero for smooth lobes.
+    pub fn pdf(&self, wo: Tuple, wi: Tuple, n: Tuple) -> f32 {
+        match *self {
+            Bsdf::Lambert { .. } => {
+                if same_side(wo, wi, n) { (&wi * &n).abs() / PI } else { 0.0 }
+            },
+            Bsdf::Conductor { roughness, metalness, .. } => {
+                let n = facing(n, wo);
+                let cos_i = &wi * &n;
+                if &wo * &n <= 0.0 || cos_i <= 0.0 {
+                    return 0.0;
+                }
+                let p_specular = specular_probability(metalness);
+                let diffuse = (1.0 - p_specular) * cos_i / PI;
+                if is_smooth(roughness) {
+                    return diffuse;
+                }
+                let h = (&wo + &wi).norm();
+                diffuse + p_specular * microfacet_pdf(&h * &n, roughness * roughness) / (4.0 * (&wo * &h))
+            },
+            Bsdf::Dielectric { ior, roughness, tint } => {
+                if is_smooth(roughness) {
+                    0.0
+                } else {
+                    rough_dielectric(wo, wi, n, ior, roughness * roughness, tint).1
+                }
+            }
+        }
+    }
+
+    // Picks a direction for wi, roughly in proportion to how much light it carries. None when the
+    // sampled direction turned out to be invalid, which ends the path.
+    pub fn sample(&self, wo: Tuple, n: Tuple, rng: &mut Rng) -> Option<BsdfSample> {
+        let wi = match *self {
+            Bsdf::Lambert { albedo } => {
+                let wi = cosine_hemisphere(facing(n, wo), rng.next_f32(), rng.next_f32());
+                let pdf = self.pdf(wo, wi, n);
+                return if pdf > 0.0 { Some(BsdfSample { wi, weight: albedo, pdf, delta: false }) } else { None };
+            },
+            Bsdf::Conductor { color, roughness, metalness } => {
+                let n = facing(n, wo);
+                let p_specular = specular_probability(metalness);
+                if rng.next_f32() >= p_specular {
+                    cosine_hemisphere(n, rng.next_f32(), rng.next_f32())
+                } else if is_smooth(roughness) {
+                    let fresnel = schlick_color(base_reflectance(color, metalness), &wo * &n);
+                    return Some(BsdfSample {
+                        wi: (-&wo).reflect(n),
+                        weight: &fresnel * (1.0 / p_specular),
+                        pdf: p_specular,
+                        delta: true
+                    });
+                } else {
+                    let m = sample_microfacet(n, roughness * roughness, rng);
+                    (-&wo).reflect(m)
+                }
+            },
+            Bsdf::Dielectric { ior, roughness, tint } => {
+                // Reflection and refraction are picked in proportion to the Fresnel term, which
+                // leaves only the tint and the change in radiance across the interface in the
+                // weight of a smooth surface.
+                let smooth = is_smooth(roughness);
+                let m = if smooth { n } else { sample_microfacet(n, roughness * roughness, rng) };
+                // Microfacets facing away from wo can't be seen from it.
+                if (&wo * &m) * (&wo * &n) <= 0.0 {
+                    return None;
+                }
+                let reflectance = fresnel_dielectric(&wo * &m, ior);
+                if rng.next_f32() < reflectance {
+                    let wi = (-&wo).reflect(m);
+                    if smooth {
+                        return Some(BsdfSample { wi, weight: Tuple::color(1.0, 1.0, 1.0), pdf: reflectance, delta: true });
+                    }
+                    // A rough surface can reflect off of a microfacet into the surface, or refract
+                    // back out of it. Neither is possible, so those samples are dropped.
+                    if !same_side(wo, wi, n) {
+                        return None;
+                    }
+                    wi
+                } else {
+                    let (wi, etap) = refract(wo, m, ior)?;
+                    if smooth {
+                        let weight = &tint * (1.0 / (etap * etap));
+                        return Some(BsdfSample { wi, weight, pdf: 1.0 - reflectance, delta: true });
+                    }
+                    if same_side(wo, wi, n) {
+                        return None;
+                    }
+                    wi
+                }
+            }
+        };
+
+        let pdf = self.pdf(wo, wi, n);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let weight = &self.evaluate(wo, wi, n) * ((&wi * &n).abs() / pdf);
+        Some(BsdfSample { wi, weight, pdf, delta: false })
+    }
+
+    // Whether every direction this BSDF scatters into is a delta lobe, so that sampling lights
+    // directly can't find anything.
+    pub fn is_delta(&self) -> bool {
+        match *self {
+            Bsdf::Lambert { .. } => false,
+            Bsdf::Conductor { roughness, metalness, .. } => is_smooth(roughness) && metalness >= 1.0,
+            Bsdf::Dielectric { roughness, .. } => is_smooth(roughness)
+        }
+    }
+}
+
+// What is wrong with value for the parameter called name, for scene loaders to report. Metalness
+// blends between two models so it stays between 0 and 1, roughness can't be negative and an
+// index of refraction has to be positive. Other parameters can be anything.
+pub fn parameter_problem(name: &str, value: f32) -> Option<String> {
+    let (valid, rule) = match name {
+        "metalness" => ((0.0..=1.0).contains(&value), "must be between 0 and 1"),
+        "roughness" => (value >= 0.0, "can't be negative"),
+        "ior" => (value > 0.0, "must be greater than 0"),
+        _ => (true, "")
+    };
+    if valid { None } else { Some(format!("{} {}, found {}", name, rule, value)) }
+}
+
+fn is_smooth(roughness: f32) -> bool {
+    roughness < SMOOTH_ROUGHNESS
+}
+
+fn same_side(wo: Tuple, wi: Tuple, n: Tuple) -> bool {
+    (&wo * &n) * (&wi * &n) > 0.0
+}
+
+// n flipped onto the same side as w.
+fn facing(n: Tuple, w: Tuple) -> Tuple {
+    if &n * &w < 0.0 { -&n } else { n }
+}
+
+// Metals spend every sample on the specular lobe, non-metals split them evenly.
+fn specular_probability(metalness: f32) -> f32 {
+    0.5 + 0.5 * metalness.clamp(0.0, 1.0)
+}
+
+fn base_reflectance(color: Tuple, metalness: f32) -> Tuple {
+    let coat = Tuple::color(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0);
+    &(&coat * (1.0 - metalness)) + &(&color * metalness)
+}
+
+fn schlick(f0: f32, cos: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+fn schlick_color(f0: Tuple, cos: f32) -> Tuple {
+    let t = (1.0 - cos.clamp(0.0, 1.0)).powi(5);
+    Tuple::color(f0.x + (1.0 - f0.x) * t, f0.y + (1.0 - f0.y) * t, f0.z + (1.0 - f0.z) * t)
+}
+
+// Fraction of unpolarized light reflected by an interface with an index of refraction of ior on
+// the inside and 1 on the outside. cos is between the direction and the outward normal, so it is
+// negative for light arriving from inside. 1 under total internal reflection.
+pub fn fresnel_dielectric(cos: f32, ior: f32) -> f32 {
+    let (cos_i, eta) = if cos < 0.0 { (-cos.max(-1.0), 1.0 / ior) } else { (cos.min(1.0), ior) };
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+// Bends wo through the interface with normal n, which may be on either side. Returns the refracted
+// direction and the ratio of the indices of refraction on its side and on wo's side. None under
+// total internal reflection.
+pub fn refract(wo: Tuple, n: Tuple, ior: f32) -> Option<(Tuple, f32)> {
+    let (mut n, mut cos_i, mut eta) = (n, &wo * &n, ior);
+    if cos_i < 0.0 {
+        n = -&n;
+        cos_i = -cos_i;
+        eta = 1.0 / ior;
+    }
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let wi = &(&wo * (-1.0 / eta)) + &(&n * (cos_i / eta - cos_t));
+    Some((wi.norm(), eta))
+}
+
+// The GGX distribution of microfacet normals, cos_m being the angle between one and the normal.
+fn ggx_d(cos_m: f32, alpha: f32) -> f32 {
+    if cos_m <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = cos_m * cos_m * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+// Smith's height correlated masking-shadowing for GGX.
+fn smith_g(cos_o: f32, cos_i: f32, alpha: f32) -> f32 {
+    let lambda = |cos: f32| {
+        let cos2 = cos * cos;
+        ((1.0 + alpha * alpha * (1.0 - cos2) / cos2).sqrt() - 1.0) / 2.0
+    };
+    1.0 / (1.0 + lambda(cos_o.abs()) + lambda(cos_i.abs()))
+}
+
+// Density of sample_microfacet over solid angle of microfacet normals.
+fn microfacet_pdf(cos_m: f32, alpha: f32) -> f32 {
+    ggx_d(cos_m, alpha) * cos_m
+}
+
+// A microfacet normal around n, picked with density D(m) * cos.
+fn sample_microfacet(n: Tuple, alpha: f32, rng: &mut Rng) -> Tuple {
+    let (u1, u2) = (rng.next_f32(), rng.next_f32());
+    let tan2 = alpha * alpha * u1 / (1.0 - u1).max(1.0e-7);
+    let cos = 1.0 / (1.0 + tan2).sqrt();
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = orthonormal_basis(n);
+    &(&(&t * (sin * phi.cos())) + &(&b * (sin * phi.sin()))) + &(&n * cos)
+}
+
+// Walter et al. 2007, "Microfacet Models for Refraction through Rough Surfaces". Returns f and
+// the density of sample picking wi, which chooses between reflection and refraction by the
+// Fresnel term of the sampled microfacet.
+fn rough_dielectric(wo: Tuple, wi: Tuple, n: Tuple, ior: f32, alpha: f32, tint: Tuple) -> (Tuple, f32) {
+    let none = (Tuple::color(0.0, 0.0, 0.0), 0.0);
+    let (cos_o, cos_i) = (&wo * &n, &wi * &n);
+    if cos_o == 0.0 || cos_i == 0.0 {
+        return none;
+    }
+    let reflect = cos_o * cos_i > 0.0;
+    // Ratio of the indices of refraction on wi's side and on wo's side.
+    let etap = if reflect { 1.0 } else if cos_o > 0.0 { ior } else { 1.0 / ior };
+    let m = &(&wi * etap) + &wo;
+    if m.mag() == 0.0 {
+        return none;
+    }
+    let m = facing(m.norm(), n);
+    // Microfacets facing away from either direction can't connect them.
+    if (&m * &wi) * cos_i < 0.0 || (&m * &wo) * cos_o < 0.0 {
+        return none;
+    }
+
+    let cos_m = &m * &n;
+    let reflectance = fresnel_dielectric(&wo * &m, ior);
+    let d = ggx_d(cos_m, alpha);
+    let g = smith_g(cos_o, cos_i, alpha);
+    if reflect {
+        let f = d * g * reflectance / (4.0 * cos_o * cos_i).abs();
+        let pdf = microfacet_pdf(cos_m, alpha) / (4.0 * (&wo * &m).abs()) * reflectance;
+        (Tuple::color(f, f, f), pdf)
+    } else {
+        let denom = (&wi * &m) + (&wo * &m) / etap;
+        let denom = denom * denom;
+        let f = d * (1.0 - reflectance) * g * ((&wi * &m) * (&wo * &m) / (denom * cos_i * cos_o)).abs() / (etap * etap);
+        let pdf = microfacet_pdf(cos_m, alpha) * (&wi * &m).abs() / denom * (1.0 - reflectance);
+        (&tint * f, pdf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    fn normal() -> Tuple {
+        Tuple::vector(0.0, 1.0, 0.0)
+    }
+
+    // A direction in the upper hemisphere at the given angle from the normal.
+    fn at_angle(theta: f32) -> Tuple {
+        Tuple::vector(theta.sin(), theta.cos(), 0.0)
+    }
+
+    // Integrates pdf over the sphere, with directions stratified over z and the angle around the
+    // normal. Uniform in z is uniform over the sphere.
+    fn integrate_pdf(bsdf: &Bsdf, wo: Tuple) -> f32 {
+        let mut rng = Rng::new(7);
+        let n = 600;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let z = 1.0 - 2.0 * (i as f32 + rng.next_f32()) / n as f32;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * (j as f32 + rng.next_f32()) / n as f32;
+                let wi = Tuple::vector(r * phi.cos(), z, r * phi.sin());
+                sum += bsdf.pdf(wo, wi, normal());
+            }
+        }
+        sum * 4.0 * PI / (n * n) as f32
+    }
+
+    // Checks that every sample's weight and pdf agree with evaluate and pdf, and returns the average
+    // weight, which is the fraction of light the surface scatters.
+    fn check_samples(bsdf: &Bsdf, wo: Tuple) -> Tuple {
+        let mut rng = Rng::new(3);
+        let n = 20000;
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            if let Some(s) = bsdf.sample(wo, normal(), &mut rng) {
+                if !s.delta {
+                    let pdf = bsdf.pdf(wo, s.wi, normal());
+                    let expected = &bsdf.evaluate(wo, s.wi, normal()) * ((&s.wi * &normal()).abs() / pdf);
+                    assert!(
+                        (pdf - s.pdf).abs() <= 1.0e-3 * pdf && (expected.x - s.weight.x).abs() <= 1.0e-3 * expected.x.max(1.0),
+                        "The sample of {:?} doesn't agree with evaluate and pdf. Sample: {:?}", bsdf, s
+                    );
+                }
+                sum = &sum + &s.weight;
+            }
+        }
+        &sum * (1.0 / n as f32)
+    }
+
+    #[test]
+    fn lambert() {
+        let bsdf = Bsdf::Lambert { albedo: Tuple::color(0.5, 0.6, 0.7) };
+        let wo = at_angle(0.3);
+        let wi = at_angle(-1.0);
+        let below = -&wi;
+        let average = check_samples(&bsdf, wo);
+        assert!(
+            bsdf.evaluate(wo, wi, normal()) == Tuple::color(0.5 / PI, 0.6 / PI, 0.7 / PI) &&
+            f_equal(bsdf.pdf(wo, wi, normal()), 1.0_f32.cos() / PI) &&
+            bsdf.evaluate(wo, below, normal()) == Tuple::color(0.0, 0.0, 0.0) &&
+            (average.y - 0.6).abs() < 1.0e-3 && (average.z - 0.7).abs() < 1.0e-3,
+            "The Lambertian BSDF was not correct. Average weight: {:?}", average
+        )
+    }
+
+    #[test]
+    fn lambert_from_below() {
+        // Opaque surfaces scatter on the side the viewer is on.
+        let bsdf = Bsdf::Lambert { albedo: Tuple::color(1.0, 1.0, 1.0) };
+        let wo = -&at_angle(0.3);
+        let s = bsdf.sample(wo, normal(), &mut Rng::new(1)).unwrap();
+        assert!(
+            &s.wi * &normal() < 0.0 && bsdf.evaluate(wo, s.wi, normal()) == Tuple::color(1.0 / PI, 1.0 / PI, 1.0 / PI),
+            "The Lambertian BSDF should reflect on the viewer's side."
+        )
+    }
+
+    #[test]
+    fn rough_conductor() {
+        let bsdf = Bsdf::Conductor { color: Tuple::color(1.0, 1.0, 1.0), roughness: 0.5, metalness: 1.0 };
+        let wo = at_angle(0.5);
+        let integral = integrate_pdf(&bsdf, wo);
+        let average = check_samples(&bsdf, wo);
+        // A white metal reflects everything except what single scattering GGX loses between the
+        // microfacets.
+        assert!(
+            integral > 0.9 && integral <= 1.01 && average.x > 0.85 && average.x <= 1.0,
+            "The rough conductor does not conserve energy. Integral: {} Average weight: {:?}", integral, average
+        )
+    }
+
+    #[test]
+    fn conductor_specular_is_reciprocal() {
+        let bsdf = Bsdf::Conductor { color: Tuple::color(0.9, 0.6, 0.3), roughness: 0.3, metalness: 1.0 };
+        let (a, b) = (at_angle(0.4), at_angle(-0.9));
+        assert!(
+            bsdf.evaluate(a, b, normal()) == bsdf.evaluate(b, a, normal()),
+            "The metal should reflect the same both ways."
+        )
+    }
+
+    #[test]
+    fn plastic_conductor() {
+        let bsdf = Bsdf::Conductor { color: Tuple::color(0.8, 0.2, 0.2), roughness: 0.4, metalness: 0.0 };
+        let wo = at_angle(0.2);
+        let integral = integrate_pdf(&bsdf, wo);
+        let average = check_samples(&bsdf, wo);
+        assert!(
+            integral > 0.95 && integral <= 1.01 && average.x < 0.85 && average.y > 0.2 && average.y < 0.3,
+            "The non-metal was not correct. Integral: {} Average weight: {:?}", integral, average
+        )
+    }
+
+    #[test]
+    fn smooth_conductor_is_a_mirror() {
+        let bsdf = Bsdf::Conductor { color: Tuple::color(0.9, 0.8, 0.7), roughness: 0.0, metalness: 1.0 };
+        let wo = at_angle(0.5);
+        let s = bsdf.sample(wo, normal(), &mut Rng::new(2)).unwrap();
+        let fresnel = schlick_color(Tuple::color(0.9, 0.8, 0.7), 0.5_f32.cos());
+        assert!(
+            s.delta && s.wi == at_angle(-0.5) && s.weight == fresnel && bsdf.is_delta() &&
+            bsdf.evaluate(wo, s.wi, normal()) == Tuple::color(0.0, 0.0, 0.0),
+            "The smooth metal should be a perfect mirror. Result: {:?}", s
+        )
+    }
+
+    #[test]
+    fn fresnel_equations() {
+        let normal_incidence = fresnel_dielectric(1.0, 1.5);
+        assert!(
+            f_equal(normal_incidence, 0.04) && fresnel_dielectric(-0.5, 1.5) == 1.0 &&
+            f_equal(fresnel_dielectric(0.0, 1.5), 1.0) && f_equal(fresnel_dielectric(-1.0, 1.5), normal_incidence),
+            "The Fresnel reflectance was not correct."
+        )
+    }
+
+    #[test]
+    fn refraction_follows_snells_law() {
+        let wo = at_angle(PI / 4.0);
+        let (wi, etap) = refract(wo, normal(), 1.5).unwrap();
+        let sin_t = (PI / 4.0).sin() / 1.5;
+        let back = refract(wi, normal(), 1.5).unwrap().0;
+        assert!(
+            f_equal(-wi.x, sin_t) && wi.y < 0.0 && etap == 1.5 && back == wo &&
+            refract(Tuple::vector(0.9, -0.1, 0.0).norm(), normal(), 1.5).is_none(),
+            "The refraction was not correct. Result: {:?}", wi
+        )
+    }
+
+    #[test]
+    fn smooth_dielectric() {
+        let bsdf = Bsdf::Dielectric { ior: 1.5, roughness: 0.0, tint: Tuple::color(1.0, 1.0, 1.0) };
+        let wo = normal();
+        let mut rng = Rng::new(9);
+        let samples: Vec<BsdfSample> = (0..10000).filter_map(|_| bsdf.sample(wo, normal(), &mut rng)).collect();
+        let reflected = samples.iter().filter(|s| s.wi == normal()).count() as f32 / samples.len() as f32;
+        let refracted = samples.iter().find(|s| s.wi == -&normal()).unwrap();
+        assert!(
+            (reflected - 0.04).abs() < 0.01 && samples.iter().all(|s| s.delta) &&
+            f_equal(refracted.weight.x, 1.0 / 2.25) && bsdf.pdf(wo, -&normal(), normal()) == 0.0,
+            "The smooth dielectric was not correct. Reflected: {}", reflected
+        )
+    }
+
+    #[test]
+    fn rough_dielectric_from_outside_and_inside() {
+        let bsdf = Bsdf::Dielectric { ior: 1.5, roughness: 0.4, tint: Tuple::color(1.0, 1.0, 1.0) };
+        let outside = at_angle(0.6);
+        let inside = -&at_angle(0.3);
+        let integral = integrate_pdf(&bsdf, outside);
+        check_samples(&bsdf, outside);
+        check_samples(&bsdf, inside);
+        let f = bsdf.evaluate(outside, inside, normal());
+        assert!(
+            integral > 0.9 && integral <= 1.01 && f.x > 0.0 && bsdf.pdf(outside, inside, normal()) > 0.0,
+            "The rough dielectric was not correct. Integral: {}", integral
+        )
+    }
+}
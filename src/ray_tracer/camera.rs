@@ -0,0 +1,720 @@
+use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::world::World;
+use crate::ray_tracer::canvas::Canvas;
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::sampling::{SamplePattern, PixelFilter, AdaptiveSettings, AdaptiveStats, ApertureShape, contrast};
+use crate::ray_tracer::progress::{RenderOptions, Progress, CancellationToken};
+use crate::ray_tracer::accumulation::Accumulator;
+use crate::ray_tracer::integrator::Integrator;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct Camera {
+    // Horizontal size of the canvas in pixels.
+    pub hsize: usize,
+    // Vertical size of the canvas in pixels.
+    pub vsize: usize,
+    // Angle, in radians, describing how much the camera can see.
+    pub field_of_view: f32,
+    // Orientation of the world relative to the camera, usually built with Matrix::view_transform.
+    pub transform: Matrix,
+    pub samples_per_pixel: usize,
+    pub sample_pattern: SamplePattern,
+    pub filter: PixelFilter,
+    pub integrator: Integrator,
+    // Radius of the lens. 0 is a pinhole camera, where everything is in focus. Anything bigger
+    // blurs everything that isn't focal_distance away from the camera, the more the bigger it is.
+    pub aperture: f32,
+    // Distance from the camera to the plane that is in focus.
+    pub focal_distance: f32,
+    pub aperture_shape: ApertureShape,
+    // Rays are spread evenly over the times between the shutter opening and closing, so that
+    // objects that move in between are blurred. When both are the same nothing is blurred.
+    pub shutter_open: f32,
+    pub shutter_close: f32
+}
+
+// A rectangle of pixels rendered in one go by Camera::render_tiles.
+#[derive(Copy, Clone)]
+struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize
+}
+
+impl Camera {
+    // Defaults to a single sample through the center of every pixel.
+    pub fn new(hsize: usize, vsize: usize, field_of_view: f32) -> Self {
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: Matrix::identity(),
+            samples_per_pixel: 1,
+            sample_pattern: SamplePattern::Regular,
+            filter: PixelFilter::Box,
+            integrator: Integrator::Phong,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            aperture_shape: ApertureShape::Circle,
+            shutter_open: 0.0,
+            shutter_close: 0.0
+        }
+    }
+
+    // Size of a single pixel on the canvas, which sits one unit in front of the camera.
+    pub fn pixel_size(&self) -> f32 {
+        let (half_width, _) = self.half_extents();
+        (half_width * 2.0) / self.hsize as f32
+    }
+
+    // Half of the width and height of the canvas one unit in front of the camera.
+    fn half_extents(&self) -> (f32, f32) {
+        let half_view = (self.field_of_view / 2.0).tan();
+        let aspect = self.hsize as f32 / self.vsize as f32;
+        if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        }
+    }
+
+    // Ray from the camera through the center of the pixel (px, py).
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_for_point(px as f32 + 0.5, py as f32 + 0.5)
+    }
+
+    // Ray from the camera through an arbitrary point on the canvas, measured in pixels from the
+    // top left corner. (0.5, 0.5) is the center of the first pixel. The ray leaves from the center
+    // of the lens when the shutter opens.
+    pub fn ray_for_point(&self, x: f32, y: f32) -> Ray {
+        self.ray_through(&self.transform.inverse(), x, y, (0.0, 0.0))
+    }
+
+    // Same as ray_for_point, but from the point lens on the lens, relative to its center. The
+    // ray still goes through the point on the focal plane that the canvas point is in front of,
+    // so only things away from the focal plane move when the lens point does.
+    pub fn ray_for_lens_point(&self, x: f32, y: f32, lens: (f32, f32)) -> Ray {
+        self.ray_through(&self.transform.inverse(), x, y, lens)
+    }
+
+    // Same as ray_for_lens_point, but reuses an already inverted transform so that rendering
+    // doesn't have to invert the matrix for every ray.
+    fn ray_through(&self, inverse: &Matrix, x: f32, y: f32, lens: (f32, f32)) -> Ray {
+        let (half_width, half_height) = self.half_extents();
+        let pixel_size = self.pixel_size();
+        // The camera looks toward -z, so +x is to the left.
+        let world_x = half_width - x * pixel_size;
+        let world_y = half_height - y * pixel_size;
+
+        let (target, origin) = if lens == (0.0, 0.0) {
+            (Tuple::point(world_x, world_y, -1.0), Tuple::point(0.0, 0.0, 0.0))
+        } else {
+            let f = self.focal_distance;
+            (Tuple::point(world_x * f, world_y * f, -f), Tuple::point(lens.0, lens.1, 0.0))
+        };
+        let target = inverse * &target;
+        let origin = inverse * &origin;
+        let direction = (&target - &origin).norm();
+
+        Ray::with_time(origin, direction, self.shutter_open)
+    }
+
+    // Ray through a point on the canvas from a random point on the lens, at a random time while
+    // the shutter is open. A pinhole camera with an instant shutter doesn't use up any random
+    // numbers.
+    fn sample_ray(&self, inverse: &Matrix, x: f32, y: f32, rng: &mut Rng) -> Ray {
+        let lens = if self.aperture > 0.0 {
+            let (lx, ly) = self.aperture_shape.sample(rng.next_f32(), rng.next_f32());
+            (lx * self.aperture, ly * self.aperture)
+        } else {
+            (0.0, 0.0)
+        };
+        let mut ray = self.ray_through(inverse, x, y, lens);
+        if self.shutter_close != self.shutter_open {
+            ray.time = self.shutter_open + rng.next_f32() * (self.shutter_close - self.shutter_open);
+        }
+        ray
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        let inverse = self.transform.inverse();
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let color = self.render_pixel(world, &inverse, x, y);
+                image.write_pixel(x, y, color);
+            }
+        }
+        image
+    }
+
+    // Same image as render, split across `threads` threads.
+    pub fn render_threaded(&self, world: &World, threads: usize) -> Canvas {
+        let options = RenderOptions { threads, ..Default::default() };
+        let (image, _) = self.render_tiles(world, &options, &CancellationToken::new(), |_| {});
+        image
+    }
+
+    // Same image as render, split into square tiles that are shared out between threads. After
+    // every tile on_progress is called, from whichever thread finished it. The token is checked
+    // before each tile is started, so once it is cancelled the tiles in flight are finished and the
+    // rest of the canvas is left black. The returned Progress tells whether the image is complete.
+    pub fn render_tiles<F>(&self, world: &World, options: &RenderOptions, cancel: &CancellationToken,
+                           on_progress: F) -> (Canvas, Progress)
+        where F: Fn(&Progress) + Sync {
+        let inverse = self.transform.inverse();
+        let (done, progress) = self.run_tiles(options, cancel, on_progress, |x, y| {
+            self.render_pixel(world, &inverse, x, y)
+        });
+
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for (tile, pixels) in done {
+            for (i, color) in pixels.into_iter().enumerate() {
+                image.write_pixel(tile.x + i % tile.width, tile.y + i / tile.width, color);
+            }
+        }
+        (image, progress)
+    }
+
+    // Adds one pass of a progressive render to the accumulator: a single filter weighted sample
+    // per pixel, placed by sample_pattern.sample_for_pass. Tiles, progress and cancellation work
+    // like render_tiles, but a cancelled pass is thrown away so every pixel in the accumulator
+    // always has the same number of samples. Returns the progress of the pass.
+    pub fn render_pass<F>(&self, world: &World, accumulator: &mut Accumulator, options: &RenderOptions,
+                          cancel: &CancellationToken, on_progress: F) -> Progress
+        where F: Fn(&Progress) + Sync {
+        assert!(
+            accumulator.width == self.hsize && accumulator.height == self.vsize,
+            "The {}x{} accumulator doesn't match the {}x{} camera.",
+            accumulator.width, accumulator.height, self.hsize, self.vsize
+        );
+        let inverse = self.transform.inverse();
+        let pass = accumulator.passes;
+        let radius = self.filter.radius();
+        let (done, progress) = self.run_tiles(options, cancel, on_progress, |x, y| {
+            let pixel = (y * self.hsize + x) as u64;
+            let (u, v) = self.sample_pattern.sample_for_pass(pass, pixel);
+            let dx = (u - 0.5) * 2.0 * radius;
+            let dy = (v - 0.5) * 2.0 * radius;
+            // A different stream than the one that placed the sample, so paths don't follow it.
+            let mut rng = Rng::new(pixel.rotate_left(17) ^ pass as u64 ^ 0xA5A5_A5A5);
+            let ray = self.sample_ray(&inverse, x as f32 + 0.5 + dx, y as f32 + 0.5 + dy, &mut rng);
+            (self.integrator.color_at(world, &ray, &mut rng), self.filter.evaluate(dx, dy))
+        });
+
+        if progress.is_complete() {
+            for (tile, samples) in done {
+                for (i, (color, weight)) in samples.into_iter().enumerate() {
+                    accumulator.add_sample(tile.x + i % tile.width, tile.y + i / tile.width, color, weight);
+                }
+            }
+            accumulator.passes += 1;
+        }
+        progress
+    }
+
+    // Splits the canvas into tiles and shares them out between threads, calling shade for every
+    // pixel. Returns the finished tiles, in no particular order, with what shade returned for each of
+    // their pixels row by row.
+    fn run_tiles<T, F, S>(&self, options: &RenderOptions, cancel: &CancellationToken, on_progress: F,
+                          shade: S) -> (Vec<(Tile, Vec<T>)>, Progress)
+        where T: Send, F: Fn(&Progress) + Sync, S: Fn(usize, usize) -> T + Sync {
+        let size = options.tile_size.max(1);
+        let mut tiles = Vec::new();
+        for y in (0..self.vsize).step_by(size) {
+            for x in (0..self.hsize).step_by(size) {
+                tiles.push(Tile { x, y, width: size.min(self.hsize - x), height: size.min(self.vsize - y) });
+            }
+        }
+
+        let start = Instant::now();
+        let next_tile = AtomicUsize::new(0);
+        let progress = Mutex::new(Progress {
+            tiles_done: 0,
+            tiles_total: tiles.len(),
+            pixels_done: 0,
+            pixels_total: self.hsize * self.vsize,
+            elapsed: Duration::default()
+        });
+
+        let done = thread::scope(|scope| {
+            let workers: Vec<_> = (0..options.threads.max(1))
+                .map(|_| scope.spawn(|| {
+                    let mut done = Vec::new();
+                    while !cancel.is_cancelled() {
+                        let i = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if i >= tiles.len() {
+                            break;
+                        }
+                        let tile = tiles[i];
+                        let mut pixels = Vec::with_capacity(tile.width * tile.height);
+                        for y in tile.y..tile.y + tile.height {
+                            for x in tile.x..tile.x + tile.width {
+                                pixels.push(shade(x, y));
+                            }
+                        }
+                        done.push((tile, pixels));
+
+                        // The callback runs under the lock so that reports arrive in order.
+                        let mut p = progress.lock().expect("A render thread panicked.");
+                        p.tiles_done += 1;
+                        p.pixels_done += tile.width * tile.height;
+                        p.elapsed = start.elapsed();
+                        on_progress(&p);
+                    }
+                    done
+                }))
+                .collect();
+            workers.into_iter().flat_map(|w| w.join().expect("A render thread panicked.")).collect()
+        });
+
+        let mut result = progress.into_inner().expect("A render thread panicked.");
+        result.elapsed = start.elapsed();
+        (done, result)
+    }
+
+    // Traces one ray through the center of every pixel, then goes back and recursively subdivides
+    // only the pixels that differ too much from one of their neighbours. Flat areas cost one ray per
+    // pixel, while edges get up to 4^max_depth extra rays.
+    pub fn render_adaptive(&self, world: &World, settings: &AdaptiveSettings) -> (Canvas, AdaptiveStats) {
+        let inverse = self.transform.inverse();
+        let mut stats = AdaptiveStats::default();
+        let mut base = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let mut rng = Rng::new((y * self.hsize + x) as u64);
+                let ray = self.sample_ray(&inverse, x as f32 + 0.5, y as f32 + 0.5, &mut rng);
+                base.write_pixel(x, y, self.integrator.color_at(world, &ray, &mut rng));
+            }
+        }
+        stats.primary_rays = self.hsize * self.vsize;
+
+        // Contrast is always measured against the unrefined image, so the result doesn't depend on
+        // the order that the pixels are refined in.
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for (x, y, &color) in base.iter() {
+            let neighbours = [
+                x.checked_sub(1).and_then(|nx| base.get(nx, y)),
+                base.get(x + 1, y),
+                y.checked_sub(1).and_then(|ny| base.get(x, ny)),
+                base.get(x, y + 1)
+            ];
+            let needs_refinement = settings.max_depth > 0 && neighbours.iter()
+                .flatten()
+                .any(|n| contrast(color, **n) > settings.threshold);
+
+            let result = if needs_refinement {
+                stats.refined_pixels += 1;
+                // Offset from the seeds used for the unrefined image so the extra rays are independent.
+                let mut rng = Rng::new((self.hsize * self.vsize + y * self.hsize + x) as u64);
+                self.refine(world, &inverse, (x as f32, y as f32), 1.0, color, 1, settings, &mut stats, &mut rng)
+            } else {
+                color
+            };
+            image.write_pixel(x, y, result);
+        }
+
+        (image, stats)
+    }
+
+    // Splits the square with its top left corner at `corner` into quadrants and traces a ray
+    // through the center of each. Quadrants that still differ from the parent's center color are
+    // split again until max_depth is reached. Returns the average of the quadrants.
+    #[allow(clippy::too_many_arguments)]
+    fn refine(&self, world: &World, inverse: &Matrix, corner: (f32, f32), size: f32, center: Tuple,
+              depth: u32, settings: &AdaptiveSettings, stats: &mut AdaptiveStats, rng: &mut Rng) -> Tuple {
+        let half = size / 2.0;
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for &(qx, qy) in [(0.0, 0.0), (half, 0.0), (0.0, half), (half, half)].iter() {
+            let quadrant = (corner.0 + qx, corner.1 + qy);
+            let ray = self.sample_ray(inverse, quadrant.0 + half / 2.0, quadrant.1 + half / 2.0, rng);
+            let mut color = self.integrator.color_at(world, &ray, rng);
+            stats.extra_rays += 1;
+            if depth < settings.max_depth && contrast(color, center) > settings.threshold {
+                color = self.refine(world, inverse, quadrant, half, color, depth + 1, settings, stats, rng);
+            }
+            sum = &sum + &color;
+        }
+
+        &sum * 0.25
+    }
+
+    // Traces samples_per_pixel rays spread over the filter's footprint and returns their filter
+    // weighted average. The random numbers are seeded by the pixel so renders are reproducible.
+    fn render_pixel(&self, world: &World, inverse: &Matrix, px: usize, py: usize) -> Tuple {
+        let mut rng = Rng::new((py * self.hsize + px) as u64);
+        let radius = self.filter.radius();
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        let mut plain_sum = Tuple::color(0.0, 0.0, 0.0);
+        let mut weight_sum = 0.0;
+        let mut abs_weight_sum = 0.0;
+        let mut count = 0;
+        for (u, v) in self.sample_pattern.generate(self.samples_per_pixel.max(1), &mut rng) {
+            let dx = (u - 0.5) * 2.0 * radius;
+            let dy = (v - 0.5) * 2.0 * radius;
+            let weight = self.filter.evaluate(dx, dy);
+            if weight == 0.0 {
+                continue;
+            }
+            let ray = self.sample_ray(inverse, px as f32 + 0.5 + dx, py as f32 + 0.5 + dy, &mut rng);
+            let color = self.integrator.color_at(world, &ray, &mut rng);
+            sum = &sum + &(&color * weight);
+            plain_sum = &plain_sum + &color;
+            weight_sum += weight;
+            abs_weight_sum += weight.abs();
+            count += 1;
+        }
+
+        filtered_average(sum, weight_sum, abs_weight_sum, plain_sum, count)
+    }
+}
+
+// Mitchell's negative lobes can cancel out most of the weight when a pixel has few samples, and
+// dividing by what's left would blow the pixel up. The plain mean of the samples is used instead
+// when less than a tenth of the weight survives. Filters without negative weights never get there.
+fn filtered_average(sum: Tuple, weight_sum: f32, abs_weight_sum: f32, plain_sum: Tuple, count: usize) -> Tuple {
+    if count == 0 {
+        sum
+    } else if weight_sum <= 0.1 * abs_weight_sum {
+        &plain_sum * (1.0 / count as f32)
+    } else {
+        &sum * (1.0 / weight_sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+    use crate::ray_tracer::matrix::RotationAxis;
+    use crate::ray_tracer::motion::Motion;
+    use crate::ray_tracer::sphere::Sphere;
+    use crate::ray_tracer::light::Light;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn construct_camera() {
+        let c = Camera::new(160, 120, PI / 2.0);
+        assert!(
+            c.hsize == 160 && c.vsize == 120 && c.field_of_view == PI / 2.0 &&
+            c.transform == Matrix::identity() && c.samples_per_pixel == 1 && c.aperture == 0.0,
+            "The camera was not constructed correctly."
+        )
+    }
+
+    #[test]
+    fn pixel_size_horizontal_canvas() {
+        let c = Camera::new(200, 125, PI / 2.0);
+        assert!(
+            f_equal(c.pixel_size(), 0.01),
+            "The pixel size was {}, expected 0.01.", c.pixel_size()
+        )
+    }
+
+    #[test]
+    fn pixel_size_vertical_canvas() {
+        let c = Camera::new(125, 200, PI / 2.0);
+        assert!(
+            f_equal(c.pixel_size(), 0.01),
+            "The pixel size was {}, expected 0.01.", c.pixel_size()
+        )
+    }
+
+    #[test]
+    fn ray_through_center_of_canvas() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_for_pixel(100, 50);
+        assert!(
+            r.origin == Tuple::point(0.0, 0.0, 0.0) && r.direction == Tuple::vector(0.0, 0.0, -1.0),
+            "The ray through the center of the canvas was not correct."
+        )
+    }
+
+    #[test]
+    fn ray_through_corner_of_canvas() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_for_pixel(0, 0);
+        assert!(
+            r.origin == Tuple::point(0.0, 0.0, 0.0) && r.direction == Tuple::vector(0.66519, 0.33259, -0.66851),
+            "The ray through the corner of the canvas was not correct. Result: {:?}", r.direction
+        )
+    }
+
+    #[test]
+    fn ray_when_camera_is_transformed() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.transform = &Matrix::rotation(PI / 4.0, RotationAxis::Y) * &Matrix::translation(0.0, -2.0, 5.0);
+        let r = c.ray_for_pixel(100, 50);
+        assert!(
+            r.origin == Tuple::point(0.0, 2.0, -5.0) &&
+            r.direction == Tuple::vector(2.0_f32.sqrt() / 2.0, 0.0, -2.0_f32.sqrt() / 2.0),
+            "The ray from the transformed camera was not correct."
+        )
+    }
+
+    #[test]
+    fn lens_rays_meet_on_focal_plane() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.aperture = 0.5;
+        c.focal_distance = 4.0;
+        // Where a ray crosses the focal plane, 4 units down -z.
+        let on_focal_plane = |r: &Ray| &r.origin + &(&r.direction * (-4.0 / r.direction.z));
+        let expected = on_focal_plane(&c.ray_for_point(30.0, 20.0));
+        let meet = [(0.5, 0.0), (-0.3, 0.2), (0.0, -0.45)].iter().all(|lens| {
+            let r = c.ray_for_lens_point(30.0, 20.0, *lens);
+            r.origin == Tuple::point(lens.0, lens.1, 0.0) && on_focal_plane(&r) == expected
+        });
+        assert!(
+            meet,
+            "Rays from every point on the lens should meet where the center ray crosses the focal plane."
+        )
+    }
+
+    #[test]
+    fn aperture_blurs_out_of_focus_edges() {
+        // Focused far behind the sphere, so its silhouette spreads into the pixels around it.
+        let w = World::new();
+        let mut c = Camera::new(21, 21, PI / 3.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0));
+        c.samples_per_pixel = 8;
+        let sharp = c.render(&w);
+        c.aperture = 1.0;
+        c.focal_distance = 20.0;
+        c.aperture_shape = ApertureShape::Polygon { blades: 6, rotation: 0.0 };
+        let blurred = c.render(&w);
+        let black = Tuple::color(0.0, 0.0, 0.0);
+        let background_pixels = (0..21).filter(|x| sharp.pixel_at(*x, 10) == black).count();
+        let blurred_background_pixels = (0..21).filter(|x| blurred.pixel_at(*x, 10) == black).count();
+        assert!(
+            blurred_background_pixels < background_pixels,
+            "Defocus should spread the sphere over more pixels. Black pixels: {} sharp, {} blurred",
+            background_pixels, blurred_background_pixels
+        )
+    }
+
+    #[test]
+    fn shutter_blurs_moving_objects() {
+        // A flat white sphere crossing in front of a single pixel camera covers the pixel for half
+        // of the time between x = -2 and x = 2.
+        let mut w = World::empty();
+        w.lights.push(Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0)));
+        let mut s = Sphere::new();
+        s.motion = Some(Motion::new(Matrix::translation(-2.0, 0.0, 0.0), Matrix::translation(2.0, 0.0, 0.0)));
+        s.material.ambient = 1.0;
+        s.material.diffuse = 0.0;
+        s.material.specular = 0.0;
+        w.objects.push(Box::new(s));
+        let mut c = Camera::new(1, 1, 0.01);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0));
+        c.samples_per_pixel = 400;
+        let still = c.render(&w).pixel_at(0, 0);
+        c.shutter_close = 1.0;
+        let blurred = c.render(&w).pixel_at(0, 0);
+        assert!(
+            still == Tuple::color(0.0, 0.0, 0.0) && (blurred.x - 0.5).abs() < 0.05,
+            "The sphere should cover the pixel for half of the shutter time. Result: {:?}", blurred
+        )
+    }
+
+    #[test]
+    fn render_world_with_camera() {
+        let w = World::new();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        c.transform = Matrix::view_transform(from, to, up);
+        let image = c.render(&w);
+        let result = image.pixel_at(5, 5);
+        assert!(
+            result == Tuple::color(0.38066, 0.47583, 0.2855),
+            "The rendered pixel was not correct. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn supersampling_smooths_silhouette() {
+        let w = World::new();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        let single = c.render(&w);
+        c.samples_per_pixel = 16;
+        c.sample_pattern = SamplePattern::Jittered;
+        c.filter = PixelFilter::Tent;
+        let multi = c.render(&w);
+        // The darkest the sphere gets is its ambient color (0.08 in red). Anything between that and
+        // black has to be a blend of the sphere and the background along its edge.
+        let single_partial = single.iter().filter(|(_, _, p)| p.x > 0.0 && p.x < 0.08).count();
+        let multi_partial = multi.iter().filter(|(_, _, p)| p.x > 0.0 && p.x < 0.08).count();
+        assert!(
+            multi_partial > single_partial,
+            "Supersampling should produce partially covered pixels along the silhouette."
+        )
+    }
+
+    #[test]
+    fn adaptive_render_skips_flat_pixels() {
+        let w = World::empty();
+        let c = Camera::new(10, 10, PI / 2.0);
+        let (image, stats) = c.render_adaptive(&w, &AdaptiveSettings::default());
+        assert!(
+            stats.primary_rays == 100 && stats.extra_rays == 0 && stats.refined_pixels == 0 &&
+            image.pixel_at(5, 5) == Tuple::color(0.0, 0.0, 0.0),
+            "A flat image should not be refined. Stats: {:?}", stats
+        )
+    }
+
+    #[test]
+    fn adaptive_render_refines_edges_only() {
+        let w = World::new();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        let (image, stats) = c.render_adaptive(&w, &AdaptiveSettings::default());
+        assert!(
+            stats.refined_pixels > 0 && stats.refined_pixels < 121 && stats.extra_rays >= 4 * stats.refined_pixels &&
+            image.pixel_at(0, 0) == Tuple::color(0.0, 0.0, 0.0),
+            "Only the pixels along the silhouette should be refined. Stats: {:?}", stats
+        )
+    }
+
+    #[test]
+    fn adaptive_render_with_zero_depth() {
+        let w = World::new();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        let settings = AdaptiveSettings { max_depth: 0, ..Default::default() };
+        let (image, stats) = c.render_adaptive(&w, &settings);
+        assert!(
+            stats.extra_rays == 0 && image.pixels == c.render(&w).pixels,
+            "A max depth of 0 should render one ray per pixel."
+        )
+    }
+
+    #[test]
+    fn supersampled_render_is_reproducible() {
+        let w = World::new();
+        let mut c = Camera::new(5, 5, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        c.samples_per_pixel = 4;
+        c.sample_pattern = SamplePattern::Halton;
+        c.filter = PixelFilter::Gaussian;
+        let a = c.render(&w);
+        let b = c.render(&w);
+        assert!(
+            a.pixels == b.pixels,
+            "Rendering the same scene twice should produce the same image."
+        )
+    }
+
+    #[test]
+    fn threaded_render_matches_single_threaded() {
+        let w = World::new();
+        let mut c = Camera::new(9, 7, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        c.samples_per_pixel = 4;
+        c.sample_pattern = SamplePattern::Jittered;
+        let single = c.render(&w);
+        let threaded = c.render_threaded(&w, 3);
+        assert!(
+            single.pixels == threaded.pixels,
+            "Rendering with several threads should produce the same image as a single thread."
+        )
+    }
+
+    #[test]
+    fn tiled_render_reports_progress() {
+        let w = World::new();
+        let mut c = Camera::new(10, 7, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        let reports = Mutex::new(Vec::new());
+        let options = RenderOptions::new(2, 4);
+        let (image, progress) = c.render_tiles(&w, &options, &CancellationToken::new(), |p| {
+            reports.lock().unwrap().push(*p);
+        });
+        let reports = reports.into_inner().unwrap();
+        // 3 columns and 2 rows of tiles, the last ones clipped to the canvas.
+        assert!(
+            progress.is_complete() && progress.tiles_total == 6 && reports.len() == 6 &&
+            reports.windows(2).all(|r| r[0].pixels_done < r[1].pixels_done) &&
+            reports[5].pixels_done == 70 && image.pixels == c.render(&w).pixels,
+            "The tiled render did not report its progress correctly. Reports: {:?}", reports
+        )
+    }
+
+    #[test]
+    fn cancelled_render_returns_partial_canvas() {
+        let w = World::new();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        let token = CancellationToken::new();
+        // Cancel as soon as the first tile, the top left quarter, is finished.
+        let (image, progress) = c.render_tiles(&w, &RenderOptions::new(1, 6), &token, |_| token.cancel());
+        let full = c.render(&w);
+        assert!(
+            !progress.is_complete() && progress.tiles_done == 1 && progress.pixels_done == 36 &&
+            image.pixel_at(5, 5) == full.pixel_at(5, 5) && image.pixel_at(6, 6) == Tuple::color(0.0, 0.0, 0.0),
+            "Cancelling should return the tiles that were already finished. Progress: {:?}", progress
+        )
+    }
+
+    #[test]
+    fn progressive_passes_converge_on_supersampled_render() {
+        let w = World::new();
+        let mut c = Camera::new(6, 6, PI / 2.0);
+        c.transform = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0),
+                                             Tuple::vector(0.0, 1.0, 0.0));
+        c.sample_pattern = SamplePattern::Halton;
+        c.samples_per_pixel = 8;
+        let mut accumulator = Accumulator::new(6, 6);
+        for _ in 0..8 {
+            c.render_pass(&w, &mut accumulator, &RenderOptions::new(2, 4), &CancellationToken::new(), |_| {});
+        }
+        // 8 Halton passes draw the same samples as 8 Halton samples per pixel.
+        let expected = c.render(&w);
+        let result = accumulator.average();
+        assert!(
+            accumulator.passes == 8 && result.pixels == expected.pixels,
+            "8 progressive passes should match a render with 8 samples per pixel."
+        )
+    }
+
+    #[test]
+    fn cancelling_filter_weights_fall_back_to_the_mean() {
+        let (a, b) = (Tuple::color(1.0, 1.0, 1.0), Tuple::color(0.0, 0.5, 0.0));
+        let sum = &(&a * 0.5) + &(&b * -0.49);
+        let result = filtered_average(sum, 0.01, 0.99, &a + &b, 2);
+        let weighted = filtered_average(&(&a * 0.5) + &(&b * 0.25), 0.75, 0.75, &a + &b, 2);
+        assert!(
+            result == Tuple::color(0.5, 0.75, 0.5) && weighted == Tuple::color(2.0 / 3.0, 5.0 / 6.0, 2.0 / 3.0),
+            "Weights that cancel out should give the plain mean. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn cancelled_pass_is_discarded() {
+        let w = World::new();
+        let c = Camera::new(8, 8, PI / 2.0);
+        let mut accumulator = Accumulator::new(8, 8);
+        let token = CancellationToken::new();
+        let progress = c.render_pass(&w, &mut accumulator, &RenderOptions::new(1, 4), &token, |_| token.cancel());
+        assert!(
+            !progress.is_complete() && accumulator.passes == 0 && accumulator.weights.iter().all(|w| *w == 0.0),
+            "A cancelled pass should not change the accumulator."
+        )
+    }
+}
@@ -1,4 +1,24 @@
 use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::tone_mapping::ExportSettings;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum CanvasError {
+    OutOfBounds { x: usize, y: usize, width: usize, height: usize }
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanvasError::OutOfBounds { x, y, width, height } => {
+                write!(f, "Pixel ({}, {}) is outside of the {}x{} canvas.", x, y, width, height)
+            }
+        }
+    }
+}
+
+impl Error for CanvasError {}
 
 pub struct Canvas {
     pub width: usize,
@@ -16,18 +36,67 @@ impl Canvas {
         }
     }
 
-    // TODO: Add error handling for out of bounds
+    // Unchecked fast path for render loops that already know their coordinates are valid. An x
+    // past the width is only caught in debug builds, in release it reads from the next row.
     pub fn pixel_at(&self, x: usize, y: usize) -> Tuple {
         let i = self.get_index(x, y);
         self.pixels[i]
     }
 
-    // TODO: Add error handling for out of bounds
+    // Unchecked fast path, see pixel_at.
     pub fn write_pixel(&mut self, x: usize, y: usize, color: Tuple) {
         let i = self.get_index(x, y);
         self.pixels[i] = color
     }
 
+    pub fn in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    // Returns None instead of wrapping or panicking when (x, y) is outside of the canvas.
+    pub fn get(&self, x: usize, y: usize) -> Option<&Tuple> {
+        if self.in_bounds(x, y) {
+            self.pixels.get(self.get_index(x, y))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Tuple> {
+        if self.in_bounds(x, y) {
+            let i = self.get_index(x, y);
+            self.pixels.get_mut(i)
+        } else {
+            None
+        }
+    }
+
+    pub fn try_pixel_at(&self, x: usize, y: usize) -> Result<Tuple, CanvasError> {
+        self.get(x, y).copied().ok_or_else(|| self.out_of_bounds(x, y))
+    }
+
+    pub fn try_write_pixel(&mut self, x: usize, y: usize, color: Tuple) -> Result<(), CanvasError> {
+        let error = self.out_of_bounds(x, y);
+        match self.get_mut(x, y) {
+            Some(pixel) => {
+                *pixel = color;
+                Ok(())
+            },
+            None => Err(error)
+        }
+    }
+
+    // Iterates over every pixel as (x, y, color), row by row starting at the top left.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &Tuple)> {
+        let width = self.width;
+        self.pixels.iter().enumerate().map(move |(i, p)| (i % width, i / width, p))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut Tuple)> {
+        let width = self.width;
+        self.pixels.iter_mut().enumerate().map(move |(i, p)| (i % width, i / width, p))
+    }
+
     // TODO: Add ability to draw a canvas to a browser with web assembly.
     pub fn canvas_to_ppm(&self) -> String {
         let mut header = create_ppm_header(self.width, self.height);
@@ -37,9 +106,24 @@ impl Canvas {
         header
     }
 
+    // Tone maps and encodes the linear pixels on the way out. The stored pixels are left untouched.
+    pub fn canvas_to_ppm_with_settings(&self, settings: &ExportSettings) -> String {
+        let mapped: Vec<Tuple> = self.pixels.iter().map(|p| settings.apply(*p)).collect();
+        let mut header = create_ppm_header(self.width, self.height);
+        let body = create_ppm_body(&mapped, self.width);
+        header.push_str(&body);
+
+        header
+    }
+
     fn get_index(&self, x: usize, y: usize) ->  usize {
+        debug_assert!(x < self.width, "x = {} is past the canvas width of {}", x, self.width);
         y * self.width + x
     }
+
+    fn out_of_bounds(&self, x: usize, y: usize) -> CanvasError {
+        CanvasError::OutOfBounds { x, y, width: self.width, height: self.height }
+    }
 }
 
 fn create_ppm_header(width: usize, height: usize) -> String {
@@ -48,7 +132,7 @@ fn create_ppm_header(width: usize, height: usize) -> String {
 
 // TODO: Refactor this function
 // TODO: Comment this function
-fn create_ppm_body(pixels: &Vec<Tuple>, width: usize) -> String {
+fn create_ppm_body(pixels: &[Tuple], width: usize) -> String {
     
     let color_scale: f32 = 255.0;
 
@@ -63,36 +147,25 @@ fn create_ppm_body(pixels: &Vec<Tuple>, width: usize) -> String {
         .map(|i| {
             (i * color_scale).round() as i32
         })
-        .map(|i| {
-            if i > 255 {
-                255
-            }
-            else if i < 0 {
-                0
-            }
-            else {
-                i
-            }
-        }).collect();
+        .map(|i| i.clamp(0, 255))
+        .collect();
 
     let mut body = String::new();
-    let mut i_width = 1;
     let mut i_chars = 0;
     let mut inserted_whitespace = false;
-    for e in trans {
+    for (i_width, e) in (1..).zip(trans) {
         let mut insert_val: String = "".to_owned();
         let val = format!("{}", e);
         // val.len() + 1: the +1 is to account for the white space that would need to be added
         if i_chars + val.len() + 1 > 70 {
-            insert_val.push_str("\n");
+            insert_val.push('\n');
             insert_val.push_str(&val);
-            i_chars = 0;
-            i_chars = i_chars + val.len();
+            i_chars = val.len();
         }
         else if i_width % (width * 3) == 0 {
-            insert_val.push_str(" ");
+            insert_val.push(' ');
             insert_val.push_str(&val);
-            insert_val.push_str("\n");
+            insert_val.push('\n');
             inserted_whitespace = true;
             i_chars = 0;
         }
@@ -102,20 +175,20 @@ fn create_ppm_body(pixels: &Vec<Tuple>, width: usize) -> String {
                 inserted_whitespace = false;
             }
             else {
-                insert_val.push_str(" ");
+                insert_val.push(' ');
                 insert_val.push_str(&val);
             }
-            i_chars = i_chars + insert_val.len();
+            i_chars += insert_val.len();
         }
         body.push_str(&insert_val);
-        i_width = i_width + 1;
     }
-    body.to_string()
+    body
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ray_tracer::tone_mapping::{ToneMapOperator, TransferFunction};
 
     #[test]
     fn create_canvas() {
@@ -145,6 +218,62 @@ mod tests {
             Expected: R = 1.0, G = 1.0, B = 1.0", p.x, p.y, p.z)
     }
 
+    #[test]
+    fn get_pixel_out_of_bounds() {
+        let c = Canvas::new(10, 20);
+        assert!(
+            c.get(9, 19).is_some() && c.get(10, 0).is_none() && c.get(0, 20).is_none(),
+            "Pixels outside of the canvas should return None."
+        )
+    }
+
+    #[test]
+    fn get_mut_updates_pixel() {
+        let mut c = Canvas::new(2, 2);
+        if let Some(p) = c.get_mut(1, 1) {
+            *p = Tuple::color(0.5, 0.5, 0.5);
+        }
+        assert!(
+            c.pixel_at(1, 1) == Tuple::color(0.5, 0.5, 0.5) && c.get_mut(2, 1).is_none(),
+            "get_mut did not return the correct pixel."
+        )
+    }
+
+    #[test]
+    fn try_write_pixel_out_of_bounds() {
+        let mut c = Canvas::new(10, 20);
+        let result = c.try_write_pixel(10, 0, Tuple::color(1.0, 1.0, 1.0));
+        assert!(
+            result == Err(CanvasError::OutOfBounds { x: 10, y: 0, width: 10, height: 20 }) &&
+            c.pixel_at(0, 1) == Tuple::color(0.0, 0.0, 0.0),
+            "Writing past the width should fail instead of wrapping into the next row."
+        )
+    }
+
+    #[test]
+    fn try_pixel_at_in_bounds() {
+        let mut c = Canvas::new(3, 3);
+        c.try_write_pixel(2, 1, Tuple::color(1.0, 0.0, 0.0)).unwrap();
+        assert!(
+            c.try_pixel_at(2, 1) == Ok(Tuple::color(1.0, 0.0, 0.0)) && c.try_pixel_at(1, 3).is_err(),
+            "try_pixel_at did not return the correct result."
+        )
+    }
+
+    #[test]
+    fn iterate_pixels_with_coordinates() {
+        let mut c = Canvas::new(3, 2);
+        for (x, y, p) in c.iter_mut() {
+            *p = Tuple::color(x as f32, y as f32, 0.0);
+        }
+        let coords: Vec<(usize, usize)> = c.iter().map(|(x, y, _)| (x, y)).collect();
+        assert!(
+            coords == vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)] &&
+            c.pixel_at(2, 1) == Tuple::color(2.0, 1.0, 0.0),
+            "The pixel iterators did not visit the pixels in row order. Result: {:?}", coords
+        )
+    }
+
     #[test]
     fn construct_ppm_header() {
         let c = Canvas::new(5, 3);
@@ -185,6 +314,18 @@ mod tests {
             "\nResult: PPM = \n{:?}\nExpected: PPM = \n{:?}", ppm, exp);
     }
 
+    #[test]
+    fn export_with_settings_leaves_canvas_linear() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Tuple::color(0.5, 1.0, 4.0));
+        let settings = ExportSettings::new(ToneMapOperator::Clamp, 0.0, TransferFunction::Srgb);
+        let ppm = c.canvas_to_ppm_with_settings(&settings);
+        assert!(
+            ppm == "P3\n1 1\n255\n188 255 255\n" && c.pixel_at(0, 0) == Tuple::color(0.5, 1.0, 4.0),
+            "\nResult: PPM = \n{:?}", ppm
+        )
+    }
+
     #[test]
     fn construct_ppm_body_trailing_newline() {
         let c = Canvas::new(5, 3);
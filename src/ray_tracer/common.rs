@@ -1,11 +1,12 @@
+pub const EPSILON: f32 = 0.00001;
+
+// Distance a point is nudged along the surface normal before casting secondary rays from it.
+// EPSILON is too small for f32 and lets the surface shadow itself ("acne").
+pub const SHADOW_BIAS: f32 = 0.001;
+
 pub fn f_equal(a: f32, b: f32) -> bool {
-    const EPSILON: f32 = 0.00001;
     let diff: f32 = a - b;
-    if diff.abs() < EPSILON {
-        true
-    } else {
-        false
-    }
+    diff.abs() < EPSILON
 }
 
 #[cfg(test)]
@@ -17,8 +18,8 @@ mod tests {
         let a: f32 = 1.222225;
         let b: f32 = 1.222226;
         let x: bool = f_equal(a, b);
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The values {} and {} should be equal = true, value was {}", a, b, x
         )
     }
@@ -27,8 +28,8 @@ mod tests {
     fn values_are_not_equal() {
         let a: f32 = 0.00001;
         let b: f32 = 0.000021;
-        assert_ne!(
-            true, f_equal(a, b),
+        assert!(
+            !f_equal(a, b),
             "The values {} and {} should be equal = false, value was {}", a, b, f_equal(a, b)
         )
     }
@@ -0,0 +1,143 @@
+use crate::ray_tracer::matrix::{Matrix, MatrixError};
+use crate::ray_tracer::quaternion::Quaternion;
+use crate::ray_tracer::tuple::Tuple;
+
+// A transform split into the steps that build it: scale, then shear, then rotate, then translate.
+// Motion blends keyframes through these, so that rotations don't shrink partway.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decomposition {
+    pub translation: Tuple,
+    pub rotation: Quaternion,
+    // Negative along z when the transform mirrors things.
+    pub scale: Tuple,
+    // The xy, xz and yz amounts of Matrix::shearing, which are all a shear needs once it comes
+    // after the scale and before the rotation.
+    pub shear: Tuple
+}
+
+impl Decomposition {
+    // Without a shear.
+    pub fn new(translation: Tuple, rotation: Quaternion, scale: Tuple) -> Self {
+        Decomposition { translation, rotation, scale, shear: Tuple::vector(0.0, 0.0, 0.0) }
+    }
+
+    // Errors if the matrix isn't affine or squashes space flat, since neither can be split up.
+    pub fn from_matrix(matrix: &Matrix) -> Result<Self, MatrixError> {
+        let m = &matrix.value;
+        if m[3] != [0.0, 0.0, 0.0, 1.0] {
+            return Err(MatrixError::NotAffine);
+        }
+        let column = |c: usize| Tuple::vector(m[0][c], m[1][c], m[2][c]);
+        let (c0, c1, c2) = (column(0), column(1), column(2));
+
+        // Gram-Schmidt: each column, less its parts along the columns before it, is a scaled axis
+        // of the rotation, and the parts taken away are the shear.
+        let sx = c0.mag();
+        if sx < 1.0e-6 {
+            return Err(MatrixError::Singular);
+        }
+        let q0 = &c0 / sx;
+        let xy = &q0 * &c1;
+        let rest = &c1 - &(&q0 * xy);
+        let sy = rest.mag();
+        if sy < 1.0e-6 {
+            return Err(MatrixError::Singular);
+        }
+        let q1 = &rest / sy;
+        let (xz, yz) = (&q0 * &c2, &q1 * &c2);
+        let rest = &(&c2 - &(&q0 * xz)) - &(&q1 * yz);
+        let mut sz = rest.mag();
+        if sz < 1.0e-6 {
+            return Err(MatrixError::Singular);
+        }
+        let mut q2 = &rest / sz;
+        // A rotation can't mirror, so a mirror is left to the z scale.
+        if &q0.cross(q1) * &q2 < 0.0 {
+            sz = -sz;
+            q2 = &q2 * -1.0;
+        }
+
+        let rotation = Matrix::new([
+            [q0.x, q1.x, q2.x, 0.0],
+            [q0.y, q1.y, q2.y, 0.0],
+            [q0.z, q1.z, q2.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ]);
+        Ok(Decomposition {
+            translation: Tuple::vector(m[0][3], m[1][3], m[2][3]),
+            rotation: Quaternion::from_matrix(&rotation),
+            scale: Tuple::vector(sx, sy, sz),
+            shear: Tuple::vector(xy / sy, xz / sz, yz / sz)
+        })
+    }
+
+    pub fn to_matrix(&self) -> Matrix {
+        let (s, h, t) = (&self.scale, &self.shear, &self.translation);
+        Matrix::identity()
+            .scale(s.x, s.y, s.z)
+            .shear(h.x, h.y, 0.0, h.z, 0.0, 0.0)
+            .then(&self.rotation.to_matrix())
+            .translate(t.x, t.y, t.z)
+    }
+}
+
+impl Default for Decomposition {
+    fn default() -> Self {
+        Decomposition::new(Tuple::vector(0.0, 0.0, 0.0), Quaternion::identity(), Tuple::vector(1.0, 1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decomposes_into_its_steps() {
+        let rotation = Quaternion::from_euler(0.4, -1.2, 2.0);
+        let matrix = Matrix::identity()
+            .scale(2.0, 0.5, 3.0)
+            .shear(0.3, -0.2, 0.0, 0.7, 0.0, 0.0)
+            .then(&rotation.to_matrix())
+            .translate(1.0, -2.0, 5.0);
+        let d = matrix.decompose().unwrap();
+        assert!(
+            d.translation == Tuple::vector(1.0, -2.0, 5.0) && d.scale == Tuple::vector(2.0, 0.5, 3.0) &&
+            d.shear == Tuple::vector(0.3, -0.2, 0.7) && d.rotation == rotation && d.to_matrix() == matrix,
+            "The matrix was not decomposed into the steps that built it. Result: {:?}", d
+        )
+    }
+
+    #[test]
+    fn any_shear_recomposes() {
+        // Shears below the diagonal come back as a different rotation, shear and scale.
+        let matrix = Matrix::identity()
+            .shear(0.0, 0.0, 0.5, 0.0, -0.4, 0.9)
+            .rotate_y(0.8)
+            .translate(0.0, 3.0, 0.0);
+        assert!(
+            matrix.decompose().unwrap().to_matrix() == matrix,
+            "The decomposition of a sheared matrix should build the same matrix again."
+        )
+    }
+
+    #[test]
+    fn mirror_goes_to_the_z_scale() {
+        let matrix = Matrix::identity().scale(-1.0, 1.0, 1.0).rotate_x(0.3);
+        let d = matrix.decompose().unwrap();
+        assert!(
+            d.scale.z < 0.0 && d.to_matrix() == matrix && Decomposition::default().to_matrix() == Matrix::identity(),
+            "A mirrored matrix should decompose with a negative z scale. Result: {:?}", d
+        )
+    }
+
+    #[test]
+    fn flat_or_projective_matrix_is_an_error() {
+        let mut projective = Matrix::identity();
+        projective.value[3][2] = 1.0;
+        assert!(
+            Matrix::scaling(1.0, 0.0, 1.0).decompose() == Err(MatrixError::Singular) &&
+            projective.decompose() == Err(MatrixError::NotAffine),
+            "Matrices that can't be split up should give an error."
+        )
+    }
+}
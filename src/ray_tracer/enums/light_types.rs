@@ -0,0 +1,10 @@
+use crate::ray_tracer::light::AreaLight;
+use crate::ray_tracer::tuple::Tuple;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightTypes {
+    Point,
+    Area(AreaLight),
+    Directional { direction: Tuple },
+    Spot { direction: Tuple, inner_angle: f32, outer_angle: f32 }
+}
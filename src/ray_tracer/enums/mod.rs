@@ -1 +1,2 @@
-pub mod object_types;
\ No newline at end of file
+pub mod object_types;
+pub mod light_types;
\ No newline at end of file
@@ -1,4 +1,7 @@
+use crate::ray_tracer::tuple::Tuple;
+
 #[derive(PartialEq)]
 pub enum ObjectTypes {
-    Sphere
+    Sphere,
+    Triangle { p1: Tuple, p2: Tuple, p3: Tuple }
 }
\ No newline at end of file
@@ -0,0 +1,270 @@
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::canvas::Canvas;
+use crate::ray_tracer::sampling::{uniform_sphere, Distribution2D};
+use crate::ray_tracer::image_format::read_image;
+use std::f32::consts::PI;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The light arriving from far away, seen by rays that miss every object.
+pub enum Background {
+    Solid(Tuple),
+    // Blends from bottom, straight down, to top, straight up.
+    Gradient { bottom: Tuple, top: Tuple },
+    Map(Box<EnvironmentMap>)
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Tuple::color(0.0, 0.0, 0.0))
+    }
+}
+
+impl Background {
+    // The light coming from direction, which must be normalized.
+    pub fn color(&self, direction: Tuple) -> Tuple {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.y + 1.0);
+                &(bottom * (1.0 - t)) + &(top * t)
+            },
+            Background::Map(map) => map.color(direction)
+        }
+    }
+
+    // A black background gives off no light, so the path tracer doesn't need to sample it.
+    pub fn is_black(&self) -> bool {
+        match self {
+            Background::Solid(color) => is_black(*color),
+            Background::Gradient { bottom, top } => is_black(*bottom) && is_black(*top),
+            Background::Map(map) => map.intensity <= 0.0 || map.distribution.integral() <= 0.0
+        }
+    }
+
+    // Picks a direction to look for light in. Returns it with the light coming from it and its
+    // density over directions, or None when the direction can't carry any light. Solid colors
+    // and gradients are sampled uniformly, maps in proportion to the brightness of their pixels.
+    pub fn sample(&self, u1: f32, u2: f32) -> Option<(Tuple, Tuple, f32)> {
+        match self {
+            Background::Map(map) => map.sample(u1, u2),
+            _ => {
+                let direction = uniform_sphere(u1, u2);
+                Some((direction, self.color(direction), 1.0 / (4.0 * PI)))
+            }
+        }
+    }
+
+    // Density of sample returning direction.
+    pub fn pdf(&self, direction: Tuple) -> f32 {
+        match self {
+            Background::Map(map) => map.pdf(direction),
+            _ => 1.0 / (4.0 * PI)
+        }
+    }
+}
+
+fn is_black(color: Tuple) -> bool {
+    color.x <= 0.0 && color.y <= 0.0 && color.z <= 0.0
+}
+
+// An equirectangular (latitude-longitude) image of everything around the scene. The top row is
+// straight up, the bottom row straight down, and the center of the image looks along +z. Pixels
+// are looked up without filtering, so that the light sampled from a pixel is exactly the light a
+// ray in its direction sees.
+pub struct EnvironmentMap {
+    // The file the image came from, if any, so that the scene can be written back out.
+    pub path: Option<PathBuf>,
+    // The sampling distribution is built from the image, so replace the whole map rather than
+    // changing the image.
+    pub image: Canvas,
+    // Scales the light of every pixel.
+    pub intensity: f32,
+    // Turns the map around the y axis, in radians.
+    pub rotation: f32,
+    distribution: Distribution2D
+}
+
+impl EnvironmentMap {
+    // Panics if the image has no pixels, which load checks for first.
+    pub fn new(image: Canvas) -> Self {
+        assert!(image.width > 0 && image.height > 0, "An environment map needs at least one pixel.");
+        // The rows near the poles cover less of the sphere, so they are picked less often.
+        let mut weights = Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as f32 + 0.5) / image.height as f32).sin();
+            for x in 0..image.width {
+                let c = image.pixel_at(x, y);
+                weights.push((0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z).max(0.0) * sin_theta);
+            }
+        }
+        EnvironmentMap {
+            path: None,
+            distribution: Distribution2D::new(&weights, image.width, image.height),
+            image,
+            intensity: 1.0,
+            rotation: 0.0
+        }
+    }
+
+    // Reads a PFM or Radiance HDR image.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let image = read_image(path.as_ref())?;
+        if image.width == 0 || image.height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the environment map is empty"));
+        }
+        Ok(EnvironmentMap { path: Some(path.as_ref().to_path_buf()), ..EnvironmentMap::new(image) })
+    }
+
+    pub fn color(&self, direction: Tuple) -> Tuple {
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+        &self.image.pixel_at(x, y) * self.intensity
+    }
+
+    fn sample(&self, u1: f32, u2: f32) -> Option<(Tuple, Tuple, f32)> {
+        let (u, v, pdf) = self.distribution.sample(u1, u2);
+        let direction = self.uv_to_direction(u, v);
+        let sin_theta = (PI * v).sin();
+        if pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        Some((direction, self.color(direction), pdf / (2.0 * PI * PI * sin_theta)))
+    }
+
+    // The image covers 2pi by pi radians, and a pixel near the poles covers sin(theta) less of the
+    // sphere than its size in the image.
+    fn pdf(&self, direction: Tuple) -> f32 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn direction_to_uv(&self, direction: Tuple) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let x = direction.x * cos - direction.z * sin;
+        let z = direction.x * sin + direction.z * cos;
+        let u = 0.5 + x.atan2(z) / (2.0 * PI);
+        // More precise than acos(y) near the poles.
+        let v = (x * x + z * z).sqrt().atan2(direction.y) / PI;
+        (u.clamp(0.0, 1.0), v)
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Tuple {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+        let (x, y, z) = (theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+        let (sin, cos) = self.rotation.sin_cos();
+        Tuple::vector(x * cos + z * sin, y, z * cos - x * sin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    // A 4x2 map, black except for one bright pixel in the top row.
+    fn map() -> EnvironmentMap {
+        let mut image = Canvas::new(4, 2);
+        for (_, _, pixel) in image.iter_mut() {
+            *pixel = Tuple::color(0.1, 0.1, 0.1);
+        }
+        image.write_pixel(2, 0, Tuple::color(10.0, 10.0, 10.0));
+        EnvironmentMap::new(image)
+    }
+
+    #[test]
+    fn gradient_blends_from_bottom_to_top() {
+        let b = Background::Gradient { bottom: Tuple::color(1.0, 1.0, 1.0), top: Tuple::color(0.0, 0.0, 1.0) };
+        assert!(
+            b.color(Tuple::vector(0.0, -1.0, 0.0)) == Tuple::color(1.0, 1.0, 1.0) &&
+            b.color(Tuple::vector(1.0, 0.0, 0.0)) == Tuple::color(0.5, 0.5, 1.0) &&
+            b.color(Tuple::vector(0.0, 1.0, 0.0)) == Tuple::color(0.0, 0.0, 1.0),
+            "The gradient was not blended correctly."
+        )
+    }
+
+    #[test]
+    fn map_lookup() {
+        // The center of the image looks along +z and u grows towards +x.
+        let mut m = map();
+        let up_and_forward = Tuple::vector(0.1, 0.5, 1.0).norm();
+        let lit = m.color(up_and_forward);
+        m.rotation = PI;
+        assert!(
+            lit == Tuple::color(10.0, 10.0, 10.0) && m.color(up_and_forward) == Tuple::color(0.1, 0.1, 0.1) &&
+            m.color(Tuple::vector(-0.1, 0.5, -1.0).norm()) == Tuple::color(10.0, 10.0, 10.0),
+            "The environment map was not looked up by direction correctly."
+        )
+    }
+
+    #[test]
+    fn directions_round_trip() {
+        let mut m = map();
+        m.rotation = 0.7;
+        let round_trips = [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7), (0.3, 0.95)].iter().all(|(u, v)| {
+            let (u2, v2) = m.direction_to_uv(m.uv_to_direction(*u, *v));
+            (u - u2).abs() < 1.0e-4 && (v - v2).abs() < 1.0e-4
+        });
+        assert!(
+            round_trips,
+            "Turning a position in the map into a direction and back should give the same position."
+        )
+    }
+
+    #[test]
+    fn map_samples_follow_brightness() {
+        let m = map();
+        let mut bright = 0;
+        let n = 1000;
+        let consistent = (0..n).all(|i| {
+            let (direction, color, pdf) = m.sample((i as f32 + 0.5) / n as f32, (((i * 7919) % n) as f32 + 0.5) / n as f32).unwrap();
+            if color.x > 1.0 {
+                bright += 1;
+            }
+            f_equal(direction.mag(), 1.0) && (pdf - m.pdf(direction)).abs() < 1.0e-3 * pdf
+        });
+        // The bright pixel holds 100 / (100 + 3 * 1 + 4 * 1) of the weight, since both rows are
+        // equally far from the poles.
+        assert!(
+            consistent && (bright as f32 / n as f32 - 100.0 / 107.0).abs() < 0.02,
+            "Samples should pick pixels by brightness and report their density. Bright fraction: {}", bright as f32 / n as f32
+        )
+    }
+
+    #[test]
+    fn map_pdf_integrates_to_one() {
+        let m = map();
+        // Midpoint rule over the sphere, in theta and phi.
+        let (steps_theta, steps_phi) = (400, 800);
+        let mut total = 0.0;
+        for i in 0..steps_theta {
+            let theta = PI * (i as f32 + 0.5) / steps_theta as f32;
+            for j in 0..steps_phi {
+                let phi = 2.0 * PI * (j as f32 + 0.5) / steps_phi as f32;
+                let direction = Tuple::vector(theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+                total += m.pdf(direction) * theta.sin();
+            }
+        }
+        total *= (PI / steps_theta as f32) * (2.0 * PI / steps_phi as f32);
+        assert!(
+            (total - 1.0).abs() < 0.01,
+            "The density over directions should integrate to 1. Result: {}", total
+        )
+    }
+
+    #[test]
+    fn black_backgrounds() {
+        assert!(
+            Background::default().is_black() && !Background::Solid(Tuple::color(0.0, 0.1, 0.0)).is_black() &&
+            EnvironmentMap::new(Canvas::new(2, 2)).pdf(Tuple::vector(0.0, 0.0, 1.0)).is_finite() &&
+            Background::Map(Box::new(EnvironmentMap::new(Canvas::new(2, 2)))).is_black(),
+            "Only backgrounds that give off no light should be black."
+        )
+    }
+}
@@ -0,0 +1,474 @@
+use crate::ray_tracer::canvas::Canvas;
+use crate::ray_tracer::tone_mapping::ExportSettings;
+use crate::ray_tracer::tuple::Tuple;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// File formats a Canvas can be saved as.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageFormat {
+    // Plain text PPM (P3), the format from the book.
+    Ppm,
+    // 8-bit RGB PNG. The image data is stored uncompressed, so the files are only slightly smaller
+    // than binary PPMs, but every image viewer can open them.
+    Png,
+    // Portable float map. Stores the linear pixels as 32-bit floats, so the export settings are
+    // ignored and the full dynamic range of the render is kept.
+    Pfm,
+    // Radiance RGBE, the usual format for HDR environment maps. Like PFM it keeps the linear
+    // values, with an 8-bit mantissa per channel and an exponent shared between them.
+    Hdr
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            _ => None
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        path.as_ref().extension().and_then(|e| e.to_str()).and_then(ImageFormat::from_name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+            ImageFormat::Pfm => "pfm",
+            ImageFormat::Hdr => "hdr"
+        }
+    }
+
+    pub fn encode(&self, canvas: &Canvas, settings: &ExportSettings) -> Vec<u8> {
+        match self {
+            ImageFormat::Ppm => canvas.canvas_to_ppm_with_settings(settings).into_bytes(),
+            ImageFormat::Png => encode_png(canvas, settings),
+            ImageFormat::Pfm => encode_pfm(canvas),
+            ImageFormat::Hdr => encode_hdr(canvas)
+        }
+    }
+
+    // Only the high dynamic range formats can be read, for use as environment maps.
+    pub fn decode(&self, bytes: &[u8]) -> io::Result<Canvas> {
+        match self {
+            ImageFormat::Pfm => decode_pfm(bytes),
+            ImageFormat::Hdr => decode_hdr(bytes),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} images can't be read, only pfm and hdr", self.extension())
+            ))
+        }
+    }
+}
+
+// Reads an image, picking the format from the file extension.
+pub fn read_image<P: AsRef<Path>>(path: P) -> io::Result<Canvas> {
+    let path = path.as_ref();
+    match ImageFormat::from_path(path) {
+        Some(format) => format.decode(&fs::read(path)?),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown image format"))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn to_byte(c: f32) -> u8 {
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn encode_png(canvas: &Canvas, settings: &ExportSettings) -> Vec<u8> {
+    // Every scanline starts with its filter type, 0 meaning no filtering.
+    let mut raw = Vec::with_capacity(canvas.height * (canvas.width * 3 + 1));
+    for row in canvas.pixels.chunks(canvas.width.max(1)) {
+        raw.push(0);
+        for pixel in row {
+            let p = settings.apply(*pixel);
+            raw.extend_from_slice(&[to_byte(p.x), to_byte(p.y), to_byte(p.z)]);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(canvas.width as u32).to_be_bytes());
+    header.extend_from_slice(&(canvas.height as u32).to_be_bytes());
+    // Bit depth 8, color type 2 (RGB), default compression, filtering and no interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Wraps data in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// PFM rows go from the bottom of the image to the top. The negative scale marks little endian.
+fn encode_pfm(canvas: &Canvas) -> Vec<u8> {
+    let mut pfm = format!("PF\n{} {}\n-1.0\n", canvas.width, canvas.height).into_bytes();
+    for row in canvas.pixels.chunks(canvas.width.max(1)).rev() {
+        for pixel in row {
+            for c in [pixel.x, pixel.y, pixel.z].iter() {
+                pfm.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+    }
+    pfm
+}
+
+// Splits the next whitespace separated word off of the start of a header. Returns it and the
+// position just past the single whitespace character that ends it.
+fn header_word(bytes: &[u8], start: usize) -> io::Result<(&str, usize)> {
+    let begin = start + bytes[start..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let end = begin + bytes[begin..].iter().take_while(|b| !b.is_ascii_whitespace()).count();
+    if end == begin || end >= bytes.len() {
+        return Err(invalid("the image header is truncated"));
+    }
+    let word = std::str::from_utf8(&bytes[begin..end]).map_err(|_| invalid("the image header isn't text"))?;
+    Ok((word, end + 1))
+}
+
+fn decode_pfm(bytes: &[u8]) -> io::Result<Canvas> {
+    let (kind, next) = header_word(bytes, 0)?;
+    let channels = match kind {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid("not a PFM image"))
+    };
+    let (width, next) = header_word(bytes, next)?;
+    let (height, next) = header_word(bytes, next)?;
+    let (scale, start) = header_word(bytes, next)?;
+    let number = |word: &str| word.parse::<usize>().map_err(|_| invalid("bad PFM size"));
+    let (width, height) = (number(width)?, number(height)?);
+    let little_endian = scale.parse::<f32>().map_err(|_| invalid("bad PFM scale"))? < 0.0;
+
+    let data = &bytes[start..];
+    if width.checked_mul(height).and_then(|n| n.checked_mul(channels * 4)) != Some(data.len()) {
+        return Err(invalid("the PFM data doesn't match its size"));
+    }
+    let values: Vec<f32> = data.chunks(4).map(|b| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if little_endian { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+    }).collect();
+    let mut canvas = Canvas::new(width, height);
+    for (i, pixel) in values.chunks(channels).enumerate() {
+        let (x, y) = (i % width, height - 1 - i / width);
+        let color = if channels == 3 { Tuple::color(pixel[0], pixel[1], pixel[2]) } else { Tuple::color(pixel[0], pixel[0], pixel[0]) };
+        canvas.write_pixel(x, y, color);
+    }
+    Ok(canvas)
+}
+
+// Written without run length encoding, which every reader understands.
+fn encode_hdr(canvas: &Canvas) -> Vec<u8> {
+    let mut hdr = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", canvas.height, canvas.width).into_bytes();
+    for pixel in canvas.pixels.iter() {
+        hdr.extend_from_slice(&to_rgbe(*pixel));
+    }
+    hdr
+}
+
+fn to_rgbe(color: Tuple) -> [u8; 4] {
+    let v = color.x.max(color.y).max(color.z);
+    if v < 1.0e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1).
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2_f32.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2_f32.powi(e);
+    let byte = |c: f32| (c.max(0.0) * scale).min(255.0) as u8;
+    [byte(color.x), byte(color.y), byte(color.z), (e + 128).clamp(0, 255) as u8]
+}
+
+fn from_rgbe(rgbe: &[u8]) -> Tuple {
+    if rgbe[3] == 0 {
+        return Tuple::color(0.0, 0.0, 0.0);
+    }
+    let f = 2_f32.powi(rgbe[3] as i32 - 136);
+    Tuple::color((rgbe[0] as f32 + 0.5) * f, (rgbe[1] as f32 + 0.5) * f, (rgbe[2] as f32 + 0.5) * f)
+}
+
+// Reads flat and run length encoded scanlines. Only the standard orientation, rows from the top
+// down and pixels left to right, is supported.
+fn decode_hdr(bytes: &[u8]) -> io::Result<Canvas> {
+    if !bytes.starts_with(b"#?") {
+        return Err(invalid("not a Radiance HDR image"));
+    }
+    // The header is a list of lines ended by an empty one, followed by the resolution line.
+    let mut pos = 0;
+    loop {
+        let end = pos + bytes[pos..].iter().position(|b| *b == b'\n').ok_or_else(|| invalid("the HDR header is truncated"))?;
+        let line = &bytes[pos..end];
+        pos = end + 1;
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("only RGBE HDR images are supported"));
+        }
+        if line.is_empty() {
+            break;
+        }
+    }
+    let (y_axis, next) = header_word(bytes, pos)?;
+    let (height, next) = header_word(bytes, next)?;
+    let (x_axis, next) = header_word(bytes, next)?;
+    let (width, start) = header_word(bytes, next)?;
+    if y_axis != "-Y" || x_axis != "+X" {
+        return Err(invalid("only HDR images stored top down and left to right are supported"));
+    }
+    let number = |word: &str| word.parse::<usize>().map_err(|_| invalid("bad HDR size"));
+    let (width, height) = (number(width)?, number(height)?);
+
+    // A run fits at most 127 values in 2 bytes, so no file holds more than 64 values per byte.
+    // Checked before allocating anything, so that a bad header can't ask for more than that.
+    let mut data = &bytes[start..];
+    let values = width.checked_mul(height).and_then(|n| n.checked_mul(4));
+    if values.is_none_or(|n| n > data.len().saturating_mul(64)) {
+        return Err(invalid("the HDR data is truncated"));
+    }
+    let mut canvas = Canvas::new(width, height);
+    let mut scanline = vec![0_u8; width * 4];
+    for y in 0..height {
+        data = read_scanline(data, &mut scanline, width)?;
+        for x in 0..width {
+            canvas.write_pixel(x, y, from_rgbe(&scanline[x * 4..x * 4 + 4]));
+        }
+    }
+    Ok(canvas)
+}
+
+// Fills scanline with width RGBE pixels and returns the rest of data. Run length encoded lines
+// start with 2, 2 and the width, then hold each of the 4 channels as runs: a count above 128
+// repeats the next byte count - 128 times, anything else is that many literal bytes.
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [u8], width: usize) -> io::Result<&'a [u8]> {
+    let truncated = || invalid("the HDR data is truncated");
+    let encoded = (8..0x8000).contains(&width) && data.len() >= 4 && data[0] == 2 && data[1] == 2 &&
+        ((data[2] as usize) << 8 | data[3] as usize) == width;
+    if !encoded {
+        let size = width * 4;
+        if data.len() < size {
+            return Err(truncated());
+        }
+        scanline.copy_from_slice(&data[..size]);
+        return Ok(&data[size..]);
+    }
+
+    let mut pos = 4;
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(pos).ok_or_else(truncated)? as usize;
+            pos += 1;
+            if count > 128 {
+                let value = *data.get(pos).ok_or_else(truncated)?;
+                pos += 1;
+                if x + count - 128 > width {
+                    return Err(invalid("an HDR run goes past the end of its scanline"));
+                }
+                for _ in 0..count - 128 {
+                    scanline[x * 4 + channel] = value;
+                    x += 1;
+                }
+            } else {
+                if count == 0 || x + count > width || pos + count > data.len() {
+                    return Err(invalid("bad HDR scanline"));
+                }
+                for value in &data[pos..pos + count] {
+                    scanline[x * 4 + channel] = *value;
+                    x += 1;
+                }
+                pos += count;
+            }
+        }
+    }
+    Ok(&data[pos..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::tuple::Tuple;
+
+    #[test]
+    fn format_from_path() {
+        assert!(
+            ImageFormat::from_path("out/image.PNG") == Some(ImageFormat::Png) &&
+            ImageFormat::from_path("image.pfm") == Some(ImageFormat::Pfm) &&
+            ImageFormat::from_path("sky.hdr") == Some(ImageFormat::Hdr) &&
+            ImageFormat::from_path("image.ppm") == Some(ImageFormat::Ppm) &&
+            ImageFormat::from_path("image.jpg").is_none() && ImageFormat::from_path("image").is_none(),
+            "The image format was not picked from the file extension correctly."
+        )
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert!(
+            crc32(b"123456789") == 0xcbf4_3926 && adler32(b"Wikipedia") == 0x11e6_0398,
+            "The checksums were not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn png_layout() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Tuple::color(1.0, 0.0, 0.5));
+        let png = ImageFormat::Png.encode(&c, &ExportSettings::default());
+        // Signature, IHDR (8 + 13 + 4), then the IDAT chunk: zlib header, one stored block holding
+        // the filter byte and 2 pixels, and the adler32 checksum.
+        let idat = &png[33..];
+        assert!(
+            &png[..8] == b"\x89PNG\r\n\x1a\n" && &png[12..16] == b"IHDR" && png[16..24] == [0, 0, 0, 2, 0, 0, 0, 1] &&
+            &idat[4..8] == b"IDAT" && idat[8..15] == [0x78, 0x01, 1, 7, 0, 0xf8, 0xff] &&
+            idat[15..22] == [0, 255, 0, 128, 0, 0, 0] && &png[png.len() - 8..png.len() - 4] == b"IEND",
+            "The PNG was not laid out correctly. Result: {:?}", png
+        )
+    }
+
+    #[test]
+    fn pfm_keeps_linear_values_bottom_up() {
+        let mut c = Canvas::new(1, 2);
+        c.write_pixel(0, 0, Tuple::color(2.5, 0.0, 0.0));
+        let pfm = ImageFormat::Pfm.encode(&c, &ExportSettings::default());
+        let header = b"PF\n1 2\n-1.0\n";
+        // The top row is written last.
+        let top = &pfm[header.len() + 12..header.len() + 16];
+        assert!(
+            pfm.starts_with(header) && pfm.len() == header.len() + 24 && top == 2.5_f32.to_le_bytes(),
+            "The PFM was not laid out correctly."
+        )
+    }
+
+    #[test]
+    fn pfm_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Tuple::color(2.5, 0.25, 100.0));
+        c.write_pixel(2, 1, Tuple::color(0.0, 1.0e-3, 7.0));
+        let result = ImageFormat::Pfm.decode(&ImageFormat::Pfm.encode(&c, &ExportSettings::default())).unwrap();
+        assert!(
+            result.width == 3 && result.height == 2 && result.pixels == c.pixels,
+            "The PFM did not survive being written and read back."
+        )
+    }
+
+    #[test]
+    fn big_endian_greyscale_pfm() {
+        let mut pfm = b"Pf\n2 1\n1.0\n".to_vec();
+        pfm.extend_from_slice(&0.5_f32.to_be_bytes());
+        pfm.extend_from_slice(&4.0_f32.to_be_bytes());
+        let result = ImageFormat::Pfm.decode(&pfm).unwrap();
+        assert!(
+            result.pixel_at(0, 0) == Tuple::color(0.5, 0.5, 0.5) && result.pixel_at(1, 0) == Tuple::color(4.0, 4.0, 4.0),
+            "The greyscale PFM was not read correctly."
+        )
+    }
+
+    #[test]
+    fn hdr_round_trip() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(0, 0, Tuple::color(1.0, 0.5, 0.25));
+        c.write_pixel(1, 0, Tuple::color(1000.0, 20.0, 0.0));
+        c.write_pixel(0, 1, Tuple::color(0.001, 0.002, 0.003));
+        let result = ImageFormat::Hdr.decode(&ImageFormat::Hdr.encode(&c, &ExportSettings::default())).unwrap();
+        let close = c.pixels.iter().zip(result.pixels.iter()).all(|(a, b)| {
+            let max = a.x.max(a.y).max(a.z);
+            [(a.x, b.x), (a.y, b.y), (a.z, b.z)].iter().all(|(p, q)| (p - q).abs() <= max / 128.0)
+        });
+        assert!(
+            close && result.pixel_at(1, 1) == Tuple::color(0.0, 0.0, 0.0),
+            "The HDR image did not survive being written and read back. Result: {:?}", result.pixels
+        )
+    }
+
+    #[test]
+    fn run_length_encoded_hdr() {
+        // One 8 pixel scanline. Red is a run of 8, green 8 literal bytes, blue a run of 8 and the
+        // exponent two runs of 4.
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        hdr.extend_from_slice(&[2, 2, 0, 8]);
+        hdr.extend_from_slice(&[136, 128]);
+        hdr.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        hdr.extend_from_slice(&[136, 0]);
+        hdr.extend_from_slice(&[132, 129, 132, 130]);
+        let result = ImageFormat::Hdr.decode(&hdr).unwrap();
+        assert!(
+            result.width == 8 && result.pixel_at(0, 0) == Tuple::color(1.0039063, 0.00390625, 0.00390625) &&
+            result.pixel_at(7, 0) == Tuple::color(2.0078125, 1.7578125, 0.0078125),
+            "The run length encoded HDR was not read correctly. Result: {:?}", result.pixels
+        )
+    }
+
+    #[test]
+    fn huge_hdr_size_is_rejected_before_allocating() {
+        let result = ImageFormat::Hdr.decode(b"#?RADIANCE\n\n-Y 200000 +X 200000\n\x02\x02\x00\x08");
+        let overflow = ImageFormat::Hdr.decode(format!("#?RADIANCE\n\n-Y {} +X 2\n", usize::MAX).as_bytes());
+        assert!(
+            result.map_err(|e| e.kind()).err() == Some(io::ErrorKind::InvalidData) && overflow.is_err(),
+            "An HDR header asking for more pixels than the file holds should be rejected."
+        )
+    }
+
+    #[test]
+    fn ldr_images_cannot_be_read() {
+        let result = ImageFormat::Png.decode(&[]);
+        assert!(
+            result.map_err(|e| e.kind()).err() == Some(io::ErrorKind::InvalidInput) &&
+            ImageFormat::Hdr.decode(b"#?RADIANCE\n\n-Y 2 +X 2\n").is_err(),
+            "Reading a PNG or a truncated HDR should fail."
+        )
+    }
+}
@@ -0,0 +1,435 @@
+use crate::ray_tracer::world::World;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::intersection::Computations;
+use crate::ray_tracer::traits::object::Object;
+use crate::ray_tracer::common::SHADOW_BIAS;
+
+// How the camera turns a ray into a color.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Integrator {
+    // The book's Phong shading with shadows. Direct light only, with a constant ambient term
+    // standing in for everything else.
+    #[default]
+    Phong,
+    // Unbiased Monte Carlo path tracing. Light bounces between surfaces, so objects light each
+    // other and pick up each other's colors. Needs many samples per pixel to converge.
+    PathTracer(PathTracerSettings)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathTracerSettings {
+    // Longest path, counted in surface hits, before it is cut off.
+    pub max_depth: usize,
+    // Number of bounces that are always followed before Russian roulette can end a path.
+    pub roulette_depth: usize
+}
+
+impl PathTracerSettings {
+    pub fn new(max_depth: usize, roulette_depth: usize) -> Self {
+        PathTracerSettings {
+            max_depth,
+            roulette_depth
+        }
+    }
+}
+
+impl Default for PathTracerSettings {
+    fn default() -> Self {
+        PathTracerSettings {
+            max_depth: 8,
+            roulette_depth: 3
+        }
+    }
+}
+
+impl Integrator {
+    // The color seen along the ray. rng is only used by the path tracer.
+    pub fn color_at(&self, world: &World, ray: &Ray, rng: &mut Rng) -> Tuple {
+        match self {
+            Integrator::Phong => world.color_at(ray),
+            Integrator::PathTracer(settings) => path_trace(world, ray, settings, rng)
+        }
+    }
+}
+
+// Every surface scatters light through its material's BSDF, Lambertian with an albedo of color *
+// diffuse unless the material has another one, so the ambient, specular and shininess values of a
+// material are ignored. Every path looks for light by sampling the lights directly at every hit
+// (next event estimation), then bounces in a direction picked by the BSDF. The light reaching a
+// diffuse surface is intensity * cos / pi, which is a factor of pi darker than the Phong diffuse
+// term.
+//
+// Emissive objects are found both ways: the camera sees them directly, and at every hit a shadow
+// ray goes to a random point on one of them. A bounce that happens to hit an emitter doesn't add
+// its emission, since the shadow rays already account for that light. The exception is a bounce
+// off of a perfectly smooth surface, like a mirror or clear glass, which shadow rays can't follow.
+//
+// The background is found both ways too, but since it surrounds the whole scene neither way is
+// good everywhere: shadow rays towards a bright spot in an environment map find it easily, while
+// bounces off of shiny surfaces find it more often than shadow rays would. Both are kept and
+// weighted with the power heuristic (multiple importance sampling), so each counts the most where
+// it works the best.
+fn path_trace(world: &World, ray: &Ray, settings: &PathTracerSettings, rng: &mut Rng) -> Tuple {
+    let mut radiance = Tuple::color(0.0, 0.0, 0.0);
+    let mut throughput = Tuple::color(1.0, 1.0, 1.0);
+    let mut ray = Ray::with_time(ray.origin, ray.direction, ray.time);
+    let emitters = world.emitters();
+    let sample_background = !world.background.is_black();
+    // Whether emitters hit by the ray should count, true for rays from the camera.
+    let mut count_emission = true;
+    // Density of the BSDF sample the ray was picked with, for weighting the background it hits.
+    let mut bsdf_pdf = 0.0;
+
+    for depth in 0..settings.max_depth {
+        let xs = world.intersect(&ray);
+        let hit = match Ray::hit(&xs) {
+            Some(hit) => hit,
+            None => {
+                if sample_background {
+                    let direction = ray.direction.norm();
+                    let background = world.background.color(direction);
+                    let weight = if count_emission { 1.0 } else { power_heuristic(bsdf_pdf, world.background.pdf(direction)) };
+                    radiance = &radiance + &(&throughput.hadamard_product(background) * weight);
+                }
+                break;
+            }
+        };
+        let comps = hit.prepare_computations(&ray);
+        let material = comps.object.get_material();
+        if count_emission {
+            radiance = &radiance + &throughput.hadamard_product(material.emission);
+        }
+
+        let bsdf = material.path_tracing_bsdf();
+        let wo = comps.eyev;
+        // BSDFs want the normal that points out of the object, not the one facing the eye.
+        let n = if comps.inside { -&comps.normalv } else { comps.normalv };
+
+        // Next event estimation: one shadow ray towards a random point on every light, and one
+        // towards a random emitter.
+        if !bsdf.is_delta() {
+            for light in world.lights.iter() {
+                let (direction, distance, incoming) = light.sample_incident(comps.over_point, rng);
+                let f = bsdf.evaluate(wo, direction, n);
+                if is_black(f) || world.is_occluded(ray_origin(&comps, direction), direction, distance, comps.time) {
+                    continue;
+                }
+                let direct = f.hadamard_product(incoming);
+                radiance = &radiance + &(&throughput.hadamard_product(direct) * (&direction * &n).abs());
+            }
+            if !emitters.is_empty() {
+                if let Some((direction, incoming)) = sample_emitter(world, &emitters, &comps, rng) {
+                    let direct = bsdf.evaluate(wo, direction, n).hadamard_product(incoming);
+                    radiance = &radiance + &(&throughput.hadamard_product(direct) * (&direction * &n).abs());
+                }
+            }
+            if sample_background {
+                if let Some((direction, incoming, pdf)) = world.background.sample(rng.next_f32(), rng.next_f32()) {
+                    let f = bsdf.evaluate(wo, direction, n);
+                    if !is_black(f) && !world.is_occluded(ray_origin(&comps, direction), direction, f32::INFINITY, comps.time) {
+                        let weight = power_heuristic(pdf, bsdf.pdf(wo, direction, n)) / pdf;
+                        let direct = f.hadamard_product(incoming);
+                        radiance = &radiance + &(&throughput.hadamard_product(direct) * ((&direction * &n).abs() * weight));
+                    }
+                }
+            }
+        }
+
+        let sample = match bsdf.sample(wo, n, rng) {
+            Some(sample) => sample,
+            None => break
+        };
+        throughput = throughput.hadamard_product(sample.weight);
+        count_emission = sample.delta;
+        bsdf_pdf = sample.pdf;
+        // Russian roulette: end dim paths early, and boost the ones that survive by the same
+        // factor so that the average stays the same.
+        if depth + 1 >= settings.roulette_depth {
+            let survival = throughput.x.max(throughput.y).max(throughput.z).clamp(0.05, 0.95);
+            if rng.next_f32() >= survival {
+                break;
+            }
+            throughput = &throughput * (1.0 / survival);
+        }
+
+        ray = Ray::with_time(ray_origin(&comps, sample.wi), sample.wi, comps.time);
+    }
+
+    radiance
+}
+
+// Where a ray leaving the surface in direction starts: above it on the eye's side, below it when
+// it goes into the surface.
+fn ray_origin(comps: &Computations, direction: Tuple) -> Tuple {
+    if &direction * &comps.normalv >= 0.0 { comps.over_point } else { comps.under_point }
+}
+
+fn is_black(color: Tuple) -> bool {
+    color.x <= 0.0 && color.y <= 0.0 && color.z <= 0.0
+}
+
+// Weight for a sample picked with density pdf, when the same light could also have been found by
+// a strategy with density other_pdf.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+// Picks an emitter at random, each one equally likely, and a point on it. Returns the direction
+// towards the point and the light arriving from it divided by the probability of the sample. The
+// surface density of the point is turned into a density over directions by multiplying with
+// distance^2 / cos at the emitter. Emitters glow on both sides.
+fn sample_emitter(world: &World, emitters: &[&dyn Object], comps: &Computations, rng: &mut Rng) -> Option<(Tuple, Tuple)> {
+    let index = ((rng.next_f32() * emitters.len() as f32) as usize).min(emitters.len() - 1);
+    let emitter = emitters[index];
+    let (position, emitter_normal, pdf) = emitter.sample_surface(rng.next_f32(), rng.next_f32(), comps.time);
+
+    let v = &position - &comps.point;
+    let distance = v.mag();
+    if distance == 0.0 {
+        return None;
+    }
+    let direction = &v * (1.0 / distance);
+    let cos_emitter = (&direction * &emitter_normal).abs();
+    let origin = ray_origin(comps, direction);
+    // The shadow ray stops just short of the sample so that the emitter doesn't block itself.
+    if cos_emitter == 0.0 || world.is_occluded(origin, direction, (&position - &origin).mag() - SHADOW_BIAS, comps.time) {
+        return None;
+    }
+    let pdf = pdf / emitters.len() as f32 * distance * distance / cos_emitter;
+    Some((direction, &emitter.get_material().emission * (1.0 / pdf)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use crate::ray_tracer::light::Light;
+    use crate::ray_tracer::sphere::Sphere;
+    use crate::ray_tracer::matrix::Matrix;
+    use crate::ray_tracer::triangle::Triangle;
+    use crate::ray_tracer::bsdf::Bsdf;
+    use crate::ray_tracer::environment::{Background, EnvironmentMap};
+    use crate::ray_tracer::canvas::Canvas;
+
+    #[test]
+    fn phong_matches_world_color_at() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let result = Integrator::Phong.color_at(&w, &r, &mut Rng::new(0));
+        assert!(
+            result == w.color_at(&r),
+            "The Phong integrator should shade like the world does. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn path_tracer_direct_light() {
+        // Bounces off of a lone sphere never hit anything again, so only the direct light counts.
+        let mut w = World::empty();
+        w.lights.push(Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0)));
+        w.objects.push(Box::new(Sphere::new()));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let result = integrator.color_at(&w, &r, &mut Rng::new(3));
+        let expected = 0.9 / PI;
+        assert!(
+            result == Tuple::color(expected, expected, expected),
+            "The directly lit sphere was not shaded correctly. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn path_tracer_misses_are_black() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        assert!(
+            integrator.color_at(&w, &r, &mut Rng::new(0)) == Tuple::color(0.0, 0.0, 0.0),
+            "A ray that misses everything should be black."
+        )
+    }
+
+    #[test]
+    fn path_tracer_color_bleeding() {
+        // A white sphere next to a big red one, lit from the side away from the red sphere. The
+        // side of the white sphere facing the red one only gets light that bounced off of it.
+        let mut w = World::empty();
+        w.lights.push(Light::new(Tuple::color(10.0, 10.0, 10.0), Tuple::point(-10.0, 0.0, 0.0)));
+        w.objects.push(Box::new(Sphere::new()));
+        let mut red = Sphere::new();
+        red.set_transform(&Matrix::translation(4.0, 0.0, 0.0) * &Matrix::scaling(2.0, 2.0, 2.0));
+        red.material.color = Tuple::color(1.0, 0.0, 0.0);
+        w.objects.push(Box::new(red));
+
+        let r = Ray::new(Tuple::point(1.5, 0.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let mut rng = Rng::new(5);
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for _ in 0..2000 {
+            sum = &sum + &integrator.color_at(&w, &r, &mut rng);
+        }
+        let phong = Integrator::Phong.color_at(&w, &r, &mut rng);
+        assert!(
+            sum.x > 0.0 && sum.y == 0.0 && sum.z == 0.0 && phong.x == phong.y,
+            "The white sphere should only be lit by red light bouncing off of its neighbour. Result: {:?}", sum
+        )
+    }
+
+    #[test]
+    fn emitters_are_visible() {
+        let mut w = World::empty();
+        let mut s = Sphere::new();
+        s.material.emission = Tuple::color(2.0, 1.0, 0.5);
+        w.objects.push(Box::new(s));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let result = integrator.color_at(&w, &r, &mut Rng::new(1));
+        assert!(
+            result == Tuple::color(2.0, 1.0, 0.5) && Integrator::Phong.color_at(&w, &r, &mut Rng::new(1)) == result,
+            "A glowing sphere should show its emission. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn sphere_light_illuminates_floor() {
+        // A sphere of radius r glowing with radiance L at distance d straight above a surface gives
+        // it an irradiance of pi * L * (r / d)^2. The black sphere reflects nothing, so a bounce off
+        // the floor that hits it would only add light if its emission got counted twice.
+        let mut w = World::empty();
+        let floor = Triangle::new(Tuple::point(-10.0, 0.0, -10.0), Tuple::point(0.0, 0.0, 20.0),
+                                  Tuple::point(10.0, 0.0, -10.0));
+        w.objects.push(Box::new(floor));
+        let mut lamp = Sphere::new();
+        lamp.set_transform(&Matrix::translation(0.0, 5.0, 0.0) * &Matrix::scaling(0.5, 0.5, 0.5));
+        lamp.material.color = Tuple::color(0.0, 0.0, 0.0);
+        lamp.material.emission = Tuple::color(100.0, 100.0, 100.0);
+        w.objects.push(Box::new(lamp));
+
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let mut rng = Rng::new(11);
+        let n = 4000;
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            sum = &sum + &integrator.color_at(&w, &r, &mut rng);
+        }
+        let result = &sum * (1.0 / n as f32);
+        let expected = 0.9 * 100.0 * (0.5_f32 / 5.0).powi(2);
+        assert!(
+            (result.x - expected).abs() < 0.05 * expected,
+            "The floor was not lit correctly by the sphere. Expected: {} Result: {:?}", expected, result
+        )
+    }
+
+    // A big glowing sphere around the point (0, 0, -20), behind a camera at (0, 0, -5).
+    fn lamp_behind_camera() -> Sphere {
+        let mut lamp = Sphere::new();
+        lamp.set_transform(&Matrix::translation(0.0, 0.0, -20.0) * &Matrix::scaling(2.0, 2.0, 2.0));
+        lamp.material.color = Tuple::color(0.0, 0.0, 0.0);
+        lamp.material.emission = Tuple::color(3.0, 2.0, 1.0);
+        lamp
+    }
+
+    #[test]
+    fn mirror_reflects_emitter() {
+        let mut w = World::empty();
+        w.objects.push(Box::new(lamp_behind_camera()));
+        let mut mirror = Sphere::new();
+        mirror.material.bsdf = Some(Bsdf::Conductor { color: Tuple::color(1.0, 1.0, 1.0), roughness: 0.0, metalness: 1.0 });
+        w.objects.push(Box::new(mirror));
+
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let result = integrator.color_at(&w, &r, &mut Rng::new(4));
+        assert!(
+            result == Tuple::color(3.0, 2.0, 1.0),
+            "The mirror should show the emitter behind the camera. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn light_passes_through_glass() {
+        // Looking through the middle of a glass sphere at an emitter. At normal incidence 4% is
+        // reflected on the way in and on the way out, so 0.96^2 of the light gets through.
+        let mut w = World::empty();
+        let mut lamp = lamp_behind_camera();
+        lamp.set_transform(&Matrix::translation(0.0, 0.0, 20.0) * &Matrix::scaling(2.0, 2.0, 2.0));
+        w.objects.push(Box::new(lamp));
+        let mut glass = Sphere::new();
+        glass.material.color = Tuple::color(0.0, 0.0, 0.0);
+        glass.material.bsdf = Some(Bsdf::Dielectric { ior: 1.5, roughness: 0.0, tint: Tuple::color(1.0, 1.0, 1.0) });
+        w.objects.push(Box::new(glass));
+
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let mut rng = Rng::new(8);
+        let n = 4000;
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            sum = &sum + &integrator.color_at(&w, &r, &mut rng);
+        }
+        let result = &sum * (1.0 / (3.0 * n as f32));
+        assert!(
+            (result.x - 0.9216).abs() < 0.02,
+            "The glass let through the wrong amount of light. Result: {:?}", result
+        )
+    }
+
+    // A convex object never sees itself, so under a sky that is white in every direction a
+    // Lambertian sphere reflects exactly its albedo.
+    fn furnace(background: Background) -> Tuple {
+        let mut w = World::empty();
+        w.background = background;
+        let mut s = Sphere::new();
+        s.material.bsdf = Some(Bsdf::Lambert { albedo: Tuple::color(0.5, 0.5, 0.5) });
+        w.objects.push(Box::new(s));
+
+        let r = Ray::new(Tuple::point(0.3, 0.2, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        let mut rng = Rng::new(3);
+        let n = 2000;
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for _ in 0..n {
+            sum = &sum + &integrator.color_at(&w, &r, &mut rng);
+        }
+        &sum * (1.0 / n as f32)
+    }
+
+    #[test]
+    fn sky_is_seen_directly() {
+        let mut w = World::empty();
+        w.background = Background::Gradient { bottom: Tuple::color(1.0, 0.0, 0.0), top: Tuple::color(0.0, 0.0, 1.0) };
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 3.0, 0.0));
+        let integrator = Integrator::PathTracer(PathTracerSettings::default());
+        assert!(
+            integrator.color_at(&w, &r, &mut Rng::new(0)) == Tuple::color(0.0, 0.0, 1.0),
+            "A camera ray that misses should see the background."
+        )
+    }
+
+    #[test]
+    fn white_sky_furnace() {
+        let result = furnace(Background::Solid(Tuple::color(1.0, 1.0, 1.0)));
+        assert!(
+            (result.x - 0.5).abs() < 0.02,
+            "A sphere with an albedo of 0.5 under a white sky should be 0.5. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn environment_map_furnace() {
+        // Importance sampling a map that is white everywhere has to give the same answer.
+        let mut image = Canvas::new(16, 8);
+        for (_, _, pixel) in image.iter_mut() {
+            *pixel = Tuple::color(1.0, 1.0, 1.0);
+        }
+        let result = furnace(Background::Map(Box::new(EnvironmentMap::new(image))));
+        assert!(
+            (result.x - 0.5).abs() < 0.02,
+            "A sphere with an albedo of 0.5 under a white map should be 0.5. Result: {:?}", result
+        )
+    }
+}
@@ -1,8 +1,13 @@
 use crate::ray_tracer::traits::object::Object;
 use crate::ray_tracer::traits::intersection_object::IntersectionObject;
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::common::SHADOW_BIAS;
 
 #[cfg(test)]
 use crate::ray_tracer::sphere::Sphere;
+#[cfg(test)]
+use crate::ray_tracer::matrix::Matrix;
 
 pub struct Intersection<'a> {
     pub t: f32,
@@ -18,14 +23,58 @@ impl<'a> Intersection<'a> {
     }
 }
 
+// Precomputed values about an intersection that are reused by the shading functions.
+pub struct Computations<'a> {
+    pub t: f32,
+    pub object: &'a dyn Object,
+    pub point: Tuple,
+    // point nudged slightly above the surface so rays cast from it don't hit the surface itself.
+    pub over_point: Tuple,
+    // The same distance below the surface, where rays refracted into it start.
+    pub under_point: Tuple,
+    pub eyev: Tuple,
+    pub normalv: Tuple,
+    // True when the ray originated inside of the object. The normal is flipped in that case so
+    // that it always points towards the eye.
+    pub inside: bool,
+    // The ray's time. Rays leaving the point have to be traced at the same time.
+    pub time: f32
+}
+
+impl Intersection<'_> {
+    pub fn prepare_computations(&self, ray: &Ray) -> Computations<'_> {
+        let point = ray.position(self.t);
+        let eyev = -&ray.direction;
+        let mut normalv = self.object.normal_at_time(point, ray.time);
+        let inside = &normalv * &eyev < 0.0;
+        if inside {
+            normalv = -&normalv;
+        }
+        let over_point = &point + &(&normalv * SHADOW_BIAS);
+        let under_point = &point - &(&normalv * SHADOW_BIAS);
+
+        Computations {
+            t: self.t,
+            object: self.object.as_ref(),
+            point,
+            over_point,
+            under_point,
+            eyev,
+            normalv,
+            inside,
+            time: ray.time
+        }
+    }
+}
+
 impl IntersectionObject for Intersection<'_> {
 
     fn get_t(&self) -> f32 {
         self.t
     }
     
-    fn get_object(&self) -> &Box<dyn Object + '_> {
-        &self.object
+    fn get_object(&self) -> &dyn Object {
+        self.object.as_ref()
     }
 }
 
@@ -46,4 +95,60 @@ mod tests {
             "The creation of the ray was not valid."
         );
     }
+
+    #[test]
+    fn precompute_state_of_intersection() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let s = Sphere::new();
+        let i = Intersection::new(4.0, Box::new(&s));
+        let comps = i.prepare_computations(&r);
+        assert!(
+            comps.t == i.t &&
+            comps.object.get_material() == s.get_material() &&
+            comps.point == Tuple::point(0.0, 0.0, -1.0) &&
+            comps.eyev == Tuple::vector(0.0, 0.0, -1.0) &&
+            comps.normalv == Tuple::vector(0.0, 0.0, -1.0) &&
+            !comps.inside,
+            "The intersection computations were not prepared correctly."
+        );
+    }
+
+    #[test]
+    fn hit_when_intersection_occurs_inside() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let s = Sphere::new();
+        let i = Intersection::new(1.0, Box::new(&s));
+        let comps = i.prepare_computations(&r);
+        assert!(
+            comps.point == Tuple::point(0.0, 0.0, 1.0) &&
+            comps.eyev == Tuple::vector(0.0, 0.0, -1.0) &&
+            comps.normalv == Tuple::vector(0.0, 0.0, -1.0) &&
+            comps.inside,
+            "The normal should be inverted when the hit is inside of the object."
+        );
+    }
+
+    #[test]
+    fn hit_offsets_the_point() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let s = Sphere::with_transform(Matrix::translation(0.0, 0.0, 1.0));
+        let i = Intersection::new(5.0, Box::new(&s));
+        let comps = i.prepare_computations(&r);
+        assert!(
+            comps.over_point.z < -SHADOW_BIAS / 2.0 && comps.point.z > comps.over_point.z,
+            "The over point was not offset above the surface."
+        );
+    }
+
+    #[test]
+    fn under_point_is_below_the_surface() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let s = Sphere::with_transform(Matrix::translation(0.0, 0.0, 1.0));
+        let i = Intersection::new(5.0, Box::new(&s));
+        let comps = i.prepare_computations(&r);
+        assert!(
+            comps.under_point.z > SHADOW_BIAS / 2.0 && comps.point.z < comps.under_point.z,
+            "The under point was not offset below the surface."
+        );
+    }
 }
\ No newline at end of file
@@ -1,63 +1,352 @@
 use crate::ray_tracer::tuple::Tuple;
 use crate::ray_tracer::material::Material;
+use crate::ray_tracer::enums::light_types::LightTypes;
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::common::EPSILON;
+use std::f32::consts::PI;
 
 
 pub struct Light {
     pub intensity: Tuple,
-    pub position: Tuple
+    // For area lights this is the center of the light.
+    pub position: Tuple,
+    pub light_type: LightTypes,
+    pub attenuation: Attenuation,
+    // Multiplies the diffuse and specular light, but not the ambient term, so that a light set
+    // up in watts doesn't flood every surface with ambient light. Set by with_power.
+    pub direct_scale: f32
+}
+
+// How the light fades with distance d: 1 / (constant + linear * d + quadratic * d^2). The default
+// (1, 0, 0) doesn't fade at all. Directional lights are never attenuated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Self {
+        Attenuation {
+            constant,
+            linear,
+            quadratic
+        }
+    }
+
+    pub fn none() -> Self {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+
+    // Physically correct falloff for a point source.
+    pub fn inverse_square() -> Self {
+        Attenuation::new(0.0, 0.0, 1.0)
+    }
+
+    pub fn factor(&self, distance: f32) -> f32 {
+        let denominator = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        1.0 / denominator.max(EPSILON)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::none()
+    }
+}
+
+// A rectangular light split into usteps x vsteps cells. Each cell is sampled once when testing for
+// shadows, so the fraction of cells that are visible from a point produces soft penumbrae.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    // Edge vector of a single cell in the u direction.
+    pub uvec: Tuple,
+    // Private so that a light always has at least one cell, see usteps() and vsteps().
+    usteps: usize,
+    // Edge vector of a single cell in the v direction.
+    pub vvec: Tuple,
+    vsteps: usize,
+    // Sample a random point in each cell instead of its center. Turns banding into noise.
+    pub jitter: bool
+}
+
+impl AreaLight {
+    // full_uvec and full_vvec are the two edges of the whole light, starting at corner. Panics if
+    // either edge has no steps, since the light would have no cells to sample.
+    pub fn new(corner: Tuple, full_uvec: Tuple, usteps: usize, full_vvec: Tuple, vsteps: usize) -> Self {
+        AreaLight::from_cells(corner, &full_uvec / usteps as f32, usteps, &full_vvec / vsteps as f32, vsteps)
+    }
+
+    // Like new, but uvec and vvec are the edges of a single cell. Panics on zero steps as well.
+    pub fn from_cells(corner: Tuple, uvec: Tuple, usteps: usize, vvec: Tuple, vsteps: usize) -> Self {
+        assert!(usteps > 0 && vsteps > 0, "An area light needs at least one step along each edge.");
+        AreaLight {
+            corner,
+            uvec,
+            usteps,
+            vvec,
+            vsteps,
+            jitter: true
+        }
+    }
+
+    pub fn usteps(&self) -> usize {
+        self.usteps
+    }
+
+    pub fn vsteps(&self) -> usize {
+        self.vsteps
+    }
+
+    pub fn samples(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    // Position in cell (u, v). `offset` is where inside of the cell, (0.5, 0.5) being its center.
+    pub fn point_on_light(&self, u: usize, v: usize, offset: (f32, f32)) -> Tuple {
+        &(&self.corner + &(&self.uvec * (u as f32 + offset.0))) + &(&self.vvec * (v as f32 + offset.1))
+    }
+
+    // One point per cell, row by row. `offset` picks where inside of each cell.
+    fn cell_points<F: FnMut() -> (f32, f32)>(&self, mut offset: F) -> Vec<Tuple> {
+        let mut points = Vec::with_capacity(self.samples());
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                points.push(self.point_on_light(u, v, offset()));
+            }
+        }
+        points
+    }
+
+    fn center(&self) -> Tuple {
+        self.point_on_light(0, 0, (self.usteps as f32 / 2.0, self.vsteps as f32 / 2.0))
+    }
 }
 
 impl Light {
     pub fn new(intensity: Tuple, position: Tuple) -> Self {
         Light {
             intensity,
-            position 
+            position,
+            light_type: LightTypes::Point,
+            attenuation: Attenuation::none(),
+            direct_scale: 1.0
+        }
+    }
+
+    pub fn area(intensity: Tuple, area: AreaLight) -> Self {
+        Light {
+            intensity,
+            position: area.center(),
+            light_type: LightTypes::Area(area),
+            attenuation: Attenuation::none(),
+            direct_scale: 1.0
+        }
+    }
+
+    // A light infinitely far away, like the sun. All of its rays travel in `direction`, so it has
+    // no position and its shadows are parallel.
+    pub fn directional(intensity: Tuple, direction: Tuple) -> Self {
+        Light {
+            intensity,
+            position: Tuple::point(0.0, 0.0, 0.0),
+            light_type: LightTypes::Directional { direction: direction.norm() },
+            attenuation: Attenuation::none(),
+            direct_scale: 1.0
+        }
+    }
+
+    // A point light that only shines inside of a cone around `direction`. The angles are measured
+    // from the cone's axis in radians. Points inside inner_angle are fully lit and the light fades
+    // smoothly to nothing at outer_angle.
+    pub fn spot(intensity: Tuple, position: Tuple, direction: Tuple, inner_angle: f32, outer_angle: f32) -> Self {
+        Light {
+            intensity,
+            position,
+            light_type: LightTypes::Spot { direction: direction.norm(), inner_angle, outer_angle },
+            attenuation: Attenuation::none(),
+            direct_scale: 1.0
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    // Sets direct_scale from the light's power in watts, spread over the directions the light
+    // shines in, and switches to inverse square attenuation. The intensity only tints the light,
+    // and calling this again replaces the power rather than adding to it. Directional lights
+    // have no position to fall off from, so watts is used as the irradiance (W/m^2) at every
+    // point instead.
+    pub fn with_power(mut self, watts: f32) -> Self {
+        let solid_angle = match &self.light_type {
+            LightTypes::Point | LightTypes::Area(_) => 4.0 * PI,
+            LightTypes::Spot { outer_angle, .. } => 2.0 * PI * (1.0 - outer_angle.cos()),
+            LightTypes::Directional { .. } => 1.0
+        };
+        self.direct_scale = watts / solid_angle;
+        if let LightTypes::Directional { .. } = self.light_type {
+            self.attenuation = Attenuation::none();
+        } else {
+            self.attenuation = Attenuation::inverse_square();
+        }
+        self
+    }
+
+    // Multiplier for the light reaching a point `distance` away from it.
+    pub fn attenuation_at(&self, distance: f32) -> f32 {
+        match self.light_type {
+            LightTypes::Directional { .. } => 1.0,
+            _ => self.attenuation.factor(distance)
+        }
+    }
+
+    // Points that shadow rays are cast towards. The cells of an area light are jittered with rng
+    // when jitter is enabled. Directional lights have no position, so they have no points.
+    pub fn sample_points(&self, rng: &mut Rng) -> Vec<Tuple> {
+        match &self.light_type {
+            LightTypes::Point | LightTypes::Spot { .. } => vec![self.position],
+            LightTypes::Area(area) if area.jitter => area.cell_points(|| (rng.next_f32(), rng.next_f32())),
+            LightTypes::Area(area) => area.cell_points(|| (0.5, 0.5)),
+            LightTypes::Directional { .. } => Vec::new()
+        }
+    }
+
+    // Direction (normalized) and distance of every shadow ray that needs to be cast from point to
+    // find out how much of the light it can see.
+    pub fn shadow_rays(&self, point: Tuple, rng: &mut Rng) -> Vec<(Tuple, f32)> {
+        match &self.light_type {
+            LightTypes::Directional { direction } => vec![(-direction, f32::INFINITY)],
+            _ => self.sample_points(rng)
+                .iter()
+                .map(|p| {
+                    let v = p - &point;
+                    (v.norm(), v.mag())
+                })
+                .collect()
+        }
+    }
+
+    // One random direction (normalized) from point towards the light for next event estimation,
+    // the distance to the light along it, and the light arriving from it before the cosine at the
+    // surface is applied. Area lights are sampled uniformly over their whole rectangle.
+    pub fn sample_incident(&self, point: Tuple, rng: &mut Rng) -> (Tuple, f32, Tuple) {
+        let target = match &self.light_type {
+            LightTypes::Directional { direction } => {
+                return (-direction, f32::INFINITY, &self.intensity * self.direct_scale)
+            },
+            LightTypes::Area(area) => {
+                let u = ((rng.next_f32() * area.usteps as f32) as usize).min(area.usteps - 1);
+                let v = ((rng.next_f32() * area.vsteps as f32) as usize).min(area.vsteps - 1);
+                area.point_on_light(u, v, (rng.next_f32(), rng.next_f32()))
+            },
+            LightTypes::Point | LightTypes::Spot { .. } => self.position
+        };
+        let v = &target - &point;
+        let distance = v.mag();
+        let scale = self.attenuation_at(distance) * self.falloff(point) * self.direct_scale;
+        (v.norm(), distance, &self.intensity * scale)
+    }
+
+    // Directions from position towards the light used for the diffuse and specular terms, along
+    // with how much the light is attenuated on the way. Area lights average the contribution from
+    // the center of each of their cells.
+    fn light_vectors(&self, position: Tuple) -> Vec<(Tuple, f32)> {
+        let towards = |p: &Tuple| {
+            let v = p - &position;
+            (v.norm(), self.attenuation_at(v.mag()))
+        };
+        match &self.light_type {
+            LightTypes::Point | LightTypes::Spot { .. } => vec![towards(&self.position)],
+            LightTypes::Area(area) => area.cell_points(|| (0.5, 0.5)).iter().map(towards).collect(),
+            LightTypes::Directional { direction } => vec![(-direction, 1.0)]
+        }
+    }
+
+    // How much of the light reaches position because of the shape of the light itself, ignoring
+    // anything in the way. Only spot lights fall off, everything else is always 1.0.
+    pub fn falloff(&self, position: Tuple) -> f32 {
+        match &self.light_type {
+            LightTypes::Spot { direction, inner_angle, outer_angle } => {
+                let cos_angle = &(&position - &self.position).norm() * direction;
+                smoothstep(outer_angle.cos(), inner_angle.cos(), cos_angle)
+            },
+            _ => 1.0
         }
     }
 
     pub fn lighting(&self, material: &Material, position: Tuple, eyev: Tuple, normalv: Tuple) -> Tuple {
+        self.lighting_with_visibility(material, position, eyev, normalv, 1.0)
+    }
+
+    // A point in shadow only receives the ambient contribution.
+    pub fn lighting_with_shadow(&self, material: &Material, position: Tuple, eyev: Tuple, normalv: Tuple,
+                                in_shadow: bool) -> Tuple {
+        let visibility = if in_shadow { 0.0 } else { 1.0 };
+        self.lighting_with_visibility(material, position, eyev, normalv, visibility)
+    }
+
+    // visibility is the fraction of the light that can be seen from position, between 0.0 (fully
+    // in shadow) and 1.0 (fully lit). It scales the diffuse and specular contributions.
+    pub fn lighting_with_visibility(&self, material: &Material, position: Tuple, eyev: Tuple, normalv: Tuple,
+                                    visibility: f32) -> Tuple {
         // Combine the surface color with the light's color/intensity
         let effective_color = material.color.hadamard_product(self.intensity);
-        // Find the direction to the light source
-        let lightv = (&self.position - &position).norm();
         // Compute the ambient contribution
         let ambient = &effective_color * material.ambient;
-        // light_dot_normal represents the cosine of the angle between the light vector and the
-        // normal vector. A negative number means the light is on the other side of the surface.
-        let light_dot_normal = &lightv * &normalv;
-        // Initalize to black
-        let mut diffuse = Tuple::color(0.0, 0.0, 0.0);
-        // Initialize to black
-        let mut specular = Tuple::color(0.0, 0.0, 0.0);
-        if light_dot_normal >= 0.0 {
-            // Compute the diffuse contribution
-            diffuse = &(&effective_color * material.diffuse) * light_dot_normal;
-            // reflect_dot_eye represents the cosine of the angle between the reflection vector
-            // and the eye vector. A negative number means the light reflects away from the eye.
-            let reflectv = -&lightv.reflect(normalv);
-            let reflect_dot_eye = &reflectv * &eyev;
-            if reflect_dot_eye > 0.0 {
-                // Compute the specular contribution
-                let factor = reflect_dot_eye.powf(material.shininess);
-                specular = &(&self.intensity * material.specular) * factor;
+        let visibility = visibility * self.falloff(position);
+        if visibility <= 0.0 {
+            return ambient;
+        }
+
+        let lightvs = self.light_vectors(position);
+        let count = lightvs.len();
+        let mut sum = Tuple::color(0.0, 0.0, 0.0);
+        for (lightv, attenuation) in lightvs {
+            // light_dot_normal represents the cosine of the angle between the light vector and the
+            // normal vector. A negative number means the light is on the other side of the surface.
+            let light_dot_normal = &lightv * &normalv;
+            if light_dot_normal >= 0.0 {
+                // Compute the diffuse contribution
+                let diffuse = &(&effective_color * material.diffuse) * (light_dot_normal * attenuation);
+                sum = &sum + &diffuse;
+                // reflect_dot_eye represents the cosine of the angle between the reflection vector
+                // and the eye vector. A negative number means the light reflects away from the eye.
+                let reflectv = -&lightv.reflect(normalv);
+                let reflect_dot_eye = &reflectv * &eyev;
+                if reflect_dot_eye > 0.0 {
+                    // Compute the specular contribution
+                    let factor = reflect_dot_eye.powf(material.shininess);
+                    let specular = &(&self.intensity * material.specular) * (factor * attenuation);
+                    sum = &sum + &specular;
+                }
             }
         }
 
-        &(&ambient + &diffuse) + &specular
+        &ambient + &(&sum * (visibility * self.direct_scale / count as f32))
     }
 }
 
+// Hermite interpolation between 0.0 at edge0 and 1.0 at edge1.
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
 // Must overload PartialEq instead of leveraging Derive PartialEq on the Light struct. This is
 // because we have a custom implementation.
 // TODO: Refactor to use f_equal (otherwise you'd just want to derive partialeq)
 impl PartialEq for Light {
     fn eq(&self, other: &Light) -> bool {
-        // Assumes that all nested vectors are the same length.
-        if self.position == other.position && self.intensity == other.intensity {
-            true
-        } else {
-            false
-        } 
+        self.position == other.position && self.intensity == other.intensity &&
+            self.light_type == other.light_type && self.attenuation == other.attenuation &&
+            self.direct_scale == other.direct_scale
     }
 }
 impl Eq for Light {}
@@ -65,6 +354,7 @@ impl Eq for Light {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
 
     #[test]
     fn create_light() {
@@ -151,6 +441,265 @@ mod tests {
         )
     }
 
+    #[test]
+    fn lighting_with_surface_in_shadow() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0));
+        let result = light.lighting_with_shadow(&m, position, eyev, normalv, true);
+        let expected = Tuple::color(0.1, 0.1, 0.1);
+        assert!(
+            result == expected,
+            "A surface in shadow should only be lit by the ambient light."
+        )
+    }
+
+    #[test]
+    fn lighting_with_partial_visibility() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0));
+        let result = light.lighting_with_visibility(&m, position, eyev, normalv, 0.5);
+        let expected = Tuple::color(1.0, 1.0, 1.0);
+        assert!(
+            result == expected,
+            "Half visibility should halve the diffuse and specular contributions. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn create_area_light() {
+        let area = AreaLight::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(2.0, 0.0, 0.0), 4,
+                                  Tuple::vector(0.0, 0.0, 1.0), 2);
+        let light = Light::area(Tuple::color(1.0, 1.0, 1.0), area);
+        assert!(
+            area.uvec == Tuple::vector(0.5, 0.0, 0.0) && area.vvec == Tuple::vector(0.0, 0.0, 0.5) &&
+            area.samples() == 8 && light.position == Tuple::point(1.0, 0.0, 0.5),
+            "The area light was not created correctly."
+        )
+    }
+
+    #[test]
+    fn area_light_from_cells() {
+        let corner = Tuple::point(0.0, 0.0, 0.0);
+        let area = AreaLight::from_cells(corner, Tuple::vector(0.5, 0.0, 0.0), 4, Tuple::vector(0.0, 0.0, 0.5), 2);
+        assert!(
+            area == AreaLight::new(corner, Tuple::vector(2.0, 0.0, 0.0), 4, Tuple::vector(0.0, 0.0, 1.0), 2) &&
+            area.usteps() == 4 && area.vsteps() == 2,
+            "An area light built from its cells should match one built from its edges."
+        )
+    }
+
+    #[test]
+    fn find_point_on_area_light() {
+        let area = AreaLight::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(2.0, 0.0, 0.0), 4,
+                                  Tuple::vector(0.0, 0.0, 1.0), 2);
+        assert!(
+            area.point_on_light(0, 0, (0.5, 0.5)) == Tuple::point(0.25, 0.0, 0.25) &&
+            area.point_on_light(1, 0, (0.5, 0.5)) == Tuple::point(0.75, 0.0, 0.25) &&
+            area.point_on_light(2, 1, (0.5, 0.5)) == Tuple::point(1.25, 0.0, 0.75) &&
+            area.point_on_light(3, 1, (0.5, 0.5)) == Tuple::point(1.75, 0.0, 0.75),
+            "The points on the area light were not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn jittered_points_stay_in_cells() {
+        let area = AreaLight::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(2.0, 0.0, 0.0), 4,
+                                  Tuple::vector(0.0, 0.0, 1.0), 2);
+        let light = Light::area(Tuple::color(1.0, 1.0, 1.0), area);
+        let mut rng = Rng::new(1);
+        let points = light.sample_points(&mut rng);
+        assert!(
+            points.len() == 8 &&
+            points[0].x >= 0.0 && points[0].x < 0.5 && points[0].z >= 0.0 && points[0].z < 0.5 &&
+            points[7].x >= 1.5 && points[7].x < 2.0 && points[7].z >= 0.5 && points[7].z < 1.0,
+            "The jittered points left their cells. Result: {:?}", points
+        )
+    }
+
+    #[test]
+    fn lighting_samples_area_light() {
+        let mut area = AreaLight::new(Tuple::point(-0.5, -0.5, -5.0), Tuple::vector(1.0, 0.0, 0.0), 2,
+                                      Tuple::vector(0.0, 1.0, 0.0), 2);
+        area.jitter = false;
+        let light = Light::area(Tuple::color(1.0, 1.0, 1.0), area);
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        let eye = Tuple::point(0.0, 0.0, -5.0);
+        let cases = [
+            (Tuple::point(0.0, 0.0, -1.0), Tuple::color(0.9965, 0.9965, 0.9965)),
+            (Tuple::point(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2), Tuple::color(0.62318, 0.62318, 0.62318))
+        ];
+        for (point, expected) in cases.iter() {
+            let eyev = (&eye - point).norm();
+            let normalv = Tuple::vector(point.x, point.y, point.z);
+            let result = light.lighting_with_visibility(&m, *point, eyev, normalv, 1.0);
+            assert!(
+                (result.x - expected.x).abs() < 0.0001,
+                "The area light was not sampled correctly. Result: {:?}, Expected: {:?}", result, expected
+            )
+        }
+    }
+
+    #[test]
+    fn directional_light_matches_distant_point_light() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let sun = Light::directional(Tuple::color(1.0, 1.0, 1.0), Tuple::vector(0.0, -1.0, 1.0));
+        let point = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 10.0, -10.0));
+        let result = sun.lighting(&m, position, eyev, normalv);
+        assert!(
+            result == point.lighting(&m, position, eyev, normalv) &&
+            result == sun.lighting(&m, Tuple::point(100.0, -50.0, 3.0), eyev, normalv),
+            "A directional light should light every point from the same direction. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn directional_shadow_rays_are_parallel() {
+        let sun = Light::directional(Tuple::color(1.0, 1.0, 1.0), Tuple::vector(0.0, -2.0, 0.0));
+        let mut rng = Rng::new(0);
+        let a = sun.shadow_rays(Tuple::point(0.0, 0.0, 0.0), &mut rng);
+        let b = sun.shadow_rays(Tuple::point(5.0, 1.0, -3.0), &mut rng);
+        assert!(
+            a.len() == 1 && a[0].0 == Tuple::vector(0.0, 1.0, 0.0) && a[0].0 == b[0].0 && a[0].1 == f32::INFINITY,
+            "The shadow rays of a directional light should be parallel and unbounded."
+        )
+    }
+
+    #[test]
+    fn spot_light_falloff() {
+        let spot = Light::spot(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, -1.0, 0.0),
+                               0.2, 0.4);
+        // Angles from the cone axis for points on the y = 0 plane.
+        let inside = spot.falloff(Tuple::point(0.0, 0.0, 0.0));
+        let edge = spot.falloff(Tuple::point(10.0 * 0.3_f32.tan(), 0.0, 0.0));
+        let outside = spot.falloff(Tuple::point(10.0 * 0.5_f32.tan(), 0.0, 0.0));
+        assert!(
+            inside == 1.0 && edge > 0.0 && edge < 1.0 && outside == 0.0,
+            "The spot light did not fall off correctly. Inside: {}, edge: {}, outside: {}", inside, edge, outside
+        )
+    }
+
+    #[test]
+    fn lighting_outside_of_spot_cone() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let spot = Light::spot(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0), Tuple::vector(0.0, 1.0, 0.0),
+                               0.2, 0.4);
+        let result = spot.lighting(&m, position, eyev, normalv);
+        assert!(
+            result == Tuple::color(0.1, 0.1, 0.1),
+            "A point outside of the spot light's cone should only get ambient light. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn default_light_is_not_attenuated() {
+        let m = Material::new();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let near = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0));
+        let far = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -1000.0));
+        assert!(
+            near.attenuation == Attenuation::none() &&
+            near.lighting(&m, position, eyev, normalv) == far.lighting(&m, position, eyev, normalv),
+            "Lights should not be attenuated by default."
+        )
+    }
+
+    #[test]
+    fn attenuation_factors() {
+        let a = Attenuation::new(1.0, 0.5, 0.25);
+        assert!(
+            a.factor(0.0) == 1.0 && a.factor(2.0) == 1.0 / 3.0 &&
+            Attenuation::inverse_square().factor(4.0) == 1.0 / 16.0,
+            "The attenuation factors were not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn inverse_square_attenuation() {
+        let mut m = Material::new();
+        m.ambient = 0.0;
+        m.specular = 0.0;
+        m.diffuse = 1.0;
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let near = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -2.0))
+            .with_attenuation(Attenuation::inverse_square());
+        let far = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -4.0))
+            .with_attenuation(Attenuation::inverse_square());
+        let near_result = near.lighting(&m, position, eyev, normalv);
+        let far_result = far.lighting(&m, position, eyev, normalv);
+        assert!(
+            near_result == Tuple::color(0.25, 0.25, 0.25) && far_result == Tuple::color(0.0625, 0.0625, 0.0625),
+            "Doubling the distance should quarter the light. Near: {:?}, far: {:?}", near_result, far_result
+        )
+    }
+
+    #[test]
+    fn directional_light_ignores_attenuation() {
+        let sun = Light::directional(Tuple::color(1.0, 1.0, 1.0), Tuple::vector(0.0, -1.0, 0.0))
+            .with_attenuation(Attenuation::inverse_square());
+        assert!(
+            sun.attenuation_at(1000.0) == 1.0,
+            "Directional lights should never be attenuated."
+        )
+    }
+
+    #[test]
+    fn point_light_power() {
+        let light = Light::new(Tuple::color(1.0, 0.5, 0.5), Tuple::point(0.0, 0.0, 0.0)).with_power(100.0);
+        let twice = Light::new(Tuple::color(1.0, 0.5, 0.5), Tuple::point(0.0, 0.0, 0.0)).with_power(50.0).with_power(100.0);
+        let expected = 100.0 / (4.0 * PI);
+        assert!(
+            light.intensity == Tuple::color(1.0, 0.5, 0.5) && light.direct_scale == expected &&
+            light.attenuation == Attenuation::inverse_square() && twice == light,
+            "The power of the light was not converted to a scale correctly. Result: {}", light.direct_scale
+        )
+    }
+
+    #[test]
+    fn power_leaves_ambient_alone() {
+        let light = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0));
+        let mut m = Material::new();
+        m.diffuse = 0.0;
+        m.specular = 0.0;
+        let (eyev, normalv) = (Tuple::vector(0.0, 0.0, -1.0), Tuple::vector(0.0, 0.0, -1.0));
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let plain = light.lighting(&m, position, eyev, normalv);
+        let powered = light.with_power(1000.0).lighting(&m, position, eyev, normalv);
+        assert!(
+            plain == powered && plain == Tuple::color(0.1, 0.1, 0.1),
+            "The power of a light should not change the ambient term. Result: {:?}", powered
+        )
+    }
+
+    #[test]
+    fn spot_light_power_is_concentrated() {
+        let point = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, 0.0)).with_power(100.0);
+        let spot = Light::spot(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, -1.0, 0.0),
+                               0.2, 0.4).with_power(100.0);
+        assert!(
+            spot.direct_scale > point.direct_scale,
+            "A spot light should be brighter than a point light with the same power."
+        )
+    }
+
     #[test]
     fn lighting_equality() {
         let l1 = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, 10.0));
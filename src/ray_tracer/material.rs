@@ -1,12 +1,19 @@
 use crate::ray_tracer::tuple::Tuple;
 use crate::ray_tracer::common::f_equal;
+use crate::ray_tracer::bsdf::Bsdf;
 
 pub struct Material {
     pub color: Tuple,
     pub ambient: f32,
     pub diffuse: f32,
     pub specular: f32,
-    pub shininess: f32
+    pub shininess: f32,
+    // Light given off by the surface itself, black for surfaces that don't glow. Any object with
+    // some emission is a light source for the path tracer.
+    pub emission: Tuple,
+    // How the path tracer scatters light off of the surface. Without one the surface is
+    // Lambertian with an albedo of color * diffuse. The Phong integrator ignores it.
+    pub bsdf: Option<Bsdf>
 }
 
 impl Material {
@@ -15,6 +22,17 @@ impl Material {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn path_tracing_bsdf(&self) -> Bsdf {
+        match self.bsdf {
+            Some(bsdf) => bsdf,
+            None => Bsdf::Lambert { albedo: &self.color * self.diffuse }
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission.x > 0.0 || self.emission.y > 0.0 || self.emission.z > 0.0
+    }
 }
 
 impl Default for Material {
@@ -24,7 +42,9 @@ impl Default for Material {
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
-            shininess: 200.0
+            shininess: 200.0,
+            emission: Tuple::color(0.0, 0.0, 0.0),
+            bsdf: None
         }
     }
 }
@@ -33,14 +53,10 @@ impl Default for Material {
 // because we have a custom implementation for comparing floating point numbers f_equal.
 impl PartialEq for Material {
     fn eq(&self, other: &Material) -> bool {
-        if f_equal(self.ambient, other.ambient) && self.color == other.color &&
+        f_equal(self.ambient, other.ambient) && self.color == other.color &&
             f_equal(self.diffuse, other.diffuse) && f_equal(self.shininess, other.shininess) &&
-            f_equal(self.specular, other.specular) {
-                true
-            }
-        else {
-            false
-        }
+            f_equal(self.specular, other.specular) && self.emission == other.emission &&
+            self.bsdf == other.bsdf
     }
 }
 impl Eq for Material {}
@@ -57,7 +73,8 @@ mod tests {
             m.ambient == 0.1 &&
             m.diffuse == 0.9 &&
             m.specular == 0.9 &&
-            m.shininess == 200.0,
+            m.shininess == 200.0 &&
+            m.emission == Tuple::color(0.0, 0.0, 0.0),
             "The creation of the material struct did not happen correctly."
         )
     }
@@ -1,12 +1,43 @@
 use crate::ray_tracer::common::f_equal;
+use crate::ray_tracer::decomposition::Decomposition;
 use crate::ray_tracer::tuple::Tuple;
+use std::error::Error;
+use std::fmt;
 use std::ops::Mul;
 use std::f32;
 
-#[derive(Clone)]
+#[derive(Debug, PartialEq)]
+pub enum MatrixError {
+    // Matrices have 4 rows of 4 numbers.
+    WrongRowCount(usize),
+    WrongRowLength { row: usize, length: usize },
+    // The matrix squashes space flat, so it can't be undone.
+    Singular,
+    // The bottom row isn't 0 0 0 1, so the matrix does more than move, turn and stretch.
+    NotAffine
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatrixError::WrongRowCount(rows) => write!(f, "Expected 4 rows, found {}.", rows),
+            MatrixError::WrongRowLength { row, length } => {
+                write!(f, "Expected 4 numbers in row {}, found {}.", row, length)
+            },
+            MatrixError::Singular => write!(f, "The matrix is not invertible."),
+            MatrixError::NotAffine => write!(f, "The matrix is not an affine transform.")
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
+// A 4x4 transformation matrix. It lives on the stack and is cheap to copy, since it is used for
+// every ray that is tested against an object.
+#[derive(Clone, Copy, Debug)]
 pub struct Matrix {
     // [row][col]
-    pub value: Vec<Vec<f32>>
+    pub value: [[f32; 4]; 4]
 }
 
 pub enum RotationAxis {
@@ -15,83 +46,200 @@ pub enum RotationAxis {
     Z
 }
 
-// TODO: Add fluent API for matrix transformations
 impl Matrix {
-    pub fn new(value: Vec<Vec<f32>>) -> Matrix {
-        // TODO: Add validation for all inner vectors being the same length. (Panic)
+    pub fn new(value: [[f32; 4]; 4]) -> Matrix {
         Matrix {
             value
         }
     }
+
+    // For rows whose size is only known at runtime, like ones read from a file.
+    pub fn from_rows(rows: &[Vec<f32>]) -> Result<Matrix, MatrixError> {
+        if rows.len() != 4 {
+            return Err(MatrixError::WrongRowCount(rows.len()));
+        }
+        let mut value = [[0.0; 4]; 4];
+        for (i, (row, values)) in rows.iter().zip(value.iter_mut()).enumerate() {
+            if row.len() != 4 {
+                return Err(MatrixError::WrongRowLength { row: i, length: row.len() });
+            }
+            values.copy_from_slice(row);
+        }
+        Ok(Matrix::new(value))
+    }
     
     // TODO: Maybe turn these functions that output a matrix into a single function that takes an enum.
     pub fn identity() -> Matrix {
-        Matrix::new(vec![
-            vec![1.0, 0.0, 0.0, 0.0],
-            vec![0.0, 1.0, 0.0, 0.0],
-            vec![0.0, 0.0, 1.0, 0.0],
-            vec![0.0, 0.0, 0.0, 1.0]
+        Matrix::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
         ])
     }
 
     pub fn translation(x: f32, y: f32, z: f32) -> Matrix {
-        Matrix::new(vec![
-            vec![1.0, 0.0, 0.0, x],
-            vec![0.0, 1.0, 0.0, y],
-            vec![0.0, 0.0, 1.0, z],
-            vec![0.0, 0.0, 0.0, 1.0]
+        Matrix::new([
+            [1.0, 0.0, 0.0, x],
+            [0.0, 1.0, 0.0, y],
+            [0.0, 0.0, 1.0, z],
+            [0.0, 0.0, 0.0, 1.0]
         ])
     }
 
     pub fn scaling(x: f32, y: f32, z: f32) -> Matrix {
-        Matrix::new(vec![
-            vec![x, 0.0, 0.0, 0.0],
-            vec![0.0, y, 0.0, 0.0],
-            vec![0.0, 0.0, z, 0.0],
-            vec![0.0, 0.0, 0.0, 1.0]
+        Matrix::new([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
         ])
     }
 
     pub fn shearing(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Matrix {
-        Matrix::new(vec![
-            vec![1.0, xy, xz, 0.0],
-            vec![yx, 1.0, yz, 0.0],
-            vec![zx, zy, 1.0, 0.0],
-            vec![0.0, 0.0, 0.0, 1.0]
+        Matrix::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
         ])
     }
 
     pub fn rotation(radians: f32, axis: RotationAxis) -> Matrix {
+        let (sin, cos) = radians.sin_cos();
         match axis {
             RotationAxis::X => {
-                Matrix::new(vec![
-                    vec![1.0, 0.0, 0.0, 0.0],
-                    vec![0.0, radians.cos(), -radians.sin(), 0.0],
-                    vec![0.0, radians.sin(), radians.cos(), 0.0],
-                    vec![0.0, 0.0, 0.0, 1.0]
-            ])}
+                Matrix::new([
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, cos, -sin, 0.0],
+                    [0.0, sin, cos, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            },
             RotationAxis::Y => {
-                Matrix::new(vec![
-                    vec![radians.cos(), 0.0, radians.sin(), 0.0],
-                    vec![0.0, 1.0, 0.0, 0.0],
-                    vec![-radians.sin(), 0.0, radians.cos(), 0.0],
-                    vec![0.0, 0.0, 0.0, 1.0]
+                Matrix::new([
+                    [cos, 0.0, sin, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [-sin, 0.0, cos, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
                 ])
             },
             RotationAxis::Z => {
-                Matrix::new(vec![
-                    vec![radians.cos(), -radians.sin(), 0.0, 0.0],
-                    vec![radians.sin(), radians.cos(), 0.0, 0.0],
-                    vec![0.0, 0.0, 1.0, 0.0],
-                    vec![0.0, 0.0, 0.0, 1.0]
+                Matrix::new([
+                    [cos, -sin, 0.0, 0.0],
+                    [sin, cos, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
                 ])
             },
         }
     }
 
+    // Orients the world relative to an eye at `from` looking towards `to`. `up` only needs to be
+    // roughly upwards, it is corrected to be perpendicular to the forward vector.
+    pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix {
+        let forward = (&to - &from).norm();
+        let left = forward.cross(up.norm());
+        let true_up = left.cross(forward);
+        let orientation = Matrix::new([
+            [left.x, left.y, left.z, 0.0],
+            [true_up.x, true_up.y, true_up.z, 0.0],
+            [-forward.x, -forward.y, -forward.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ]);
+
+        &orientation * &Matrix::translation(-from.x, -from.y, -from.z)
+    }
+
+    // Turns by radians around axis, which doesn't need to be normalized. Positive angles turn
+    // the same way as the rotations around x, y and z.
+    pub fn axis_rotation(axis: Tuple, radians: f32) -> Matrix {
+        let a = axis.norm();
+        let (sin, cos) = radians.sin_cos();
+        let t = 1.0 - cos;
+        Matrix::new([
+            [t * a.x * a.x + cos, t * a.x * a.y - sin * a.z, t * a.x * a.z + sin * a.y, 0.0],
+            [t * a.x * a.y + sin * a.z, t * a.y * a.y + cos, t * a.y * a.z - sin * a.x, 0.0],
+            [t * a.x * a.z - sin * a.y, t * a.y * a.z + sin * a.x, t * a.z * a.z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    // Turns around x first, then y, then z, all around the fixed world axes.
+    pub fn euler_rotation(x: f32, y: f32, z: f32) -> Matrix {
+        Matrix::identity().rotate_x(x).rotate_y(y).rotate_z(z)
+    }
+
+    // The angles euler_rotation was given, for a matrix that only rotates. When y is a quarter turn
+    // x and z turn around the same axis, so z is taken to be 0.
+    pub fn euler_angles(&self) -> (f32, f32, f32) {
+        let m = &self.value;
+        let y = (-m[2][0]).clamp(-1.0, 1.0).asin();
+        if m[2][0].abs() < 1.0 - 1.0e-6 {
+            (m[2][1].atan2(m[2][2]), y, m[1][0].atan2(m[0][0]))
+        } else {
+            ((-m[1][2]).atan2(m[1][1]), y, 0.0)
+        }
+    }
+
+    // Places something at from with its -z axis pointing at to and its y axis roughly along up,
+    // without stretching it. When up is perpendicular to the direction this undoes view_transform,
+    // which moves the world in front of an eye instead.
+    pub fn look_at(from: Tuple, to: Tuple, up: Tuple) -> Matrix {
+        let forward = (&to - &from).norm();
+        let left = forward.cross(up.norm()).norm();
+        let true_up = left.cross(forward);
+        Matrix::new([
+            [left.x, true_up.x, -forward.x, from.x],
+            [left.y, true_up.y, -forward.y, from.y],
+            [left.z, true_up.z, -forward.z, from.z],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    // Chainable versions of the constructors, applied after the transform they're called on, so
+    // Matrix::identity().scale(2.0, 2.0, 2.0).translate(0.0, 1.0, 0.0) scales and then moves,
+    // in the order it reads.
+    pub fn translate(self, x: f32, y: f32, z: f32) -> Matrix {
+        &Matrix::translation(x, y, z) * &self
+    }
+
+    pub fn scale(self, x: f32, y: f32, z: f32) -> Matrix {
+        &Matrix::scaling(x, y, z) * &self
+    }
+
+    pub fn shear(self, xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Matrix {
+        &Matrix::shearing(xy, xz, yx, yz, zx, zy) * &self
+    }
+
+    pub fn rotate_x(self, radians: f32) -> Matrix {
+        &Matrix::rotation(radians, RotationAxis::X) * &self
+    }
+
+    pub fn rotate_y(self, radians: f32) -> Matrix {
+        &Matrix::rotation(radians, RotationAxis::Y) * &self
+    }
+
+    pub fn rotate_z(self, radians: f32) -> Matrix {
+        &Matrix::rotation(radians, RotationAxis::Z) * &self
+    }
+
+    pub fn rotate_around(self, axis: Tuple, radians: f32) -> Matrix {
+        &Matrix::axis_rotation(axis, radians) * &self
+    }
+
+    pub fn rotate_euler(self, x: f32, y: f32, z: f32) -> Matrix {
+        &Matrix::euler_rotation(x, y, z) * &self
+    }
+
+    // Applies other after this transform.
+    pub fn then(self, other: &Matrix) -> Matrix {
+        other * &self
+    }
+
     pub fn transpose(mut self) -> Matrix {
-        for i in 0..self.value.len() {
-            for j in i..self.value.len() {
+        for i in 0..4 {
+            for j in i + 1..4 {
                 let temp = self.value[j][i];
                 self.value[j][i] = self.value[i][j];
                 self.value[i][j] = temp;
@@ -101,30 +249,80 @@ impl Matrix {
     }
 
     pub fn determinant(&self) -> f32 {
-        determinant(&self)
+        let (s, c) = self.minors();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
     }
 
+    // Panics if the matrix is not invertible, see try_inverse.
     pub fn inverse(&self) -> Matrix {
-        if !self.is_invertible() {
-            panic!("The matrix is not invertible!");
+        match self.try_inverse() {
+            Ok(inverse) => inverse,
+            Err(_) => panic!("The matrix is not invertible!")
         }
+    }
 
-        let mut m = self.clone();
-        let d = self.determinant();
-
-        for i in 0..self.value.len() {
-            for j in 0..self.value[i].len() {
-                let c = cofactor(self, i, j);
-                m.value[j][i] = c / d;
-            }
+    // Built from the 2x2 determinants of the top and bottom two rows, which each cofactor is a
+    // sum of, instead of expanding every cofactor on its own. A matrix is singular when its
+    // determinant is 0, or so close to it that the inverse doesn't fit in an f32.
+    pub fn try_inverse(&self) -> Result<Matrix, MatrixError> {
+        let m = &self.value;
+        let (s, c) = self.minors();
+        let determinant = self.determinant();
+        if determinant == 0.0 || !determinant.is_finite() {
+            return Err(MatrixError::Singular);
         }
+        let d = 1.0 / determinant;
+        let inverse = Matrix::new([
+            [
+                (m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3]) * d,
+                (-m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3]) * d,
+                (m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3]) * d,
+                (-m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3]) * d
+            ],
+            [
+                (-m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1]) * d,
+                (m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1]) * d,
+                (-m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1]) * d,
+                (m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1]) * d
+            ],
+            [
+                (m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0]) * d,
+                (-m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0]) * d,
+                (m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0]) * d,
+                (-m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0]) * d
+            ],
+            [
+                (-m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0]) * d,
+                (m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0]) * d,
+                (-m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0]) * d,
+                (m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0]) * d
+            ]
+        ]);
+        if inverse.value.iter().flatten().all(|v| v.is_finite()) {
+            Ok(inverse)
+        } else {
+            Err(MatrixError::Singular)
+        }
+    }
 
-        m
+    pub fn is_invertible(&self) -> bool {
+        self.try_inverse().is_ok()
+    }
+
+    // The translation, rotation, scale and shear that build this matrix.
+    pub fn decompose(&self) -> Result<Decomposition, MatrixError> {
+        Decomposition::from_matrix(self)
     }
 
-    // This might not need to be on the matrix's public API
-    fn is_invertible(&self) -> bool {
-        !f_equal(self.determinant(), 0.0) 
+    // The determinants of every pair of columns in the top two rows (s) and the bottom two rows
+    // (c), in the same order, so that s[i] and c[5 - i] cover complementary columns.
+    fn minors(&self) -> ([f32; 6], [f32; 6]) {
+        let m = &self.value;
+        let pair = |r: usize, a: usize, b: usize| m[r][a] * m[r + 1][b] - m[r + 1][a] * m[r][b];
+        (
+            [pair(0, 0, 1), pair(0, 0, 2), pair(0, 0, 3), pair(0, 1, 2), pair(0, 1, 3), pair(0, 2, 3)],
+            [pair(2, 0, 1), pair(2, 0, 2), pair(2, 0, 3), pair(2, 1, 2), pair(2, 1, 3), pair(2, 2, 3)]
+        )
     }
 }
 
@@ -132,19 +330,7 @@ impl Matrix {
 // because we have a custom implementation for comparing floating point numbers f_equal.
 impl PartialEq for Matrix {
     fn eq(&self, other: &Matrix) -> bool {
-        // Assumes that all nested vectors are the same length.
-        if (&self.value.len() == &other.value.len()) & (&self.value[0].len() == &other.value[0].len()) {
-            for i in 0..self.value.len() {
-                for j in 0..self.value[i].len() {
-                    if !f_equal(self.value[i][j], other.value[i][j]) {
-                        return false
-                    }
-                }
-            }
-            true
-        } else {
-            false
-        } 
+        self.value.iter().flatten().zip(other.value.iter().flatten()).all(|(a, b)| f_equal(*a, *b))
     }
 }
 impl Eq for Matrix {}
@@ -153,11 +339,9 @@ impl Mul for &Matrix {
     type Output = Matrix;
 
     fn mul(self, other: &Matrix) -> Matrix {
-        
-        let mut m = self.clone();
-
-        for i in 0..m.value.len() {
-            for j in 0..m.value[i].len() {
+        let mut m = Matrix::new([[0.0; 4]; 4]);
+        for i in 0..4 {
+            for j in 0..4 {
                 m.value[i][j] = self.value[i][0] * other.value[0][j] +
                             self.value[i][1] * other.value[1][j] +
                             self.value[i][2] * other.value[2][j] +
@@ -168,72 +352,21 @@ impl Mul for &Matrix {
     }
 }
 
-// This is super janky. There must be a better way to do this. Only works for 4x4 matrices/
+// Transforms are affine, so the bottom row is always 0 0 0 1 and a point stays a point and a vector
+// stays a vector.
 impl Mul<&Tuple> for &Matrix {
     type Output = Tuple;
 
     fn mul(self, other: &Tuple) -> Tuple {
-
         let w = match other.w {
             Some(0) => 0.0,
             Some(1) => 1.0,
             _ => panic!("This operation can't be performed on a color."),
         };
-
-        let mut t = Tuple::point(0.0, 0.0, 0.0);
-        for i in 0..self.value.len() {
-            let value = self.value[i][0] * other.x +
-                    self.value[i][1] * other.y +
-                    self.value[i][2] * other.z +
-                    self.value[i][3] * w;
-            
-            match i {
-                0 => t.x = value,
-                1 => t.y = value,
-                2 => t.z = value,
-                3 => t.w = Some(value as u8),
-                _ => panic!("Matrix and Tuple have mismatched sizes.")
-            }
-        }
-        t
-    }
-}
-
-fn determinant(matrix: &Matrix) -> f32 {
-    // Assumes the matrix will always be square
-    let dim = matrix.value.len();
-    match dim {
-        2 => matrix.value[0][0] * matrix.value[1][1] - matrix.value[0][1] * matrix.value[1][0],
-        3 | 4 => {
-            let mut det = 0.0;
-            for i in 0..matrix.value.len() {
-                det = det + matrix.value[0][i] * cofactor(&matrix, 0, i);
-            }
-            det
-        },
-        _ => panic!("Can only take the determinant of 2x2, 3x3, or 4x4 matrices."),
-    }
-}
-
-// Takes in a reference to a matrix and clones it internally to return a new submatrix.
-fn submatrix(matrix: &Matrix, row: usize, col: usize) -> Matrix {
-    let mut sub = matrix.clone();
-    sub.value.remove(row);
-    for i in 0..sub.value.len() {
-        sub.value[i].remove(col);
-    }
-    sub
-}
-
-fn minor(matrix: &Matrix, row: usize, col: usize) -> f32 {    
-    determinant(&submatrix(&matrix, row, col))
-}
-
-fn cofactor(matrix: &Matrix, row: usize, col: usize) -> f32 {
-    if (row + col) % 2 == 0 {
-        minor(&matrix, row, col)
-    } else {
-        -minor(&matrix, row, col)
+        let row = |i: usize| {
+            self.value[i][0] * other.x + self.value[i][1] * other.y + self.value[i][2] * other.z + self.value[i][3] * w
+        };
+        Tuple { x: row(0), y: row(1), z: row(2), w: other.w }
     }
 }
 
@@ -243,11 +376,11 @@ mod tests {
 
     #[test]
     fn create_4x4_matrix() {
-        let m = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![5.5, 6.5, 7.5, 8.5],
-            vec![9.0, 10.0, 11.0, 12.0],
-            vec![13.5, 14.5, 15.5, 16.5]]
+        let m = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.5, 6.5, 7.5, 8.5],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.5, 14.5, 15.5, 16.5]]
         );
         assert!(
             m.value[0][0] == 1.0 && m.value[0][3] == 4.0 && m.value[1][0] == 5.5 && m.value[1][2] == 7.5 && 
@@ -256,44 +389,19 @@ mod tests {
         );
     }
 
-    #[test]
-    fn create_2x2_matrix() {
-        let m = Matrix::new(vec![
-            vec![-3.0, 5.0],
-            vec![1.0, -2.0]
-        ]);
-        assert!(
-            m.value[0][0] == -3.0 && m.value[0][1] == 5.0 && m.value[1][0] == 1.0 && m.value[1][1] == -2.0,
-            "The 2x2 matrix was not created correctly."
-        );
-    }
-
-    #[test]
-    fn create_3x3_matrix() {
-        let m = Matrix::new(vec![
-            vec![-3.0, 5.0, 0.0],
-            vec![1.0, -2.0, -7.0],
-            vec![0.0, 1.0, 1.0]
-        ]);
-        assert!(
-            m.value[0][0] == -3.0 && m.value[1][1] == -2.0 && m.value[2][2] == 1.0,
-            "The 3x3 matrix was not created correctly."
-        );
-    }
-
     #[test]
     fn matricies_are_equal() {
-        let m1 = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![5.0, 6.0, 7.0, 8.0],
-            vec![9.0, 8.0, 7.0, 6.0],
-            vec![5.0, 4.0, 3.0, 2.0]
+        let m1 = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0]
         ]);
-        let m2 = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![5.0, 6.0, 7.0, 8.0],
-            vec![9.0, 8.0, 7.0, 6.0],
-            vec![5.0, 4.0, 3.0, 2.0]
+        let m2 = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0]
         ]);
         assert!(
             m1 == m2,
@@ -302,43 +410,43 @@ mod tests {
     }
     #[test]
     fn matricies_are_not_equal() {
-        let m1 = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![5.0, 6.0, 7.0, 8.0],
-            vec![9.0, 8.0, 7.0, 6.0],
-            vec![5.0, 4.0, 3.0, 2.0]
+        let m1 = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0]
         ]);
-        let m2 = Matrix::new(vec![
-            vec![2.0, 3.0, 4.0, 5.0],
-            vec![6.0, 7.0, 8.0, 9.0],
-            vec![8.0, 7.0, 6.0, 5.0],
-            vec![4.0, 3.0, 2.0, 1.0]
+        let m2 = Matrix::new([
+            [2.0, 3.0, 4.0, 5.0],
+            [6.0, 7.0, 8.0, 9.0],
+            [8.0, 7.0, 6.0, 5.0],
+            [4.0, 3.0, 2.0, 1.0]
         ]);
-        assert_eq!(
-            m1 == m2, false,
+        assert!(
+            m1 != m2,
             "The matrices are not equal!"
         );
     }
 
     #[test]
     fn multiply_two_matrices() {
-        let a = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![5.0, 6.0, 7.0, 8.0],
-            vec![9.0, 8.0, 7.0, 6.0],
-            vec![5.0, 4.0, 3.0, 2.0]
+        let a = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0]
         ]);
-        let b = Matrix::new(vec![
-            vec![-2.0, 1.0, 2.0, 3.0],
-            vec![3.0, 2.0, 1.0, -1.0],
-            vec![4.0, 3.0, 6.0, 5.0],
-            vec![1.0, 2.0, 7.0, 8.0]
+        let b = Matrix::new([
+            [-2.0, 1.0, 2.0, 3.0],
+            [3.0, 2.0, 1.0, -1.0],
+            [4.0, 3.0, 6.0, 5.0],
+            [1.0, 2.0, 7.0, 8.0]
         ]);
-        let result = Matrix::new(vec![
-            vec![20.0, 22.0, 50.0, 48.0],
-            vec![44.0, 54.0, 114.0, 108.0],
-            vec![40.0, 58.0, 110.0, 102.0],
-            vec![16.0, 26.0, 46.0, 42.0]
+        let result = Matrix::new([
+            [20.0, 22.0, 50.0, 48.0],
+            [44.0, 54.0, 114.0, 108.0],
+            [40.0, 58.0, 110.0, 102.0],
+            [16.0, 26.0, 46.0, 42.0]
         ]);
         assert!(
             &a * &b == result,
@@ -348,11 +456,11 @@ mod tests {
 
     #[test]
     fn multiply_tuple_and_matrix() {
-        let a = Matrix::new(vec![
-            vec![1.0, 2.0, 3.0, 4.0],
-            vec![2.0, 4.0, 4.0, 2.0],
-            vec![8.0, 6.0, 4.0, 1.0],
-            vec![0.0, 0.0, 0.0, 1.0]
+        let a = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [2.0, 4.0, 4.0, 2.0],
+            [8.0, 6.0, 4.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0]
         ]);
         let b = Tuple::point(1.0, 2.0, 3.0);
         let result = Tuple::point(18.0, 24.0, 33.0);
@@ -364,11 +472,11 @@ mod tests {
 
     #[test]
     fn multiply_matrix_by_identity_matrix() {
-        let a = Matrix::new(vec![
-            vec![0.0, 1.0, 2.0, 4.0],
-            vec![1.0, 2.0, 4.0, 8.0],
-            vec![2.0, 4.0, 8.0, 16.0],
-            vec![4.0, 8.0, 16.0, 32.0]
+        let a = Matrix::new([
+            [0.0, 1.0, 2.0, 4.0],
+            [1.0, 2.0, 4.0, 8.0],
+            [2.0, 4.0, 8.0, 16.0],
+            [4.0, 8.0, 16.0, 32.0]
         ]);
         assert!(
             &a * &Matrix::identity() == a,
@@ -387,17 +495,17 @@ mod tests {
 
     #[test]
     fn transpose_matrix() {
-        let a = Matrix::new(vec![
-            vec![0.0, 9.0, 3.0, 0.0],
-            vec![9.0, 8.0, 0.0, 8.0],
-            vec![1.0, 8.0, 5.0, 3.0],
-            vec![0.0, 0.0, 5.0, 8.0]
+        let a = Matrix::new([
+            [0.0, 9.0, 3.0, 0.0],
+            [9.0, 8.0, 0.0, 8.0],
+            [1.0, 8.0, 5.0, 3.0],
+            [0.0, 0.0, 5.0, 8.0]
         ]);
-        let result = Matrix::new(vec![
-            vec![0.0, 9.0, 1.0, 0.0],
-            vec![9.0, 8.0, 8.0, 0.0],
-            vec![3.0, 0.0, 5.0, 5.0],
-            vec![0.0, 8.0, 3.0, 8.0]
+        let result = Matrix::new([
+            [0.0, 9.0, 1.0, 0.0],
+            [9.0, 8.0, 8.0, 0.0],
+            [3.0, 0.0, 5.0, 5.0],
+            [0.0, 8.0, 3.0, 8.0]
         ]);
         assert!(
             a.transpose() == result,
@@ -408,47 +516,20 @@ mod tests {
     #[test]
     fn transpose_identity_matrix() {
         assert!(
-            &Matrix::identity().transpose() == &Matrix::identity(),
+            Matrix::identity().transpose() == Matrix::identity(),
             "The result of the matrix transpose is not correct!"
         );
     }
 
-    #[test]
-    fn determinant_of_2x2() {
-        let a = Matrix::new(vec![
-            vec![1.0, 5.0],
-            vec![-3.0, 2.0]
-        ]);
-        let result = determinant(&a);
-        assert!(
-            f_equal(result, 17.0),
-            "The determinant should be 17, instead the result was {}", result
-        );
-    }
-
-    #[test]
-    fn determinant_of_3x3() {
-        let a = Matrix::new(vec![
-            vec![1.0, 2.0, 6.0],
-            vec![-5.0, 8.0, -4.0],
-            vec![2.0, 6.0, 4.0]
-        ]);
-        let result = determinant(&a);
-        assert!(
-            f_equal(result, -196.0),
-            "The determinant should be -196, instead the result was {}", result
-        );
-    }
-
     #[test]
     fn determinant_of_4x4() {
-        let a = Matrix::new(vec![
-            vec![-2.0, -8.0, 3.0, 5.0],
-            vec![-3.0, 1.0, 7.0, 3.0],
-            vec![1.0, 2.0, -9.0, 6.0],
-            vec![-6.0, 7.0, 7.0, -9.0]
+        let a = Matrix::new([
+            [-2.0, -8.0, 3.0, 5.0],
+            [-3.0, 1.0, 7.0, 3.0],
+            [1.0, 2.0, -9.0, 6.0],
+            [-6.0, 7.0, 7.0, -9.0]
         ]);
-        let result = determinant(&a);
+        let result = a.determinant();
         assert!(
             f_equal(result, -4071.0),
             "The determinant should be -4071, instead the result was {}", result
@@ -456,110 +537,68 @@ mod tests {
     }
 
     #[test]
-    fn submatrix_of_3x3() {
-        let a = Matrix::new(vec![
-            vec![1.0, 5.0, 0.0],
-            vec![-3.0, 2.0, 7.0],
-            vec![0.0, 6.0, -3.0]
-        ]);
-        let b = Matrix::new(vec![
-            vec![-3.0, 2.0],
-            vec![0.0, 6.0]
-        ]);
-        assert!(
-            submatrix(&a, 0, 2) == b,
-            "The submatrix of the 3x3 matrix is not correct!"
-        );
-    }
-
-    #[test]
-    fn submatrix_of_4x4() {
-        let a = Matrix::new(vec![
-            vec![-6.0, 1.0, 1.0, 6.0],
-            vec![-8.0, 5.0, 8.0, 6.0],
-            vec![-1.0, 0.0, 8.0, 2.0],
-            vec![-7.0, 1.0, -1.0, 1.0]
-        ]);
-        let b = Matrix::new(vec![
-            vec![-6.0, 1.0, 6.0],
-            vec![-8.0, 8.0, 6.0],
-            vec![-7.0, -1.0, 1.0]
-        ]);
-        assert!(
-            submatrix(&a, 2, 1) == b,
-            "The submatrix of the 4x4 matrix is not correct!"
-        );
-    }
-
-    #[test]
-    fn minor_of_3x3() {
-        let a = Matrix::new(vec![
-            vec![3.0, 5.0, 0.0],
-            vec![2.0, -1.0, -7.0],
-            vec![6.0, -1.0, 5.0]
+    fn matrix_is_invertible() {
+        let a = Matrix::new([
+            [6.0, 4.0, 4.0, 4.0],
+            [5.0, 5.0, 7.0, 6.0],
+            [4.0, -9.0, 3.0, -7.0],
+            [9.0, 1.0, 7.0, -6.0]
         ]);
-        let result = minor(&a, 1, 0);
         assert!(
-            f_equal(result, 25.0),
-            "The minor should be 25, instead the result was {}", result
+            a.is_invertible(),
+            "The matrix should be invertible, but isn't."
         );
     }
 
     #[test]
-    fn cofactor_of_3x3() {
-        let a = Matrix::new(vec![
-            vec![3.0, 5.0, 0.0],
-            vec![2.0, -1.0, -7.0],
-            vec![6.0, -1.0, 5.0]
+    fn matrix_is_not_invertible() {
+        let a = Matrix::new([
+            [-4.0, 2.0, -2.0, -3.0],
+            [9.0, 6.0, 2.0, 6.0],
+            [0.0, -5.0, 1.0, -5.0],
+            [0.0, 0.0, 0.0, 0.0]
         ]);
-        let result = cofactor(&a, 1, 0);
         assert!(
-            f_equal(result, -25.0),
-            "The cofactor should be -25, instead the result was {}", result
+            !a.is_invertible(),
+            "The matrix should not be invertible, but is."
         );
     }
 
     #[test]
-    fn matrix_is_invertible() {
-        let a = Matrix::new(vec![
-            vec![6.0, 4.0, 4.0, 4.0],
-            vec![5.0, 5.0, 7.0, 6.0],
-            vec![4.0, -9.0, 3.0, -7.0],
-            vec![9.0, 1.0, 7.0, -6.0]
-        ]);
+    fn try_inverse_of_singular_matrix() {
+        let small = Matrix::scaling(0.01, 0.01, 0.01);
         assert!(
-            a.is_invertible(),
-            "The matrix should be invertible, but isn't."
+            Matrix::scaling(0.0, 1.0, 1.0).try_inverse() == Err(MatrixError::Singular) &&
+            small.try_inverse() == Ok(Matrix::scaling(100.0, 100.0, 100.0)),
+            "Only matrices that flatten space should fail to invert."
         );
     }
 
     #[test]
-    fn matrix_is_not_invertible() {
-        let a = Matrix::new(vec![
-            vec![-4.0, 2.0, -2.0, -3.0],
-            vec![9.0, 6.0, 2.0, 6.0],
-            vec![0.0, -5.0, 1.0, -5.0],
-            vec![0.0, 0.0, 0.0, 0.0]
-        ]);
+    fn matrix_from_rows() {
+        let rows = vec![vec![1.0, 0.0, 0.0, 5.0], vec![0.0, 1.0, 0.0, 0.0], vec![0.0, 0.0, 1.0, 0.0], vec![0.0, 0.0, 0.0, 1.0]];
+        let short = vec![vec![1.0, 0.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![0.0, 0.0, 1.0, 0.0], vec![0.0, 0.0, 0.0, 1.0]];
         assert!(
-            !a.is_invertible(),
-            "The matrix should not be invertible, but is."
+            Matrix::from_rows(&rows) == Ok(Matrix::translation(5.0, 0.0, 0.0)) &&
+            Matrix::from_rows(&rows[..3]) == Err(MatrixError::WrongRowCount(3)) &&
+            Matrix::from_rows(&short) == Err(MatrixError::WrongRowLength { row: 1, length: 3 }),
+            "Rows should only make a matrix when there are 4 of 4 numbers each."
         );
     }
 
     #[test]
     fn inverse_4x4_matrix_1() {
-        let a = Matrix::new(vec![
-            vec![-5.0, 2.0, 6.0, -8.0],
-            vec![1.0, -5.0, 1.0, 8.0],
-            vec![7.0, 7.0, -6.0, -7.0],
-            vec![1.0, -3.0, 7.0, 4.0]
+        let a = Matrix::new([
+            [-5.0, 2.0, 6.0, -8.0],
+            [1.0, -5.0, 1.0, 8.0],
+            [7.0, 7.0, -6.0, -7.0],
+            [1.0, -3.0, 7.0, 4.0]
         ]);
-        let b = Matrix::new(vec![
-            vec![0.21805, 0.45113, 0.24060, -0.04511],
-            vec![-0.80827, -1.45677, -0.44361, 0.52068],
-            vec![-0.07895, -0.22368, -0.05263, 0.19737],
-            vec![-0.52256, -0.81391, -0.30075, 0.30639]
+        let b = Matrix::new([
+            [0.21805, 0.45113, 0.24060, -0.04511],
+            [-0.80827, -1.45677, -0.44361, 0.52068],
+            [-0.07895, -0.22368, -0.05263, 0.19737],
+            [-0.52256, -0.81391, -0.30075, 0.30639]
         ]);
         assert!(
             a.inverse() == b,
@@ -569,17 +608,17 @@ mod tests {
 
     #[test]
     fn inverse_4x4_matrix_2() {
-        let a = Matrix::new(vec![
-            vec![8.0, -5.0, 9.0, 2.0],
-            vec![7.0, 5.0, 6.0, 1.0],
-            vec![-6.0, 0.0, 9.0, 6.0],
-            vec![-3.0, 0.0, -9.0, -4.0]
+        let a = Matrix::new([
+            [8.0, -5.0, 9.0, 2.0],
+            [7.0, 5.0, 6.0, 1.0],
+            [-6.0, 0.0, 9.0, 6.0],
+            [-3.0, 0.0, -9.0, -4.0]
         ]);
-        let b = Matrix::new(vec![
-            vec![-0.15385, -0.15385, -0.28205, -0.53846],
-            vec![-0.07692, 0.12308, 0.02564, 0.03077],
-            vec![0.35897, 0.35897, 0.43590, 0.92308],
-            vec![-0.69231, -0.69231, -0.76923, -1.92308]
+        let b = Matrix::new([
+            [-0.15385, -0.15385, -0.28205, -0.53846],
+            [-0.07692, 0.12308, 0.02564, 0.03077],
+            [0.35897, 0.35897, 0.43590, 0.92308],
+            [-0.69231, -0.69231, -0.76923, -1.92308]
         ]);
         assert!(
             a.inverse() == b,
@@ -589,17 +628,17 @@ mod tests {
 
     #[test]
     fn inverse_4x4_matrix_3() {
-        let a = Matrix::new(vec![
-            vec![9.0, 3.0, 0.0, 9.0],
-            vec![-5.0, -2.0, -6.0, -3.0],
-            vec![-4.0, 9.0, 6.0, 4.0],
-            vec![-7.0, 6.0, 6.0, 2.0]
+        let a = Matrix::new([
+            [9.0, 3.0, 0.0, 9.0],
+            [-5.0, -2.0, -6.0, -3.0],
+            [-4.0, 9.0, 6.0, 4.0],
+            [-7.0, 6.0, 6.0, 2.0]
         ]);
-        let b = Matrix::new(vec![
-            vec![-0.04074, -0.07778, 0.14444, -0.22222],
-            vec![-0.07778, 0.03333, 0.36667, -0.33333],
-            vec![-0.02901, -0.14630, -0.10926, 0.12963],
-            vec![0.17778, 0.06667, -0.26667, 0.33333]
+        let b = Matrix::new([
+            [-0.04074, -0.07778, 0.14444, -0.22222],
+            [-0.07778, 0.03333, 0.36667, -0.33333],
+            [-0.02901, -0.14630, -0.10926, 0.12963],
+            [0.17778, 0.06667, -0.26667, 0.33333]
         ]);
         assert!(
             a.inverse() == b,
@@ -609,17 +648,17 @@ mod tests {
     
     #[test]
     fn multiply_by_inverse() {
-        let a = Matrix::new(vec![
-            vec![3.0, -9.0, 7.0, 3.0],
-            vec![3.0, -8.0, 2.0, -9.0],
-            vec![-4.0, 4.0, 4.0, 1.0],
-            vec![-6.0, 5.0, -1.0, 1.0]
-        ]);
-        let b = Matrix::new(vec![
-            vec![8.0, 2.0, 2.0, 2.0],
-            vec![3.0, -1.0, 7.0, 0.0],
-            vec![7.0, 0.0, 5.0, 4.0],
-            vec![6.0, -2.0, 0.0, 5.0]
+        let a = Matrix::new([
+            [3.0, -9.0, 7.0, 3.0],
+            [3.0, -8.0, 2.0, -9.0],
+            [-4.0, 4.0, 4.0, 1.0],
+            [-6.0, 5.0, -1.0, 1.0]
+        ]);
+        let b = Matrix::new([
+            [8.0, 2.0, 2.0, 2.0],
+            [3.0, -1.0, 7.0, 0.0],
+            [7.0, 0.0, 5.0, 4.0],
+            [6.0, -2.0, 0.0, 5.0]
         ]);
         let c = &a * &b;
         assert!(
@@ -846,6 +885,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn view_transform_default_orientation() {
+        let from = Tuple::point(0.0, 0.0, 0.0);
+        let to = Tuple::point(0.0, 0.0, -1.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        assert!(
+            Matrix::view_transform(from, to, up) == Matrix::identity(),
+            "The default orientation should be the identity matrix."
+        );
+    }
+
+    #[test]
+    fn view_transform_looking_in_positive_z() {
+        let from = Tuple::point(0.0, 0.0, 0.0);
+        let to = Tuple::point(0.0, 0.0, 1.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        assert!(
+            Matrix::view_transform(from, to, up) == Matrix::scaling(-1.0, 1.0, -1.0),
+            "Looking in positive z should reflect the x and z axis."
+        );
+    }
+
+    #[test]
+    fn view_transform_moves_the_world() {
+        let from = Tuple::point(0.0, 0.0, 8.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        assert!(
+            Matrix::view_transform(from, to, up) == Matrix::translation(0.0, 0.0, -8.0),
+            "The view transform should move the world, not the eye."
+        );
+    }
+
+    #[test]
+    fn arbitrary_view_transform() {
+        let from = Tuple::point(1.0, 3.0, 2.0);
+        let to = Tuple::point(4.0, -2.0, 8.0);
+        let up = Tuple::vector(1.0, 1.0, 0.0);
+        let result = Matrix::view_transform(from, to, up);
+        let expected = Matrix::new([
+            [-0.50709, 0.50709, 0.67612, -2.36643],
+            [0.76772, 0.60609, 0.12122, -2.82843],
+            [-0.35857, 0.59761, -0.71714, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ]);
+        assert!(
+            result == expected,
+            "The arbitrary view transform was not calculated correctly."
+        );
+    }
+
     #[test]
     fn chained_transformations() {
         let p = Tuple::point(1.0, 0.0, 1.0);
@@ -860,4 +950,62 @@ mod tests {
             "Transforming the point by the series of transformations resulted in {:#?}, the expected output was {:#?}", result, expected
         );
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn fluent_transformations_apply_in_reading_order() {
+        let p = Tuple::point(1.0, 0.0, 1.0);
+        let t = Matrix::identity()
+            .rotate_x(std::f32::consts::PI/2.0)
+            .scale(5.0, 5.0, 5.0)
+            .translate(10.0, 5.0, 7.0);
+        let sheared = Matrix::identity().scale(2.0, 1.0, 1.0).shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert!(
+            &t * &p == Tuple::point(15.0, 0.0, 7.0) &&
+            sheared == &Matrix::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * &Matrix::scaling(2.0, 1.0, 1.0) &&
+            Matrix::identity().then(&Matrix::translation(1.0, 0.0, 0.0)).rotate_y(0.5) ==
+                Matrix::identity().translate(1.0, 0.0, 0.0).rotate_y(0.5),
+            "The chained transformations were not applied in the order they were written."
+        );
+    }
+
+    #[test]
+    fn rotation_around_an_axis() {
+        let axis = Tuple::vector(1.0, 1.0, 1.0);
+        let p = &Matrix::axis_rotation(axis, 2.0 * std::f32::consts::PI / 3.0) * &Tuple::point(1.0, 0.0, 0.0);
+        assert!(
+            Matrix::axis_rotation(Tuple::vector(0.0, 2.0, 0.0), 0.7) == Matrix::rotation(0.7, RotationAxis::Y) &&
+            Matrix::axis_rotation(Tuple::vector(1.0, 0.0, 0.0), -0.3) == Matrix::rotation(-0.3, RotationAxis::X) &&
+            Matrix::identity().rotate_around(Tuple::vector(0.0, 0.0, 1.0), 1.2) == Matrix::rotation(1.2, RotationAxis::Z) &&
+            p == Tuple::point(0.0, 1.0, 0.0),
+            "The rotation around the axis was not correct. Result: {:?}", p
+        );
+    }
+
+    #[test]
+    fn euler_angles_round_trip() {
+        let m = Matrix::euler_rotation(0.3, -0.8, 2.1);
+        let expected = Matrix::identity().rotate_x(0.3).rotate_y(-0.8).rotate_z(2.1);
+        let (x, y, z) = m.euler_angles();
+        let locked = Matrix::euler_rotation(0.4, std::f32::consts::FRAC_PI_2, 0.0);
+        let (lx, ly, lz) = locked.euler_angles();
+        assert!(
+            m == expected && f_equal(x, 0.3) && f_equal(y, -0.8) && f_equal(z, 2.1) &&
+            Matrix::euler_rotation(lx, ly, lz) == locked && f_equal(lz, 0.0),
+            "The euler angles did not round trip. Result: {} {} {}", x, y, z
+        );
+    }
+
+    #[test]
+    fn look_at_undoes_the_view_transform() {
+        let (from, to, up) = (Tuple::point(1.0, 3.0, 2.0), Tuple::point(4.0, -2.0, 8.0), Tuple::vector(5.0, 3.0, 0.0));
+        let placed = Matrix::look_at(from, to, up);
+        let forward = &placed * &Tuple::vector(0.0, 0.0, -1.0);
+        let tilted = Matrix::look_at(from, to, Tuple::vector(0.0, 1.0, 0.0));
+        assert!(
+            &placed * &Matrix::view_transform(from, to, up) == Matrix::identity() &&
+            &placed * &Tuple::point(0.0, 0.0, 0.0) == from && forward == (&to - &from).norm() &&
+            f_equal(tilted.determinant(), 1.0),
+            "look_at should place the origin at from, facing to."
+        );
+    }
+}
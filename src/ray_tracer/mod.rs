@@ -1,13 +1,29 @@
 pub mod canvas;
 pub mod tuple;
 pub mod matrix;
+pub mod quaternion;
+pub mod decomposition;
+pub mod motion;
+pub mod animation;
 pub mod common;
 pub mod ray;
 pub mod sphere;
+pub mod triangle;
 pub mod intersection;
 pub mod light;
 pub mod material;
+pub mod bsdf;
 pub mod world;
+pub mod environment;
+pub mod tone_mapping;
+pub mod random;
+pub mod sampling;
+pub mod camera;
+pub mod image_format;
+pub mod progress;
+pub mod accumulation;
+pub mod integrator;
+pub mod scene;
 
 pub mod traits;
 pub mod enums;
\ No newline at end of file
@@ -0,0 +1,124 @@
+use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::decomposition::Decomposition;
+use crate::ray_tracer::animation::Lerp;
+
+// A transform that changes over time, given as keyframes. Between two keyframes the translation,
+// rotation, scale and shear of the transforms are blended separately, so an object turns without
+// shrinking. Before the first keyframe and after the last one the object holds still. Times use
+// the same units as the camera's shutter.
+#[derive(Clone, PartialEq)]
+pub struct Motion {
+    // Sorted by time. They are split into steps when the motion is made, so they shouldn't be
+    // changed afterwards.
+    pub keyframes: Vec<(f32, Matrix)>,
+    // The steps of each keyframe, None for one that isn't affine or can't be inverted.
+    steps: Vec<Option<Decomposition>>
+}
+
+impl Motion {
+    // Moves from start at time 0 to end at time 1.
+    pub fn new(start: Matrix, end: Matrix) -> Self {
+        Motion::from_keyframes(vec![(0.0, start), (1.0, end)])
+    }
+
+    // The keyframes don't need to be in order. Panics if there aren't any.
+    pub fn from_keyframes(mut keyframes: Vec<(f32, Matrix)>) -> Self {
+        assert!(!keyframes.is_empty(), "A motion needs at least one keyframe.");
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let steps = keyframes.iter().map(|(_, m)| Decomposition::from_matrix(m).ok()).collect();
+        Motion { keyframes, steps }
+    }
+
+    pub fn transform_at(&self, time: f32) -> Matrix {
+        let next = self.keyframes.partition_point(|(t, _)| *t <= time);
+        if next == 0 {
+            return self.keyframes[0].1;
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1].1;
+        }
+        let (t0, m0) = &self.keyframes[next - 1];
+        let (t1, m1) = &self.keyframes[next];
+        let t = (time - t0) / (t1 - t0);
+        match (&self.steps[next - 1], &self.steps[next]) {
+            (Some(d0), Some(d1)) => d0.lerp(d1, t).to_matrix(),
+            // Keyframes that can't be split up are blended element by element.
+            _ => m0.lerp(m1, t)
+        }
+    }
+
+    // The inverse of transform_at. Where the object is squashed flat, see flips_at, the nearest
+    // keyframe that can be inverted stands in, so that one bad time sample can't stop a render.
+    pub fn inverse_at(&self, time: f32) -> Matrix {
+        if let Ok(inverse) = self.transform_at(time).try_inverse() {
+            return inverse;
+        }
+        let mut nearest: Vec<&(f32, Matrix)> = self.keyframes.iter().collect();
+        nearest.sort_by(|a, b| (a.0 - time).abs().total_cmp(&(b.0 - time).abs()));
+        nearest.iter().find_map(|(_, m)| m.try_inverse().ok()).unwrap_or_else(Matrix::identity)
+    }
+
+    // The time of the first keyframe that mirrors the object when the one before it doesn't, or
+    // the other way around. No rotation can mirror, so the object is squashed flat somewhere in
+    // between and can't be inverted there.
+    pub fn flips_at(&self) -> Option<f32> {
+        self.keyframes.windows(2)
+            .find(|pair| pair[0].1.determinant() * pair[1].1.determinant() < 0.0)
+            .map(|pair| pair[1].0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::matrix::RotationAxis;
+    use crate::ray_tracer::tuple::Tuple;
+
+    #[test]
+    fn interpolates_between_keyframes() {
+        let m = Motion::new(Matrix::translation(0.0, 0.0, 0.0), Matrix::translation(2.0, 0.0, -4.0));
+        assert!(
+            m.transform_at(0.0) == Matrix::translation(0.0, 0.0, 0.0) &&
+            m.transform_at(0.25) == Matrix::translation(0.5, 0.0, -1.0) &&
+            m.transform_at(1.0) == Matrix::translation(2.0, 0.0, -4.0),
+            "The transform was not interpolated between the keyframes correctly."
+        )
+    }
+
+    #[test]
+    fn holds_still_outside_of_keyframes() {
+        let m = Motion::from_keyframes(vec![
+            (2.0, Matrix::scaling(3.0, 3.0, 3.0)),
+            (0.5, Matrix::identity()),
+            (1.0, Matrix::scaling(2.0, 2.0, 2.0))
+        ]);
+        assert!(
+            m.transform_at(-1.0) == Matrix::identity() && m.transform_at(1.5) == Matrix::scaling(2.5, 2.5, 2.5) &&
+            m.transform_at(7.0) == Matrix::scaling(3.0, 3.0, 3.0),
+            "The keyframes should be sorted and held before the first and after the last."
+        )
+    }
+
+    #[test]
+    fn half_turn_keeps_its_scale() {
+        let start = Matrix::scaling(2.0, 2.0, 2.0);
+        let end = &Matrix::rotation(std::f32::consts::PI, RotationAxis::Y) * &start;
+        let halfway = Motion::new(start, end).transform_at(0.5);
+        let scale = Decomposition::from_matrix(&halfway).unwrap().scale;
+        assert!(
+            scale == Tuple::vector(2.0, 2.0, 2.0) && (&halfway * &Tuple::vector(1.0, 0.0, 0.0)).mag() > 1.99,
+            "A half turn should keep the object's size halfway through. Result: {:?}", scale
+        )
+    }
+
+    #[test]
+    fn mirrored_motion_can_still_be_inverted() {
+        let m = Motion::new(Matrix::scaling(1.0, 1.0, 1.0), Matrix::scaling(-1.0, 1.0, 1.0));
+        let inverse = m.inverse_at(0.5);
+        assert!(
+            m.transform_at(0.5).try_inverse().is_err() && inverse.try_inverse().is_ok() &&
+            m.flips_at() == Some(1.0) && Motion::new(Matrix::identity(), Matrix::scaling(2.0, 1.0, 1.0)).flips_at().is_none(),
+            "A motion that mirrors the object should be reported, and still have an inverse."
+        )
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::thread;
+
+// How Camera::render_tiles splits up the work.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+    pub threads: usize,
+    // Width and height of a tile in pixels. Progress is reported and cancellation is checked once
+    // per tile.
+    pub tile_size: usize
+}
+
+impl RenderOptions {
+    pub fn new(threads: usize, tile_size: usize) -> Self {
+        RenderOptions {
+            threads,
+            tile_size
+        }
+    }
+}
+
+// One thread per CPU and 16x16 tiles.
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            tile_size: 16
+        }
+    }
+}
+
+// A snapshot of how far along a render is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub pixels_done: usize,
+    pub pixels_total: usize,
+    pub elapsed: Duration
+}
+
+impl Progress {
+    // Between 0 and 1. An empty render is complete.
+    pub fn fraction(&self) -> f32 {
+        if self.pixels_total == 0 {
+            1.0
+        } else {
+            self.pixels_done as f32 / self.pixels_total as f32
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pixels_done == self.pixels_total
+    }
+
+    // Estimated time left, assuming the remaining pixels take as long as the finished ones did on
+    // average. None until the first tile is done.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.pixels_done == 0 {
+            return None;
+        }
+        let remaining = (self.pixels_total - self.pixels_done) as f64 / self.pixels_done as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+// Shared flag for stopping a render early. Clones share the same flag, so one can be handed to
+// the render and another kept by whoever decides to stop it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(pixels_done: usize, pixels_total: usize, secs: u64) -> Progress {
+        Progress { tiles_done: 0, tiles_total: 0, pixels_done, pixels_total, elapsed: Duration::from_secs(secs) }
+    }
+
+    #[test]
+    fn eta_from_average_speed() {
+        let result = progress(25, 100, 10).eta();
+        assert!(
+            result == Some(Duration::from_secs(30)),
+            "The ETA was not calculated correctly. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn no_eta_before_first_pixel() {
+        let p = progress(0, 100, 3);
+        assert!(
+            p.eta().is_none() && p.fraction() == 0.0 && !p.is_complete(),
+            "There should be no ETA before any pixels are done."
+        )
+    }
+
+    #[test]
+    fn cancel_is_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        clone.cancel();
+        assert!(
+            token.is_cancelled(),
+            "Cancelling a clone should cancel the original token."
+        )
+    }
+}
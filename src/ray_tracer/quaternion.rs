@@ -0,0 +1,253 @@
+use crate::ray_tracer::common::f_equal;
+use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::tuple::Tuple;
+use std::ops::Mul;
+
+// A rotation, as w + xi + yj + zk. Rotations are unit quaternions, which every constructor here
+// returns. q and -q turn things the same way, but they aren't equal.
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    // No rotation.
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // Turns by radians around axis, the same way as Matrix::axis_rotation. The axis doesn't need
+    // to be normalized.
+    pub fn from_axis_angle(axis: Tuple, radians: f32) -> Self {
+        let a = axis.norm();
+        let (sin, cos) = (radians / 2.0).sin_cos();
+        Quaternion::new(cos, a.x * sin, a.y * sin, a.z * sin)
+    }
+
+    // Turns around x first, then y, then z, like Matrix::euler_rotation.
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Self {
+        let qx = Quaternion::from_axis_angle(Tuple::vector(1.0, 0.0, 0.0), x);
+        let qy = Quaternion::from_axis_angle(Tuple::vector(0.0, 1.0, 0.0), y);
+        let qz = Quaternion::from_axis_angle(Tuple::vector(0.0, 0.0, 1.0), z);
+        &(&qz * &qy) * &qx
+    }
+
+    // The rotation part of a matrix that rotates without scaling or shearing.
+    pub fn from_matrix(matrix: &Matrix) -> Self {
+        let m = &matrix.value;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        // Divides by the largest of the four components, which is never close to 0.
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(0.25 * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s)
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Quaternion::new((m[2][1] - m[1][2]) / s, 0.25 * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s)
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Quaternion::new((m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, 0.25 * s, (m[1][2] + m[2][1]) / s)
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Quaternion::new((m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, 0.25 * s)
+        };
+        q.norm()
+    }
+
+    pub fn to_matrix(&self) -> Matrix {
+        let Quaternion { w, x, y, z } = *self;
+        Matrix::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    // The axis, normalized, and the angle from 0 to 2pi. Without a rotation the axis is x.
+    pub fn to_axis_angle(&self) -> (Tuple, f32) {
+        let angle = 2.0 * self.w.clamp(-1.0, 1.0).acos();
+        let sin = (1.0 - self.w * self.w).max(0.0).sqrt();
+        if sin < 1.0e-6 {
+            return (Tuple::vector(1.0, 0.0, 0.0), angle);
+        }
+        (Tuple::vector(self.x / sin, self.y / sin, self.z / sin), angle)
+    }
+
+    // The angles for from_euler.
+    pub fn to_euler(&self) -> (f32, f32, f32) {
+        self.to_matrix().euler_angles()
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn norm(&self) -> Self {
+        let m = self.magnitude();
+        Quaternion::new(self.w / m, self.x / m, self.y / m, self.z / m)
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    // The opposite rotation, for unit quaternions.
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    // Turns a vector, or a point around the origin.
+    pub fn rotate(&self, t: &Tuple) -> Tuple {
+        // v + 2w(q x v) + 2q x (q x v), with q the vector part.
+        let q = Tuple::vector(self.x, self.y, self.z);
+        let v = Tuple::vector(t.x, t.y, t.z);
+        let qv = q.cross(v);
+        let rotated = &(&v + &(&qv * (2.0 * self.w))) + &(&q.cross(qv) * 2.0);
+        Tuple { x: rotated.x, y: rotated.y, z: rotated.z, w: t.w }
+    }
+
+    // Turns at a constant speed from self, at t = 0, to other, at t = 1, the short way around.
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        let mut end = *other;
+        if cos < 0.0 {
+            cos = -cos;
+            end = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+        }
+        // Nearly the same rotation, where the angle can't be found precisely. Blending the
+        // components is just as good there.
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Quaternion::new(
+            a * self.w + b * end.w,
+            a * self.x + b * end.x,
+            a * self.y + b * end.y,
+            a * self.z + b * end.z
+        ).norm()
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::identity()
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Quaternion) -> bool {
+        f_equal(self.w, other.w) && f_equal(self.x, other.x) && f_equal(self.y, other.y) && f_equal(self.z, other.z)
+    }
+}
+
+// a * b turns by b first and then by a, like multiplying matrices.
+impl Mul for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::matrix::RotationAxis;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn axis_angle_matches_matrix() {
+        let axis = Tuple::vector(1.0, -2.0, 0.5);
+        let q = Quaternion::from_axis_angle(axis, 1.3);
+        let (back_axis, angle) = q.to_axis_angle();
+        assert!(
+            q.to_matrix() == Matrix::axis_rotation(axis, 1.3) &&
+            Quaternion::from_axis_angle(Tuple::vector(0.0, 1.0, 0.0), 0.4).to_matrix() == Matrix::rotation(0.4, RotationAxis::Y) &&
+            back_axis == axis.norm() && f_equal(angle, 1.3),
+            "The quaternion should turn the same way as the matrix for the same axis and angle."
+        )
+    }
+
+    #[test]
+    fn rotates_tuples() {
+        let q = Quaternion::from_axis_angle(Tuple::vector(0.0, 0.0, 1.0), FRAC_PI_2);
+        let general = Quaternion::from_euler(0.3, 1.1, -0.7);
+        let p = Tuple::point(1.0, 2.0, 3.0);
+        assert!(
+            q.rotate(&Tuple::vector(1.0, 0.0, 0.0)) == Tuple::vector(0.0, 1.0, 0.0) &&
+            q.rotate(&Tuple::point(0.0, 2.0, 5.0)) == Tuple::point(-2.0, 0.0, 5.0) &&
+            general.rotate(&p) == &Matrix::euler_rotation(0.3, 1.1, -0.7) * &p,
+            "The quaternion did not rotate the tuples correctly."
+        )
+    }
+
+    #[test]
+    fn composition_applies_the_right_rotation_first() {
+        let a = Quaternion::from_axis_angle(Tuple::vector(1.0, 0.0, 0.0), 0.9);
+        let b = Quaternion::from_axis_angle(Tuple::vector(0.0, 1.0, 1.0), -0.4);
+        assert!(
+            (&a * &b).to_matrix() == &a.to_matrix() * &b.to_matrix() &&
+            &a * &a.conjugate() == Quaternion::identity(),
+            "Multiplying quaternions should compose their rotations like matrices."
+        )
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        // Angles near pi have a negative trace, which takes the other branches.
+        let rotations = [
+            Quaternion::from_euler(0.2, -0.5, 1.0),
+            Quaternion::from_axis_angle(Tuple::vector(1.0, 0.1, 0.0), 3.0),
+            Quaternion::from_axis_angle(Tuple::vector(0.1, 1.0, 0.2), 3.1),
+            Quaternion::from_axis_angle(Tuple::vector(0.0, 0.3, 1.0), PI)
+        ];
+        for q in rotations.iter() {
+            let back = Quaternion::from_matrix(&q.to_matrix());
+            assert!(
+                back == *q || back == Quaternion::new(-q.w, -q.x, -q.y, -q.z),
+                "The quaternion did not survive a round trip through a matrix. Result: {:?}, expected {:?}", back, q
+            )
+        }
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let (x, y, z) = Quaternion::from_euler(0.3, -0.8, 2.1).to_euler();
+        assert!(
+            f_equal(x, 0.3) && f_equal(y, -0.8) && f_equal(z, 2.1) &&
+            Quaternion::from_euler(0.3, -0.8, 2.1).to_matrix() == Matrix::euler_rotation(0.3, -0.8, 2.1),
+            "The euler angles did not survive a round trip. Result: {} {} {}", x, y, z
+        )
+    }
+
+    #[test]
+    fn slerp_turns_at_constant_speed() {
+        let axis = Tuple::vector(0.0, 1.0, 0.0);
+        let start = Quaternion::from_axis_angle(axis, 0.2);
+        let end = Quaternion::from_axis_angle(axis, 1.8);
+        let negated = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+        assert!(
+            start.slerp(&end, 0.25) == Quaternion::from_axis_angle(axis, 0.6) &&
+            start.slerp(&end, 0.0) == start && start.slerp(&end, 1.0) == end &&
+            start.slerp(&negated, 0.5) == Quaternion::from_axis_angle(axis, 1.0) &&
+            start.slerp(&start, 0.5) == start,
+            "slerp should turn the short way at a constant speed."
+        )
+    }
+}
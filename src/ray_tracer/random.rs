@@ -0,0 +1,74 @@
+use crate::ray_tracer::tuple::Tuple;
+
+// Small, fast pseudo random number generator (xorshift64*). Renders need a lot of random numbers
+// but no cryptographic guarantees, and seeding per pixel keeps renders reproducible no matter
+// which order (or thread) the pixels are rendered in.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Run the seed through splitmix64 so that neighbouring seeds (like pixel indices) produce
+        // unrelated sequences, and so that a seed of 0 doesn't leave xorshift stuck at 0.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 0x2545_F491_4F6C_DD1D } else { z }
+        }
+    }
+
+    // Seeds a generator from a position, so that shading the same point always draws the same
+    // random numbers regardless of which pixel or thread got there first.
+    pub fn from_point(point: &Tuple) -> Self {
+        let seed = (point.x.to_bits() as u64)
+            ^ (point.y.to_bits() as u64).rotate_left(21)
+            ^ (point.z.to_bits() as u64).rotate_left(42);
+        Rng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniformly distributed in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        // The top 24 bits fill the f32 mantissa exactly, so the result can never round up to 1.0.
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert!(
+                a.next_u64() == b.next_u64(),
+                "Two generators with the same seed should produce the same sequence."
+            )
+        }
+    }
+
+    #[test]
+    fn next_f32_in_unit_interval() {
+        let mut r = Rng::new(0);
+        for _ in 0..10000 {
+            let v = r.next_f32();
+            assert!(
+                (0.0..1.0).contains(&v),
+                "The random value {} was outside of [0, 1).", v
+            )
+        }
+    }
+}
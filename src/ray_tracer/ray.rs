@@ -2,22 +2,28 @@ use crate::ray_tracer::tuple::Tuple;
 use crate::ray_tracer::sphere::Sphere;
 use crate::ray_tracer::intersection::Intersection;
 use crate::ray_tracer::matrix::Matrix;
-
-#[cfg(test)]
 use crate::ray_tracer::traits::object::Object;
 
 pub struct Ray {
     //TODO: This doesn't feel safe becasue an origin HAS to be a point, not a vector. How can I have more safety here?
     pub origin: Tuple,
-    pub direction: Tuple
+    pub direction: Tuple,
+    // When the ray is traced, for motion blur. Moving objects are intersected where they are at
+    // this time.
+    pub time: f32
 }
 
 impl Ray {
     //TODO: If I can't get stronger types on origin and direction then I should add validation here.
     pub fn new(origin: Tuple, direction: Tuple) -> Self {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Tuple, direction: Tuple, time: f32) -> Self {
         Ray {
             origin,
-            direction
+            direction,
+            time
         }
     }
 
@@ -30,8 +36,9 @@ impl Ray {
     pub fn transform(&self, m: Matrix) -> Ray {
         Ray {
             origin: &m * &self.origin,
-            direction: &m * &self.direction
-        }        
+            direction: &m * &self.direction,
+            time: self.time
+        }
     }
 }
 
@@ -42,7 +49,7 @@ impl <'a> Ray {
     // if there is an intersection with the object.
     pub fn intersect(&self, s: &'a Sphere) -> Option<Vec<Intersection<'a>>> { 
         // Tranform the ray to find the it's intersection with the transformed Sphere.
-        let transformed_ray = self.transform(s.transform.inverse());
+        let transformed_ray = self.transform(s.inverse_at(self.time));
         
         // Yields the vector from the sphere's origin to the ray's origin
         let sphere_to_ray = &transformed_ray.origin - &s.origin;
@@ -65,20 +72,10 @@ impl <'a> Ray {
     }
 
     // Returns the closest positive intersection to the Ray's origin.
-    pub fn hit(is: &'a Vec<Intersection>) -> Option<&'a Intersection<'a>> {
-        let mut closest = &is[0];
-        for i in 1..is.len() {
-            if closest.t < 0.0 && is[i].t >= 0.0 {
-                closest = &is[i];
-            } else if is[i].t < closest.t && is[i].t >= 0.0 {
-                closest = &is[i];
-            }
-        }
-        if closest.t < 0.0 {
-            None
-        } else {
-            Some(closest)
-        }
+    pub fn hit(is: &'a [Intersection]) -> Option<&'a Intersection<'a>> {
+        is.iter()
+            .filter(|i| i.t >= 0.0)
+            .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal))
     }
 }
 
@@ -121,7 +118,7 @@ mod tests {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Sphere::new();
         let result = r.intersect(&s).unwrap();
-        let expected = vec![Intersection::new(4.0, Box::new(&s)), Intersection::new(6.0, Box::new(&s))];
+        let expected = [Intersection::new(4.0, Box::new(&s)), Intersection::new(6.0, Box::new(&s))];
         assert!(
             (result[0].t == expected[0].t) && (result[1].t == expected[1].t),
             "The t values of the intersection were not calculated correctly!"
@@ -133,7 +130,7 @@ mod tests {
         let r = Ray::new(Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Sphere::new();
         let result = r.intersect(&s).unwrap();
-        let expected = vec![Intersection::new(5.0, Box::new(&s)), Intersection::new(5.0, Box::new(&s))];
+        let expected = [Intersection::new(5.0, Box::new(&s)), Intersection::new(5.0, Box::new(&s))];
         assert!(
             (result[0].t == expected[0].t) && (result[1].t == expected[1].t),
             "The t values of the intersection were not calculated correctly!"
@@ -156,7 +153,7 @@ mod tests {
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Sphere::new();
         let result = r.intersect(&s).unwrap();
-        let expected = vec![Intersection::new(-1.0, Box::new(&s)), Intersection::new(1.0, Box::new(&s))];
+        let expected = [Intersection::new(-1.0, Box::new(&s)), Intersection::new(1.0, Box::new(&s))];
         assert!(
             (result[0].t == expected[0].t) && (result[1].t == expected[1].t),
             "The t values of the intersection were not calculated correctly!"
@@ -168,7 +165,7 @@ mod tests {
         let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Sphere::new();
         let result = r.intersect(&s).unwrap();
-        let expected = vec![Intersection::new(-6.0, Box::new(&s)), Intersection::new(-4.0, Box::new(&s))];
+        let expected = [Intersection::new(-6.0, Box::new(&s)), Intersection::new(-4.0, Box::new(&s))];
         assert!(
             (result[0].t == expected[0].t) && (result[1].t == expected[1].t),
             "The t values of the intersection were not calculated correctly!"
@@ -247,6 +244,15 @@ mod tests {
         )
     }
 
+    #[test]
+    fn find_hit_no_intersections() {
+        let is: Vec<Intersection> = Vec::new();
+        assert!(
+            Ray::hit(&is).is_none(),
+            "An empty list of intersections should not have a hit."
+        )
+    }
+
     #[test]
     fn translate_ray() {
         let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0));
@@ -276,7 +282,7 @@ mod tests {
     #[test]
     fn intersect_scaled_sphere_with_ray() {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let s = Sphere { transform: Matrix::scaling(2.0, 2.0, 2.0), ..Default::default() };
+        let s = Sphere::with_transform(Matrix::scaling(2.0, 2.0, 2.0));
         let result = r.intersect(&s).unwrap();
         assert!(
             result[0].t == 3.0 && result[1].t == 7.0,
@@ -287,7 +293,7 @@ mod tests {
     #[test]
     fn intersect_translated_sphere_with_ray() {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let s = Sphere { transform: Matrix::translation(5.0, 5.0, 5.0), ..Default::default()};
+        let s = Sphere::with_transform(Matrix::translation(5.0, 5.0, 5.0));
         let result = r.intersect(&s);
         assert!(
             result.is_none(),
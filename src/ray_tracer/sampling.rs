@@ -0,0 +1,558 @@
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::tuple::Tuple;
+
+// Where the samples for a single pixel are placed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplePattern {
+    // Evenly spaced grid, every sample at the center of its cell. Counts that aren't a perfect
+    // square leave the last row of the grid partly empty, so the bottom of the pixel gets fewer
+    // samples than the top.
+    Regular,
+    // Stratified: the same grid, but each sample is placed randomly inside of its cell.
+    Jittered,
+    // Low discrepancy Halton sequence (bases 2 and 3), randomly shifted per pixel so neighbouring
+    // pixels don't share the same pattern.
+    Halton
+}
+
+// Reconstruction filters used to weight each sample by its distance from the pixel center.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PixelFilter {
+    Box,
+    Tent,
+    Gaussian,
+    // Mitchell-Netravali with B = C = 1/3.
+    Mitchell
+}
+
+// Shape of a camera's aperture, which is what out of focus highlights (bokeh) take the shape of.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ApertureShape {
+    #[default]
+    Circle,
+    // A regular polygon with one corner per blade, like a real lens's iris. rotation turns it
+    // counterclockwise, in radians. With no rotation the first corner points to +x.
+    Polygon { blades: usize, rotation: f32 }
+}
+
+// Controls Camera::render_adaptive. A pixel is refined when its color differs from a neighbour
+// by more than threshold in any channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSettings {
+    pub threshold: f32,
+    // How many times a pixel may be split into quadrants. 0 disables refinement.
+    pub max_depth: u32
+}
+
+impl Default for AdaptiveSettings {
+    fn default() -> Self {
+        AdaptiveSettings {
+            threshold: 0.1,
+            max_depth: 3
+        }
+    }
+}
+
+// Counts of the work done by an adaptive render.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct AdaptiveStats {
+    // One ray per pixel.
+    pub primary_rays: usize,
+    // Rays cast while refining high contrast pixels.
+    pub extra_rays: usize,
+    pub refined_pixels: usize
+}
+
+// Largest difference between two colors in any single channel.
+pub fn contrast(a: Tuple, b: Tuple) -> f32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs()).max((a.z - b.z).abs())
+}
+
+// Two unit vectors that are perpendicular to each other and to the unit vector n.
+pub fn orthonormal_basis(n: Tuple) -> (Tuple, Tuple) {
+    // Frisvad's method, with the singularity moved from -z to the branch below.
+    let sign = if n.z >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let t = Tuple::vector(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bitangent = Tuple::vector(b, sign + n.y * n.y * a, -n.y);
+    (t, bitangent)
+}
+
+// Maps (u1, u2) in the unit square to a direction in the hemisphere around the unit vector normal,
+// with a probability density of cos(theta) / pi. Used to pick bounce directions for diffuse
+// surfaces, which reflect the most light close to the normal.
+pub fn cosine_hemisphere(normal: Tuple, u1: f32, u2: f32) -> Tuple {
+    let r = u1.sqrt();
+    let phi = 2.0 * std::f32::consts::PI * u2;
+    let (t, b) = orthonormal_basis(normal);
+    let z = (1.0 - u1).max(0.0).sqrt();
+    (&(&(&t * (r * phi.cos())) + &(&b * (r * phi.sin()))) + &(&normal * z)).norm()
+}
+
+// Maps (u1, u2) to a direction anywhere on the unit sphere, with a probability density of
+// 1 / 4pi.
+pub fn uniform_sphere(u1: f32, u2: f32) -> Tuple {
+    let z = 1.0 - 2.0 * u1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * u2;
+    Tuple::vector(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Maps (u1, u2) to a point on the unit disk, with a uniform density. Shirley and Chiu's concentric
+// mapping keeps nearby samples together, so stratified samples stay stratified on the disk.
+pub fn concentric_disk(u1: f32, u2: f32) -> (f32, f32) {
+    let (a, b) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let quarter_pi = std::f32::consts::FRAC_PI_4;
+    let (r, theta) = if a.abs() > b.abs() { (a, quarter_pi * (b / a)) } else { (b, 2.0 * quarter_pi - quarter_pi * (a / b)) };
+    (r * theta.cos(), r * theta.sin())
+}
+
+impl ApertureShape {
+    // Maps (u1, u2) to a point inside the aperture, with a uniform density. The aperture fits
+    // inside the unit circle.
+    pub fn sample(&self, u1: f32, u2: f32) -> (f32, f32) {
+        match *self {
+            ApertureShape::Polygon { blades, rotation } if blades >= 3 => {
+                // Pick one of the triangles between the center and two neighbouring corners, then
+                // a point inside of it. u1 is reused for the point after picking the triangle.
+                let scaled = u1 * blades as f32;
+                let i = (scaled as usize).min(blades - 1);
+                let u1 = scaled - i as f32;
+                let step = 2.0 * std::f32::consts::PI / blades as f32;
+                let (a, b) = (rotation + step * i as f32, rotation + step * (i + 1) as f32);
+                let s = u1.sqrt();
+                let (b1, b2) = (s * (1.0 - u2), s * u2);
+                (b1 * a.cos() + b2 * b.cos(), b1 * a.sin() + b2 * b.sin())
+            },
+            _ => concentric_disk(u1, u2)
+        }
+    }
+}
+
+// Piecewise constant probability distribution over [0, 1), with one piece per value of func.
+// Pieces are picked in proportion to their value. If every value is 0 they are all equally likely.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution1D {
+    pub func: Vec<f32>,
+    // Integral of func over [0, 1).
+    pub integral: f32,
+    cdf: Vec<f32>
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f32>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f32;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 { *c / integral } else { i as f32 / n as f32 };
+        }
+        Distribution1D { func, integral, cdf }
+    }
+
+    // Maps u to a position in [0, 1). Returns the position, its probability density and the
+    // index of the piece it is in.
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let n = self.func.len();
+        let i = (self.cdf.partition_point(|c| *c <= u).max(1) - 1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 { ((u - self.cdf[i]) / width).clamp(0.0, 1.0) } else { 0.0 };
+        let x = ((i as f32 + offset) / n as f32).min(1.0 - f32::EPSILON);
+        (x, self.pdf(i), i)
+    }
+
+    // Density of the positions in piece i.
+    pub fn pdf(&self, i: usize) -> f32 {
+        if self.integral > 0.0 { self.func[i] / self.integral } else { 1.0 }
+    }
+}
+
+// Piecewise constant distribution over the unit square, from a grid of values stored row by row.
+// A row is picked first, in proportion to its total, then a column within it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], width: usize, height: usize) -> Self {
+        let rows: Vec<Distribution1D> = func.chunks(width).take(height).map(|row| Distribution1D::new(row.to_vec())).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    // Maps (u1, u2) to a point (u, v) in the unit square and its probability density.
+    pub fn sample(&self, u1: f32, u2: f32) -> (f32, f32, f32) {
+        let (v, pdf_v, row) = self.marginal.sample(u2);
+        let (u, pdf_u, _) = self.rows[row].sample(u1);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    // Integral of the values over the unit square.
+    pub fn integral(&self) -> f32 {
+        self.marginal.integral
+    }
+
+    pub fn pdf(&self, u: f32, v: f32) -> f32 {
+        let row = ((v * self.rows.len() as f32) as usize).min(self.rows.len() - 1);
+        let column = ((u * self.rows[row].func.len() as f32) as usize).min(self.rows[row].func.len() - 1);
+        if self.marginal.integral > 0.0 {
+            self.rows[row].func[column] / self.marginal.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+impl SamplePattern {
+    // Returns `count` sample positions in the unit square [0, 1) x [0, 1).
+    pub fn generate(&self, count: usize, rng: &mut Rng) -> Vec<(f32, f32)> {
+        match self {
+            SamplePattern::Regular => grid(count, |_, _| (0.5, 0.5)),
+            SamplePattern::Jittered => grid(count, |_, _| (rng.next_f32(), rng.next_f32())),
+            SamplePattern::Halton => {
+                let shift_u = rng.next_f32();
+                let shift_v = rng.next_f32();
+                (0..count)
+                    .map(|i| {
+                        let u = (radical_inverse(i + 1, 2) + shift_u).fract();
+                        let v = (radical_inverse(i + 1, 3) + shift_v).fract();
+                        (u, v)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // A single sample position for pass `pass` of a progressive render, where the total number of
+    // samples isn't known up front. `pixel` identifies the pixel the sample is for. Halton keeps
+    // walking the same shifted sequence that generate uses, Jittered becomes uniformly random and
+    // Regular always returns the center.
+    pub fn sample_for_pass(&self, pass: usize, pixel: u64) -> (f32, f32) {
+        match self {
+            SamplePattern::Regular => (0.5, 0.5),
+            SamplePattern::Jittered => {
+                let mut rng = Rng::new(pixel ^ ((pass as u64) << 32));
+                (rng.next_f32(), rng.next_f32())
+            },
+            SamplePattern::Halton => {
+                let mut rng = Rng::new(pixel);
+                let shift_u = rng.next_f32();
+                let shift_v = rng.next_f32();
+                ((radical_inverse(pass + 1, 2) + shift_u).fract(), (radical_inverse(pass + 1, 3) + shift_v).fract())
+            }
+        }
+    }
+}
+
+// Splits the unit square into a grid with at least `count` cells and places one sample in each of
+// the first `count` cells. `offset` picks the position inside of a cell, as a fraction of its size.
+fn grid<F: FnMut(usize, usize) -> (f32, f32)>(count: usize, mut offset: F) -> Vec<(f32, f32)> {
+    let cols = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = count.div_ceil(cols).max(1);
+    (0..count)
+        .map(|i| {
+            let (col, row) = (i % cols, i / cols);
+            let (du, dv) = offset(col, row);
+            ((col as f32 + du) / cols as f32, (row as f32 + dv) / rows as f32)
+        })
+        .collect()
+}
+
+// Mirrors the digits of i in the given base around the decimal point.
+fn radical_inverse(mut i: usize, base: usize) -> f32 {
+    let inv_base = 1.0 / base as f32;
+    let mut fraction = inv_base;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f32 * fraction;
+        i /= base;
+        fraction *= inv_base;
+    }
+    result
+}
+
+impl PixelFilter {
+    // Distance from the pixel center, in pixels, past which the filter weight is 0.
+    pub fn radius(&self) -> f32 {
+        match self {
+            PixelFilter::Box => 0.5,
+            PixelFilter::Tent => 1.0,
+            PixelFilter::Gaussian => 1.5,
+            PixelFilter::Mitchell => 2.0
+        }
+    }
+
+    // Weight of a sample offset (dx, dy) pixels from the pixel center. The filters are separable.
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius {
+            return 0.0;
+        }
+        match self {
+            PixelFilter::Box => 1.0,
+            PixelFilter::Tent => 1.0 - x,
+            PixelFilter::Gaussian => {
+                let alpha = 2.0;
+                // Subtracting the value at the radius makes the filter go smoothly to 0 at its edge.
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            },
+            PixelFilter::Mitchell => {
+                let b = 1.0 / 3.0;
+                let c = 1.0 / 3.0;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2) +
+                        (6.0 - 2.0 * b)) / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x.powi(2) +
+                        (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    #[test]
+    fn regular_pattern_cell_centers() {
+        let mut rng = Rng::new(0);
+        let result = SamplePattern::Regular.generate(4, &mut rng);
+        assert!(
+            result == vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)],
+            "The regular pattern did not place the samples at the cell centers. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn single_regular_sample_is_pixel_center() {
+        let mut rng = Rng::new(0);
+        let result = SamplePattern::Regular.generate(1, &mut rng);
+        assert!(
+            result == vec![(0.5, 0.5)],
+            "A single regular sample should be at the pixel center. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_their_cells() {
+        let mut rng = Rng::new(7);
+        let result = SamplePattern::Jittered.generate(9, &mut rng);
+        for (i, (u, v)) in result.iter().enumerate() {
+            let (col, row) = ((i % 3) as f32, (i / 3) as f32);
+            assert!(
+                *u >= col / 3.0 && *u < (col + 1.0) / 3.0 && *v >= row / 3.0 && *v < (row + 1.0) / 3.0,
+                "The jittered sample {} at ({}, {}) left its cell.", i, u, v
+            )
+        }
+    }
+
+    #[test]
+    fn halton_samples_in_unit_square() {
+        let mut rng = Rng::new(3);
+        let result = SamplePattern::Halton.generate(16, &mut rng);
+        assert!(
+            result.len() == 16 && result.iter().all(|(u, v)| (0.0..1.0).contains(u) && (0.0..1.0).contains(v)),
+            "The Halton samples were not inside of the unit square."
+        )
+    }
+
+    #[test]
+    fn radical_inverse_base_2() {
+        assert!(
+            f_equal(radical_inverse(1, 2), 0.5) && f_equal(radical_inverse(2, 2), 0.25) &&
+            f_equal(radical_inverse(3, 2), 0.75),
+            "The radical inverse was not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn contrast_is_largest_channel_difference() {
+        let result = contrast(Tuple::color(0.1, 0.5, 0.9), Tuple::color(0.2, 0.1, 0.8));
+        assert!(
+            f_equal(result, 0.4),
+            "The contrast was {}, expected 0.4.", result
+        )
+    }
+
+    #[test]
+    fn filters_are_zero_outside_radius() {
+        for f in [PixelFilter::Box, PixelFilter::Tent, PixelFilter::Gaussian, PixelFilter::Mitchell].iter() {
+            assert!(
+                f.evaluate(f.radius() + 0.01, 0.0) == 0.0 && f.evaluate(0.0, 0.0) > 0.0,
+                "The {:?} filter has the wrong support.", f
+            )
+        }
+    }
+
+    #[test]
+    fn tent_filter_weights() {
+        let f = PixelFilter::Tent;
+        assert!(
+            f_equal(f.evaluate(0.0, 0.0), 1.0) && f_equal(f.evaluate(0.5, 0.0), 0.5) &&
+            f_equal(f.evaluate(0.5, 0.5), 0.25),
+            "The tent filter weights were not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn mitchell_filter_weights() {
+        let f = PixelFilter::Mitchell;
+        assert!(
+            f_equal(f.evaluate(0.0, 0.0), (8.0_f32 / 9.0).powi(2)) && f_equal(f.evaluate(2.0, 0.0), 0.0) &&
+            f.evaluate(1.5, 0.0) < 0.0,
+            "The Mitchell filter weights were not calculated correctly."
+        )
+    }
+
+    #[test]
+    fn halton_passes_follow_the_generated_sequence() {
+        let generated = SamplePattern::Halton.generate(5, &mut Rng::new(42));
+        let passes: Vec<(f32, f32)> = (0..5).map(|i| SamplePattern::Halton.sample_for_pass(i, 42)).collect();
+        assert!(
+            generated == passes,
+            "Progressive Halton samples should match the generated sequence. Result: {:?}", passes
+        )
+    }
+
+    #[test]
+    fn jittered_passes_differ() {
+        let a = SamplePattern::Jittered.sample_for_pass(0, 7);
+        let b = SamplePattern::Jittered.sample_for_pass(1, 7);
+        assert!(
+            a != b && a == SamplePattern::Jittered.sample_for_pass(0, 7),
+            "Each pass should get its own reproducible sample."
+        )
+    }
+
+    #[test]
+    fn orthonormal_basis_is_perpendicular() {
+        for n in [Tuple::vector(0.0, 0.0, 1.0), Tuple::vector(0.0, 0.0, -1.0), Tuple::vector(1.0, 2.0, -3.0).norm()].iter() {
+            let (t, b) = orthonormal_basis(*n);
+            assert!(
+                f_equal(&t * n, 0.0) && f_equal(&b * n, 0.0) && f_equal(&t * &b, 0.0) &&
+                f_equal(t.mag(), 1.0) && f_equal(b.mag(), 1.0),
+                "The basis for {:?} was not orthonormal.", n
+            )
+        }
+    }
+
+    #[test]
+    fn cosine_hemisphere_distribution() {
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let mut rng = Rng::new(11);
+        let n = 20000;
+        let mut cos_sum = 0.0;
+        for _ in 0..n {
+            let d = cosine_hemisphere(normal, rng.next_f32(), rng.next_f32());
+            let cos = &d * &normal;
+            assert!(cos >= 0.0 && f_equal(d.mag(), 1.0), "The direction {:?} left the hemisphere.", d);
+            cos_sum += cos;
+        }
+        // E[cos] under a cos / pi density is 2/3.
+        let mean = cos_sum / n as f32;
+        assert!(
+            (mean - 2.0 / 3.0).abs() < 0.01,
+            "The mean cosine was {}, expected about 2/3.", mean
+        )
+    }
+
+    #[test]
+    fn distribution_1d_follows_values() {
+        let d = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+        let (x, pdf, i) = d.sample(0.3);
+        let (x2, _, i2) = d.sample(0.9);
+        assert!(
+            d.integral == 2.0 && i == 1 && f_equal(x, 0.3666667) && pdf == 1.5 && i2 == 3 && f_equal(x2, 0.95) &&
+            d.pdf(2) == 0.0,
+            "The distribution was not sampled correctly. Result: {} {} {} {}", x, pdf, i, x2
+        )
+    }
+
+    #[test]
+    fn empty_distribution_is_uniform() {
+        let d = Distribution1D::new(vec![0.0, 0.0]);
+        let (x, pdf, i) = d.sample(0.75);
+        assert!(
+            f_equal(x, 0.75) && pdf == 1.0 && i == 1,
+            "A distribution without any weight should be uniform."
+        )
+    }
+
+    #[test]
+    fn distribution_2d_density_matches_samples() {
+        let func = [1.0, 2.0, 0.0, 5.0, 1.0, 3.0];
+        let d = Distribution2D::new(&func, 3, 2);
+        let mut rng = Rng::new(21);
+        let consistent = (0..200).all(|_| {
+            let (u, v, pdf) = d.sample(rng.next_f32(), rng.next_f32());
+            f_equal(pdf, d.pdf(u, v)) && pdf > 0.0
+        });
+        // The mean of func over the square is 2, so the density at a value of 5 is 2.5.
+        assert!(
+            consistent && f_equal(d.pdf(0.5, 0.75), 0.5) && f_equal(d.pdf(0.1, 0.9), 2.5) && f_equal(d.pdf(0.9, 0.1), 0.0),
+            "The 2D distribution's density doesn't match its samples."
+        )
+    }
+
+    #[test]
+    fn disk_samples_are_uniform() {
+        // Stratified samples on the disk: all inside, and a quarter of them within radius 0.5.
+        let n = 64;
+        let mut inner = 0;
+        let mut inside = true;
+        for i in 0..n {
+            for j in 0..n {
+                let (x, y) = concentric_disk((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                let r2 = x * x + y * y;
+                inside &= r2 <= 1.0 + 1.0e-5;
+                if r2 < 0.25 {
+                    inner += 1;
+                }
+            }
+        }
+        let fraction = inner as f32 / (n * n) as f32;
+        assert!(
+            inside && (fraction - 0.25).abs() < 0.01 && concentric_disk(0.5, 0.5) == (0.0, 0.0),
+            "Disk samples should cover the disk uniformly. Inner fraction: {}", fraction
+        )
+    }
+
+    #[test]
+    fn polygon_aperture_samples_stay_inside() {
+        // A square rotated so that its sides are axis aligned: |x| and |y| stay below 1 / sqrt(2)
+        // and the samples spread evenly over the four quadrants.
+        let shape = ApertureShape::Polygon { blades: 4, rotation: std::f32::consts::FRAC_PI_4 };
+        let half_side = std::f32::consts::FRAC_1_SQRT_2 + 1.0e-5;
+        let n = 40;
+        let mut quadrants = [0; 4];
+        let mut inside = true;
+        for i in 0..n {
+            for j in 0..n {
+                let (x, y) = shape.sample((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                inside &= x.abs() <= half_side && y.abs() <= half_side;
+                quadrants[(x < 0.0) as usize * 2 + (y < 0.0) as usize] += 1;
+            }
+        }
+        assert!(
+            inside && quadrants.iter().all(|q| *q == n * n / 4),
+            "Samples should stay inside the square aperture and cover it evenly. Quadrants: {:?}", quadrants
+        )
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum SceneError {
+    // The file couldn't be read.
+    Io(io::Error),
+    // Something is wrong with the contents of the file, on the given line.
+    Parse { line: usize, message: String }
+}
+
+impl SceneError {
+    pub fn parse(line: usize, message: &str) -> Self {
+        SceneError::Parse { line, message: message.to_owned() }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Parse { line, message } => write!(f, "line {}: {}", line, message)
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+// io::Error doesn't implement PartialEq, so two Io errors are equal when they have the same kind.
+impl PartialEq for SceneError {
+    fn eq(&self, other: &SceneError) -> bool {
+        match (self, other) {
+            (SceneError::Io(a), SceneError::Io(b)) => a.kind() == b.kind(),
+            (SceneError::Parse { line: l1, message: m1 }, SceneError::Parse { line: l2, message: m2 }) => {
+                l1 == l2 && m1 == m2
+            },
+            _ => false
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use crate::ray_tracer::scene::error::SceneError;
+
+// Minimal JSON reader and writer. Numbers are kept as the text they were written as, so an f32
+// that is written out and read back is exactly the same value. Every value remembers the line it
+// started on so that errors can point at it. Values built in code use line 0.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Json {
+    pub value: JsonValue,
+    pub line: usize
+}
+
+impl Json {
+    pub fn new(value: JsonValue) -> Self {
+        Json {
+            value,
+            line: 0
+        }
+    }
+
+    // f32's Display output is the shortest text that parses back to the same value. JSON has no
+    // way to write NaN or infinity, so those become null.
+    pub fn number(n: f32) -> Self {
+        if n.is_finite() {
+            Json::new(JsonValue::Number(format!("{}", n)))
+        } else {
+            Json::new(JsonValue::Null)
+        }
+    }
+
+    pub fn integer(n: usize) -> Self {
+        Json::new(JsonValue::Number(n.to_string()))
+    }
+
+    pub fn boolean(b: bool) -> Self {
+        Json::new(JsonValue::Bool(b))
+    }
+
+    pub fn string(s: &str) -> Self {
+        Json::new(JsonValue::String(s.to_owned()))
+    }
+
+    pub fn array(items: Vec<Json>) -> Self {
+        Json::new(JsonValue::Array(items))
+    }
+
+    pub fn numbers(values: &[f32]) -> Self {
+        Json::array(values.iter().map(|n| Json::number(*n)).collect())
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Self {
+        Json::new(JsonValue::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect()))
+    }
+
+    pub fn as_str(&self) -> Result<&str, SceneError> {
+        match &self.value {
+            JsonValue::String(s) => Ok(s),
+            _ => Err(SceneError::parse(self.line, "expected a string"))
+        }
+    }
+
+    pub fn as_f32(&self) -> Result<f32, SceneError> {
+        match &self.value {
+            JsonValue::Number(n) => n.parse::<f32>()
+                .map_err(|_| SceneError::parse(self.line, &format!("'{}' is out of range", n))),
+            _ => Err(SceneError::parse(self.line, "expected a number"))
+        }
+    }
+
+    pub fn as_usize(&self) -> Result<usize, SceneError> {
+        match &self.value {
+            JsonValue::Number(n) => n.parse::<usize>()
+                .map_err(|_| SceneError::parse(self.line, &format!("expected a whole number, found {}", n))),
+            _ => Err(SceneError::parse(self.line, "expected a whole number"))
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, SceneError> {
+        match &self.value {
+            JsonValue::Bool(b) => Ok(*b),
+            _ => Err(SceneError::parse(self.line, "expected true or false"))
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&Vec<Json>, SceneError> {
+        match &self.value {
+            JsonValue::Array(items) => Ok(items),
+            _ => Err(SceneError::parse(self.line, "expected an array"))
+        }
+    }
+
+    pub fn as_f32_array(&self) -> Result<Vec<f32>, SceneError> {
+        self.as_array()?.iter().map(|n| n.as_f32()).collect()
+    }
+
+    pub fn as_object(&self) -> Result<&Vec<(String, Json)>, SceneError> {
+        match &self.value {
+            JsonValue::Object(fields) => Ok(fields),
+            _ => Err(SceneError::parse(self.line, "expected an object"))
+        }
+    }
+
+    // Returns the field when this value is an object that contains it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    // Like get, but a missing field is an error.
+    pub fn field(&self, key: &str) -> Result<&Json, SceneError> {
+        self.as_object()?;
+        self.get(key).ok_or_else(|| SceneError::parse(self.line, &format!("missing field '{}'", key)))
+    }
+
+    // Rejects fields that aren't in allowed. `what` names the object in the error message.
+    pub fn check_fields(&self, what: &str, allowed: &[&str]) -> Result<(), SceneError> {
+        for (key, value) in self.as_object()? {
+            if !allowed.contains(&key.as_str()) {
+                return Err(SceneError::parse(value.line, &format!("unknown field '{}' in {}", key, what)));
+            }
+        }
+        Ok(())
+    }
+
+    // Writes the value with two space indentation. Arrays that only hold numbers, strings, booleans
+    // or null stay on a single line.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match &self.value {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Number(n) => out.push_str(n),
+            JsonValue::String(s) => write_string(out, s),
+            JsonValue::Array(items) => {
+                if items.is_empty() {
+                    out.push_str("[]");
+                } else if items.iter().all(|i| !matches!(i.value, JsonValue::Array(_) | JsonValue::Object(_))) {
+                    out.push('[');
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(", ");
+                        }
+                        item.write(out, indent);
+                    }
+                    out.push(']');
+                } else {
+                    out.push_str("[\n");
+                    for (i, item) in items.iter().enumerate() {
+                        push_indent(out, indent + 1);
+                        item.write(out, indent + 1);
+                        out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                    }
+                    push_indent(out, indent);
+                    out.push(']');
+                }
+            },
+            JsonValue::Object(fields) => {
+                if fields.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+pub fn parse(source: &str) -> Result<Json, SceneError> {
+    let mut parser = Parser { chars: source.chars().collect(), pos: 0, line: 1 };
+    parser.skip_whitespace();
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(SceneError::parse(parser.line, "unexpected text after the end of the document"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SceneError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(SceneError::parse(self.line, &format!("expected '{}', found '{}'", expected, c))),
+            None => Err(SceneError::parse(self.line, &format!("expected '{}', found the end of the document", expected)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, SceneError> {
+        let line = self.line;
+        let value = match self.peek() {
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => JsonValue::String(self.string()?),
+            Some('t') => { self.keyword("true")?; JsonValue::Bool(true) },
+            Some('f') => { self.keyword("false")?; JsonValue::Bool(false) },
+            Some('n') => { self.keyword("null")?; JsonValue::Null },
+            Some(c) if c == '-' || c.is_ascii_digit() => JsonValue::Number(self.number()?),
+            Some(c) => return Err(SceneError::parse(line, &format!("unexpected '{}'", c))),
+            None => return Err(SceneError::parse(line, "unexpected end of the document"))
+        };
+        Ok(Json { value, line })
+    }
+
+    fn keyword(&mut self, word: &str) -> Result<(), SceneError> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(SceneError::parse(self.line, "unexpected text"));
+            }
+        }
+        Ok(())
+    }
+
+    fn object(&mut self) -> Result<JsonValue, SceneError> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, Json)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            if self.peek() != Some('"') {
+                return Err(SceneError::parse(line, "expected a field name"));
+            }
+            let key = self.string()?;
+            if fields.iter().any(|(k, _)| *k == key) {
+                return Err(SceneError::parse(line, &format!("'{}' is defined more than once", key)));
+            }
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                _ => return Err(SceneError::parse(self.line, "expected ',' or '}'"))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, SceneError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(SceneError::parse(self.line, "expected ',' or ']'"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SceneError> {
+        let line = self.line;
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let mut code = self.hex4()?;
+                        // Characters outside of the basic plane are written as a surrogate pair.
+                        if (0xD800..0xDC00).contains(&code) && self.next() == Some('\\') && self.next() == Some('u') {
+                            let low = self.hex4()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => s.push(c),
+                            None => return Err(SceneError::parse(self.line, "invalid unicode escape"))
+                        }
+                    },
+                    _ => return Err(SceneError::parse(self.line, "invalid escape sequence"))
+                },
+                Some('\n') | None => return Err(SceneError::parse(line, "unterminated string")),
+                Some(c) => s.push(c)
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, SceneError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(d) => code = code * 16 + d,
+                None => return Err(SceneError::parse(self.line, "invalid unicode escape"))
+            }
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<String, SceneError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        // Rust accepts a few things JSON doesn't (like "1." or "+1"), so check the grammar.
+        if !is_json_number(&text) {
+            return Err(SceneError::parse(self.line, &format!("invalid number '{}'", text)));
+        }
+        Ok(text)
+    }
+}
+
+// -? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?
+fn is_json_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    if i < bytes.len() && bytes[i] == b'-' {
+        i += 1;
+    }
+    let int_start = i;
+    let int_digits = digits(&mut i);
+    if int_digits == 0 || (int_digits > 1 && bytes[int_start] == b'0') {
+        return false;
+    }
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        i += 1;
+        if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_document() {
+        let doc = parse("{\n  \"name\": \"a\\\"b\",\n  \"values\": [1, -2.5e3, true, null],\n  \"empty\": {}\n}").unwrap();
+        let values = doc.get("values").unwrap().as_array().unwrap();
+        assert!(
+            doc.get("name").unwrap().as_str().unwrap() == "a\"b" &&
+            values[0].as_usize().unwrap() == 1 && values[1].as_f32().unwrap() == -2500.0 &&
+            values[2].as_bool().unwrap() && values[3].value == JsonValue::Null &&
+            doc.get("values").unwrap().line == 3 && doc.get("empty").unwrap().as_object().unwrap().is_empty(),
+            "The document was not parsed correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn write_and_parse_round_trip() {
+        let doc = Json::object(vec![
+            ("text", Json::string("tab\t \u{1F600} \\")),
+            ("numbers", Json::numbers(&[0.1, -0.0, 1.0e-30, 3.402_823_5e38])),
+            ("nested", Json::array(vec![Json::object(vec![("ok", Json::boolean(false))])]))
+        ]);
+        let text = doc.to_pretty_string();
+        let result = parse(&text).unwrap();
+        assert!(
+            result.to_pretty_string() == text &&
+            result.get("numbers").unwrap().as_f32_array().unwrap()[2] == 1.0e-30 &&
+            result.get("text").unwrap().as_str().unwrap() == "tab\t \u{1F600} \\",
+            "Writing and parsing did not round trip. Result: {}", text
+        )
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let doc = parse("\"\\u00e9\\ud83d\\ude00\"").unwrap();
+        assert!(
+            doc.as_str().unwrap() == "\u{e9}\u{1F600}",
+            "The unicode escapes were not decoded correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        for text in ["01", "1.", "-", "1e", "+1", ".5"].iter() {
+            assert!(
+                parse(text).is_err(),
+                "'{}' should not parse as a number.", text
+            )
+        }
+    }
+
+    #[test]
+    fn errors_report_line() {
+        let result = parse("{\n  \"a\": 1,\n  \"b\": [1 2]\n}");
+        assert!(
+            result == Err(SceneError::parse(3, "expected ',' or ']'")),
+            "The error did not point at the right line. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn duplicate_fields_are_rejected() {
+        let result = parse("{ \"a\": 1, \"a\": 2 }");
+        assert!(
+            result == Err(SceneError::parse(1, "'a' is defined more than once")),
+            "Duplicate fields should be an error. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn check_fields_reports_unknown_field() {
+        let doc = parse("{\n  \"color\": [1, 1, 1],\n  \"colour\": [1, 1, 1]\n}").unwrap();
+        let result = doc.check_fields("material", &["color"]);
+        assert!(
+            result == Err(SceneError::parse(3, "unknown field 'colour' in material")),
+            "The unknown field was not reported. Result: {:?}", result
+        )
+    }
+}
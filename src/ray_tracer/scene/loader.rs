@@ -0,0 +1,1224 @@
+use crate::ray_tracer::scene::error::SceneError;
+use crate::ray_tracer::scene::yaml::{self, Node, Value};
+use crate::ray_tracer::world::World;
+use crate::ray_tracer::camera::Camera;
+use crate::ray_tracer::light::{Light, AreaLight, Attenuation};
+use crate::ray_tracer::material::Material;
+use crate::ray_tracer::bsdf::{Bsdf, parameter_problem};
+use crate::ray_tracer::matrix::{Matrix, RotationAxis};
+use crate::ray_tracer::motion::Motion;
+use crate::ray_tracer::animation::{Interpolation, Keyframe, Lerp, Track};
+use crate::ray_tracer::sphere::Sphere;
+use crate::ray_tracer::triangle::Triangle;
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::sampling::{SamplePattern, PixelFilter, ApertureShape};
+use crate::ray_tracer::integrator::{Integrator, PathTracerSettings};
+use crate::ray_tracer::environment::{Background, EnvironmentMap};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A scene file is a list of commands, each one a set of 'key: value' pairs:
+//
+//   - add: camera                      # width, height, field-of-view, from, to, up, samples,
+//                                      # pattern, filter, integrator (phong or path),
+//                                      # max-depth, roulette-depth, aperture, focal-distance,
+//                                      # blades, blade-rotation, shutter-open, shutter-close
+//   - add: light                       # type (point, area, directional or spot), at, intensity,
+//                                      # corner, uvec, usteps, vvec, vsteps, jitter, direction,
+//                                      # inner-angle, outer-angle, attenuation, power
+//   - add: sphere                      # material, transform, motion
+//   - add: triangle                    # p1, p2, p3, material, transform, motion
+//   - add: mesh                        # vertices, faces, material, transform, motion
+//   - add: background                  # type (solid, gradient or map), color, bottom, top, file,
+//                                      # intensity, rotation
+//   - define: name                     # value, extend
+//
+// A mesh's vertices are a list of points and its faces a list of [ i, j, k ] indices into it, one
+// triangle per face. Materials are either written out (color, ambient, diffuse, specular,
+// shininess, emission, bsdf) or the name of a define. Objects with an emission give off light in
+// the path tracer, which scatters light with the bsdf: a mapping with a type (lambert, conductor or
+// dielectric) and albedo, color, roughness, metalness, ior or tint as the type needs. Transforms
+// are a list of [ translate, x, y, z ], [ scale, x, y, z ], [ rotate-x, r ], [ rotate-y, r ],
+// [ rotate-z, r ] and [ shear, xy, xz, yx, yz, zx, zy ] entries, or names of defines holding such
+// lists. They are applied in the order they are listed. Angles are radians.
+// Objects that move have a motion instead of a transform: a list of keyframes, each a mapping with
+// a time and a transform. Moving objects are blurred over the time the camera's shutter is open.
+// Neighbouring keyframes can't switch between mirrored and not, which would squash the object flat.
+//
+// Rays that miss everything see the background, black unless the scene adds one. A map's file is a
+// PFM or Radiance HDR image in the equirectangular layout, relative to the scene file.
+//
+// Any value can be animated by replacing it with a mapping that only holds keyframes:
+//
+//   from:
+//     keyframes:
+//       - time: 0
+//         value: [ 0, 1.5, -5 ]
+//         interpolation: smooth      # linear (the default) or smooth, for getting to the next one
+//       - time: 2
+//         value: [ 5, 1.5, 0 ]
+//
+// Numbers are blended between keyframes, as are lists and mappings of the same shape, entry by
+// entry. Whole numbers, like a camera's width or samples, are rounded to the nearest one. Anything
+// else, like the name of a define, changes when the next keyframe is reached. A
+// scene loaded at a time has its values resolved for that time, and the camera's shutter opens
+// and closes that long after the times it is given.
+pub struct Scene {
+    pub world: World,
+    pub camera: Camera
+}
+
+impl Scene {
+    // Files the scene refers to are looked for relative to the current directory.
+    pub fn parse(source: &str) -> Result<Scene, SceneError> {
+        Scene::parse_at(source, 0.0)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        Scene::load_at(path, 0.0)
+    }
+
+    // The scene as it is at time, for animated scenes.
+    pub fn parse_at(source: &str, time: f32) -> Result<Scene, SceneError> {
+        Scene::parse_in(source, Path::new(""), time)
+    }
+
+    pub fn load_at<P: AsRef<Path>>(path: P, time: f32) -> Result<Scene, SceneError> {
+        let source = fs::read_to_string(path.as_ref())?;
+        Scene::parse_in(&source, path.as_ref().parent().unwrap_or_else(|| Path::new("")), time)
+    }
+
+    fn parse_in(source: &str, directory: &Path, time: f32) -> Result<Scene, SceneError> {
+        let document = resolve(&yaml::parse(source)?, time)?;
+        let mut loader = Loader {
+            defines: HashMap::new(),
+            world: World::empty(),
+            camera: None,
+            has_background: false,
+            directory: directory.to_path_buf()
+        };
+        for item in document.as_sequence()? {
+            loader.command(item)?;
+        }
+
+        match loader.camera {
+            Some(mut camera) => {
+                camera.shutter_open += time;
+                camera.shutter_close += time;
+                Ok(Scene { world: loader.world, camera })
+            },
+            None => Err(SceneError::parse(document.line, "the scene doesn't add a camera"))
+        }
+    }
+}
+
+struct Loader {
+    defines: HashMap<String, Node>,
+    world: World,
+    camera: Option<Camera>,
+    has_background: bool,
+    // The scene file's directory.
+    directory: PathBuf
+}
+
+impl Loader {
+    fn command(&mut self, item: &Node) -> Result<(), SceneError> {
+        if let Some(name) = item.get("define") {
+            check_keys(item, "define", &["define", "extend", "value"])?;
+            let name = name.as_str()?.to_owned();
+            let value = required(item, "value")?;
+            let value = match item.get("extend") {
+                Some(base) => self.extend(base, value)?,
+                None => value.clone()
+            };
+            self.defines.insert(name, value);
+            return Ok(());
+        }
+
+        let add = match item.get("add") {
+            Some(add) => add,
+            None => return Err(SceneError::parse(item.line, "expected 'add' or 'define'"))
+        };
+        match add.as_str()? {
+            "camera" => {
+                if self.camera.is_some() {
+                    return Err(SceneError::parse(item.line, "the scene already has a camera"));
+                }
+                self.camera = Some(self.camera(item)?);
+            },
+            "light" => {
+                let light = self.light(item)?;
+                self.world.lights.push(light);
+            },
+            "sphere" => {
+                let sphere = self.sphere(item)?;
+                self.world.objects.push(Box::new(sphere));
+            },
+            "triangle" => {
+                let triangle = self.triangle(item)?;
+                self.world.objects.push(Box::new(triangle));
+            },
+            "mesh" => {
+                for triangle in self.mesh(item)? {
+                    self.world.objects.push(Box::new(triangle));
+                }
+            },
+            "background" => {
+                if self.has_background {
+                    return Err(SceneError::parse(item.line, "the scene already has a background"));
+                }
+                self.world.background = self.background(item)?;
+                self.has_background = true;
+            },
+            other => return Err(SceneError::parse(add.line, &format!("unknown object '{}'", other)))
+        }
+        Ok(())
+    }
+
+    // Merges the mapping in value on top of the define named by base.
+    fn extend(&self, base: &Node, value: &Node) -> Result<Node, SceneError> {
+        let mut entries = self.define(base)?.as_mapping()?.clone();
+        for (key, node) in value.as_mapping()? {
+            match entries.iter_mut().find(|(k, _)| k == key) {
+                Some(entry) => entry.1 = node.clone(),
+                None => entries.push((key.clone(), node.clone()))
+            }
+        }
+        Ok(Node::new(Value::Mapping(entries), value.line))
+    }
+
+    fn define(&self, name: &Node) -> Result<&Node, SceneError> {
+        let key = name.as_str()?;
+        self.defines.get(key)
+            .ok_or_else(|| SceneError::parse(name.line, &format!("'{}' hasn't been defined", key)))
+    }
+
+    fn camera(&self, item: &Node) -> Result<Camera, SceneError> {
+        check_keys(item, "camera",
+            &["add", "width", "height", "field-of-view", "from", "to", "up", "samples", "pattern", "filter",
+              "integrator", "max-depth", "roulette-depth", "aperture", "focal-distance", "blades", "blade-rotation",
+              "shutter-open", "shutter-close"])?;
+        let mut camera = Camera::new(
+            required(item, "width")?.as_usize()?,
+            required(item, "height")?.as_usize()?,
+            required(item, "field-of-view")?.as_f32()?
+        );
+        let from = point(required(item, "from")?)?;
+        let to = point(required(item, "to")?)?;
+        camera.transform = Matrix::view_transform(from, to, vector(required(item, "up")?)?);
+        if !camera.transform.is_invertible() {
+            return Err(SceneError::parse(item.line, "the camera's from, to and up don't give it a direction to look in"));
+        }
+        if let Some(samples) = item.get("samples") {
+            camera.samples_per_pixel = samples.as_usize()?;
+        }
+        if let Some(pattern) = item.get("pattern") {
+            camera.sample_pattern = match pattern.as_str()? {
+                "regular" => SamplePattern::Regular,
+                "jittered" => SamplePattern::Jittered,
+                "halton" => SamplePattern::Halton,
+                other => return Err(SceneError::parse(pattern.line, &format!("unknown sample pattern '{}'", other)))
+            };
+        }
+        if let Some(filter) = item.get("filter") {
+            camera.filter = match filter.as_str()? {
+                "box" => PixelFilter::Box,
+                "tent" => PixelFilter::Tent,
+                "gaussian" => PixelFilter::Gaussian,
+                "mitchell" => PixelFilter::Mitchell,
+                other => return Err(SceneError::parse(filter.line, &format!("unknown pixel filter '{}'", other)))
+            };
+        }
+        let mut settings = PathTracerSettings::default();
+        if let Some(depth) = item.get("max-depth") {
+            settings.max_depth = depth.as_usize()?;
+        }
+        if let Some(depth) = item.get("roulette-depth") {
+            settings.roulette_depth = depth.as_usize()?;
+        }
+        let integrator = item.get("integrator");
+        camera.integrator = match integrator.map(|i| i.as_str()).transpose()? {
+            None | Some("phong") => {
+                // Phong doesn't bounce, so a depth would silently do nothing.
+                if let Some((key, depth)) = ["max-depth", "roulette-depth"].iter().find_map(|k| item.get(k).map(|d| (k, d))) {
+                    return Err(SceneError::parse(depth.line, &format!("{} only applies to the path integrator", key)));
+                }
+                Integrator::Phong
+            },
+            Some("path") => Integrator::PathTracer(settings),
+            Some(other) => {
+                return Err(SceneError::parse(integrator.map_or(item.line, |i| i.line),
+                                             &format!("unknown integrator '{}'", other)));
+            }
+        };
+
+        // The lens focuses on the point the camera looks at, unless told otherwise.
+        if let Some(aperture) = item.get("aperture") {
+            camera.aperture = aperture.as_f32()?;
+            if camera.aperture < 0.0 {
+                return Err(SceneError::parse(aperture.line, "the aperture can't be negative"));
+            }
+        }
+        camera.focal_distance = match item.get("focal-distance") {
+            Some(distance) => match distance.as_f32()? {
+                d if d > 0.0 => d,
+                _ => return Err(SceneError::parse(distance.line, "the focal distance must be greater than 0"))
+            },
+            None => (&to - &from).mag()
+        };
+        if let Some(blades) = item.get("blades") {
+            let count = blades.as_usize()?;
+            if count < 3 {
+                return Err(SceneError::parse(blades.line, "an aperture needs at least 3 blades"));
+            }
+            let rotation = item.get("blade-rotation").map_or(Ok(0.0), |r| r.as_f32())?;
+            camera.aperture_shape = ApertureShape::Polygon { blades: count, rotation };
+        }
+        if let Some(open) = item.get("shutter-open") {
+            camera.shutter_open = open.as_f32()?;
+        }
+        camera.shutter_close = match item.get("shutter-close") {
+            Some(close) => match close.as_f32()? {
+                t if t >= camera.shutter_open => t,
+                _ => return Err(SceneError::parse(close.line, "the shutter can't close before it opens"))
+            },
+            None => camera.shutter_open
+        };
+        Ok(camera)
+    }
+
+    fn light(&self, item: &Node) -> Result<Light, SceneError> {
+        let kind = match item.get("type") {
+            Some(kind) => kind.as_str()?,
+            None => "point"
+        };
+        let intensity = color(required(item, "intensity")?)?;
+        let mut light = match kind {
+            "point" => {
+                check_keys(item, "point light", &["add", "type", "at", "intensity", "attenuation", "power"])?;
+                Light::new(intensity, point(required(item, "at")?)?)
+            },
+            "area" => {
+                check_keys(item, "area light", &["add", "type", "corner", "uvec", "usteps", "vvec", "vsteps",
+                    "jitter", "intensity", "attenuation", "power"])?;
+                let mut area = AreaLight::new(
+                    point(required(item, "corner")?)?,
+                    vector(required(item, "uvec")?)?,
+                    steps(required(item, "usteps")?)?,
+                    vector(required(item, "vvec")?)?,
+                    steps(required(item, "vsteps")?)?
+                );
+                if let Some(jitter) = item.get("jitter") {
+                    area.jitter = jitter.as_bool()?;
+                }
+                Light::area(intensity, area)
+            },
+            "directional" => {
+                check_keys(item, "directional light", &["add", "type", "direction", "intensity", "power"])?;
+                Light::directional(intensity, vector(required(item, "direction")?)?)
+            },
+            "spot" => {
+                check_keys(item, "spot light", &["add", "type", "at", "direction", "inner-angle", "outer-angle",
+                    "intensity", "attenuation", "power"])?;
+                let inner = required(item, "inner-angle")?;
+                let outer = required(item, "outer-angle")?.as_f32()?;
+                if inner.as_f32()? > outer {
+                    return Err(SceneError::parse(inner.line, "the inner angle can't be wider than the outer angle"));
+                }
+                Light::spot(
+                    intensity,
+                    point(required(item, "at")?)?,
+                    vector(required(item, "direction")?)?,
+                    inner.as_f32()?,
+                    outer
+                )
+            },
+            other => {
+                let line = item.get("type").map_or(item.line, |n| n.line);
+                return Err(SceneError::parse(line, &format!("unknown light type '{}'", other)));
+            }
+        };
+
+        // Power replaces the attenuation, so an explicit attenuation is applied after it.
+        if let Some(power) = item.get("power") {
+            light = light.with_power(power.as_f32()?);
+        }
+        if let Some(attenuation) = item.get("attenuation") {
+            let [constant, linear, quadratic] = triple(attenuation)?;
+            light = light.with_attenuation(Attenuation::new(constant, linear, quadratic));
+        }
+        Ok(light)
+    }
+
+    fn sphere(&self, item: &Node) -> Result<Sphere, SceneError> {
+        check_keys(item, "sphere", &["add", "material", "transform", "motion"])?;
+        let mut sphere = Sphere::new();
+        if let Some(material) = item.get("material") {
+            sphere.material = self.material(material)?;
+        }
+        if let Some(transform) = item.get("transform") {
+            sphere.set_transform(invertible(self.transform(transform)?, item.line, "sphere's transform")?);
+        }
+        sphere.motion = self.motion(item, "sphere")?;
+        Ok(sphere)
+    }
+
+    fn triangle(&self, item: &Node) -> Result<Triangle, SceneError> {
+        check_keys(item, "triangle", &["add", "p1", "p2", "p3", "material", "transform", "motion"])?;
+        let mut triangle = Triangle::new(
+            point(required(item, "p1")?)?,
+            point(required(item, "p2")?)?,
+            point(required(item, "p3")?)?
+        );
+        if triangle.is_degenerate() {
+            return Err(SceneError::parse(item.line, "the triangle's corners are in a line, so it has no area"));
+        }
+        if let Some(material) = item.get("material") {
+            triangle.material = self.material(material)?;
+        }
+        if let Some(transform) = item.get("transform") {
+            triangle.set_transform(invertible(self.transform(transform)?, item.line, "triangle's transform")?);
+        }
+        triangle.motion = self.motion(item, "triangle")?;
+        Ok(triangle)
+    }
+
+    fn mesh(&self, item: &Node) -> Result<Vec<Triangle>, SceneError> {
+        check_keys(item, "mesh", &["add", "vertices", "faces", "material", "transform", "motion"])?;
+        let vertices = required(item, "vertices")?.as_sequence()?
+            .iter()
+            .map(point)
+            .collect::<Result<Vec<Tuple>, SceneError>>()?;
+        let transform = match item.get("transform") {
+            Some(transform) => invertible(self.transform(transform)?, item.line, "mesh's transform")?,
+            None => Matrix::identity()
+        };
+        let motion = self.motion(item, "mesh")?;
+
+        let mut triangles = Vec::new();
+        for face in required(item, "faces")?.as_sequence()? {
+            let indices = face.as_sequence()?.iter().map(|i| i.as_usize()).collect::<Result<Vec<usize>, SceneError>>()?;
+            if indices.len() != 3 {
+                return Err(SceneError::parse(face.line, &format!("expected 3 indices, found {}", indices.len())));
+            }
+            if let Some(i) = indices.iter().find(|i| **i >= vertices.len()) {
+                return Err(SceneError::parse(
+                    face.line,
+                    &format!("vertex {} doesn't exist, the mesh has {} vertices", i, vertices.len())
+                ));
+            }
+            let mut triangle = Triangle::new(vertices[indices[0]], vertices[indices[1]], vertices[indices[2]]);
+            if triangle.is_degenerate() {
+                return Err(SceneError::parse(face.line, "the face's corners are in a line, so it has no area"));
+            }
+            triangle.set_transform(transform);
+            triangle.motion = motion.clone();
+            if let Some(material) = item.get("material") {
+                triangle.material = self.material(material)?;
+            }
+            triangles.push(triangle);
+        }
+        Ok(triangles)
+    }
+
+    fn background(&self, item: &Node) -> Result<Background, SceneError> {
+        let kind = required(item, "type")?;
+        match kind.as_str()? {
+            "solid" => {
+                check_keys(item, "solid background", &["add", "type", "color"])?;
+                Ok(Background::Solid(color(required(item, "color")?)?))
+            },
+            "gradient" => {
+                check_keys(item, "gradient background", &["add", "type", "bottom", "top"])?;
+                Ok(Background::Gradient { bottom: color(required(item, "bottom")?)?, top: color(required(item, "top")?)? })
+            },
+            "map" => {
+                check_keys(item, "map background", &["add", "type", "file", "intensity", "rotation"])?;
+                let file = required(item, "file")?;
+                let mut map = EnvironmentMap::load(self.directory.join(file.as_str()?))
+                    .map_err(|e| SceneError::parse(file.line, &format!("can't read '{}': {}", file.as_str().unwrap_or(""), e)))?;
+                if let Some(intensity) = item.get("intensity") {
+                    map.intensity = intensity.as_f32()?;
+                }
+                if let Some(rotation) = item.get("rotation") {
+                    map.rotation = rotation.as_f32()?;
+                }
+                Ok(Background::Map(Box::new(map)))
+            },
+            other => Err(SceneError::parse(kind.line, &format!("unknown background '{}'", other)))
+        }
+    }
+
+    fn material(&self, node: &Node) -> Result<Material, SceneError> {
+        let node = match node.value {
+            Value::Scalar(_) => self.define(node)?,
+            _ => node
+        };
+        check_keys(node, "material", &["color", "ambient", "diffuse", "specular", "shininess", "emission", "bsdf"])?;
+        let mut material = Material::new();
+        if let Some(c) = node.get("color") {
+            material.color = color(c)?;
+        }
+        if let Some(ambient) = node.get("ambient") {
+            material.ambient = ambient.as_f32()?;
+        }
+        if let Some(diffuse) = node.get("diffuse") {
+            material.diffuse = diffuse.as_f32()?;
+        }
+        if let Some(specular) = node.get("specular") {
+            material.specular = specular.as_f32()?;
+        }
+        if let Some(shininess) = node.get("shininess") {
+            material.shininess = shininess.as_f32()?;
+        }
+        if let Some(emission) = node.get("emission") {
+            material.emission = color(emission)?;
+        }
+        if let Some(bsdf) = node.get("bsdf") {
+            material.bsdf = Some(bsdf_from_node(bsdf, material.color)?);
+        }
+        Ok(material)
+    }
+
+    // The object's keyframes, if it has any.
+    fn motion(&self, item: &Node, what: &str) -> Result<Option<Motion>, SceneError> {
+        let motion = match item.get("motion") {
+            Some(motion) => motion,
+            None => return Ok(None)
+        };
+        if item.get("transform").is_some() {
+            return Err(SceneError::parse(motion.line, "an object can't have both a transform and a motion"));
+        }
+        let keyframes = motion.as_sequence()?;
+        if keyframes.is_empty() {
+            return Err(SceneError::parse(motion.line, "a motion needs at least one keyframe"));
+        }
+        let keyframes = keyframes.iter().map(|keyframe| {
+            check_keys(keyframe, "keyframe", &["time", "transform"])?;
+            let transform = required(keyframe, "transform")?;
+            let what = format!("{}'s keyframe transform", what);
+            Ok((required(keyframe, "time")?.as_f32()?, invertible(self.transform(transform)?, transform.line, &what)?,
+                keyframe.line))
+        }).collect::<Result<Vec<_>, SceneError>>()?;
+        let result = Motion::from_keyframes(keyframes.iter().map(|(time, transform, _)| (*time, *transform)).collect());
+        // Every keyframe can be inverted, but the blend between them can't always be.
+        if let Some(time) = result.flips_at() {
+            let line = keyframes.iter().find(|(t, _, _)| *t == time).map_or(motion.line, |(_, _, line)| *line);
+            return Err(SceneError::parse(line, &format!(
+                "the {}'s keyframe mirrors it when the one before doesn't, so it would be squashed flat in between", what
+            )));
+        }
+        Ok(Some(result))
+    }
+
+    // Each entry is applied after the ones before it.
+    fn transform(&self, node: &Node) -> Result<Matrix, SceneError> {
+        self.transform_within(node, &mut Vec::new())
+    }
+
+    // expanding holds the names of the defines being expanded, so that a define that ends up
+    // including itself is an error instead of endless recursion.
+    fn transform_within(&self, node: &Node, expanding: &mut Vec<String>) -> Result<Matrix, SceneError> {
+        let mut matrix = Matrix::identity();
+        for entry in node.as_sequence()? {
+            let step = match entry.value {
+                Value::Scalar(_) => {
+                    let name = entry.as_str()?.to_owned();
+                    if expanding.contains(&name) {
+                        let cycle = format!("{} -> {}", expanding.join(" -> "), name);
+                        return Err(SceneError::parse(entry.line, &format!("the define '{}' includes itself: {}", name, cycle)));
+                    }
+                    let define = self.define(entry)?;
+                    expanding.push(name);
+                    let step = self.transform_within(define, expanding)?;
+                    expanding.pop();
+                    step
+                },
+                _ => transform_step(entry)?
+            };
+            matrix = matrix.then(&step);
+        }
+        Ok(matrix)
+    }
+}
+
+// Replaces every animated value in node with its value at time.
+fn resolve(node: &Node, time: f32) -> Result<Node, SceneError> {
+    resolve_value(node, time, false)
+}
+
+// Keys that hold whole numbers, which still have to read as whole numbers between keyframes.
+const WHOLE_NUMBER_KEYS: [&str; 9] =
+    ["width", "height", "samples", "max-depth", "roulette-depth", "blades", "usteps", "vsteps", "faces"];
+
+// whole is set for the value of one of WHOLE_NUMBER_KEYS, and rounds the numbers blended in it.
+fn resolve_value(node: &Node, time: f32, whole: bool) -> Result<Node, SceneError> {
+    match &node.value {
+        Value::Mapping(entries) if entries.len() == 1 && entries[0].0 == "keyframes" => {
+            let track = keyframes(&entries[0].1)?;
+            let (k0, k1, f) = track.segment(time);
+            let blended = blend(&resolve_value(&k0.value, time, whole)?, &resolve_value(&k1.value, time, whole)?, f);
+            Ok(if whole { round(&blended) } else { blended })
+        },
+        Value::Mapping(entries) => {
+            let entries = entries.iter()
+                .map(|(key, value)| {
+                    let whole = WHOLE_NUMBER_KEYS.contains(&key.as_str());
+                    Ok((key.clone(), resolve_value(value, time, whole)?))
+                })
+                .collect::<Result<Vec<(String, Node)>, SceneError>>()?;
+            Ok(Node::new(Value::Mapping(entries), node.line))
+        },
+        Value::Sequence(items) => {
+            let items = items.iter()
+                .map(|item| resolve_value(item, time, whole))
+                .collect::<Result<Vec<Node>, SceneError>>()?;
+            Ok(Node::new(Value::Sequence(items), node.line))
+        },
+        Value::Scalar(_) => Ok(node.clone())
+    }
+}
+
+// Rounds every number in node to the nearest whole number.
+fn round(node: &Node) -> Node {
+    let value = match &node.value {
+        Value::Scalar(x) => match x.parse::<f32>() {
+            Ok(x) => Value::Scalar(x.round().to_string()),
+            Err(_) => node.value.clone()
+        },
+        Value::Sequence(items) => Value::Sequence(items.iter().map(round).collect()),
+        Value::Mapping(entries) => Value::Mapping(entries.iter().map(|(key, value)| (key.clone(), round(value))).collect())
+    };
+    Node::new(value, node.line)
+}
+
+fn keyframes(node: &Node) -> Result<Track<Node>, SceneError> {
+    let items = node.as_sequence()?;
+    if items.is_empty() {
+        return Err(SceneError::parse(node.line, "an animated value needs at least one keyframe"));
+    }
+    let keyframes = items.iter().map(|item| {
+        check_keys(item, "keyframe", &["time", "value", "interpolation"])?;
+        let interpolation = match item.get("interpolation") {
+            Some(interpolation) => match interpolation.as_str()? {
+                "linear" => Interpolation::Linear,
+                "smooth" => Interpolation::Smooth,
+                other => {
+                    return Err(SceneError::parse(interpolation.line, &format!("unknown interpolation '{}'", other)));
+                }
+            },
+            None => Interpolation::Linear
+        };
+        Ok(Keyframe { time: required(item, "time")?.as_f32()?, value: required(item, "value")?.clone(), interpolation })
+    }).collect::<Result<Vec<Keyframe<Node>>, SceneError>>()?;
+    Ok(Track::new(keyframes))
+}
+
+// Blends two keyframe values, f of the way from a to b.
+fn blend(a: &Node, b: &Node, f: f32) -> Node {
+    let value = match (&a.value, &b.value) {
+        (Value::Scalar(x), Value::Scalar(y)) => match (x.parse::<f32>(), y.parse::<f32>()) {
+            (Ok(x), Ok(y)) => Value::Scalar(x.lerp(&y, f).to_string()),
+            _ => a.value.clone()
+        },
+        (Value::Sequence(xs), Value::Sequence(ys)) if xs.len() == ys.len() => {
+            Value::Sequence(xs.iter().zip(ys.iter()).map(|(x, y)| blend(x, y, f)).collect())
+        },
+        (Value::Mapping(xs), Value::Mapping(ys)) if xs.len() == ys.len() && xs.iter().zip(ys.iter()).all(|(x, y)| x.0 == y.0) => {
+            Value::Mapping(xs.iter().zip(ys.iter()).map(|(x, y)| (x.0.clone(), blend(&x.1, &y.1, f))).collect())
+        },
+        _ => a.value.clone()
+    };
+    Node::new(value, a.line)
+}
+
+// Rays are taken into an object's space with the inverse of its transform, so a transform that
+// squashes the object flat can't be used.
+fn invertible(matrix: Matrix, line: usize, what: &str) -> Result<Matrix, SceneError> {
+    match matrix.try_inverse() {
+        Ok(_) => Ok(matrix),
+        Err(_) => Err(SceneError::parse(line, &format!("the {} can't be inverted, it scales something to nothing", what)))
+    }
+}
+
+fn transform_step(entry: &Node) -> Result<Matrix, SceneError> {
+    let parts = entry.as_sequence()?;
+    if parts.is_empty() {
+        return Err(SceneError::parse(entry.line, "empty transform"));
+    }
+    let name = parts[0].as_str()?;
+    let args = parts[1..].iter().map(|n| n.as_f32()).collect::<Result<Vec<f32>, SceneError>>()?;
+    let expected = match name {
+        "translate" | "scale" => 3,
+        "rotate-x" | "rotate-y" | "rotate-z" => 1,
+        "shear" => 6,
+        other => return Err(SceneError::parse(entry.line, &format!("unknown transform '{}'", other)))
+    };
+    if args.len() != expected {
+        return Err(SceneError::parse(
+            entry.line,
+            &format!("'{}' takes {} numbers, found {}", name, expected, args.len())
+        ));
+    }
+    Ok(match name {
+        "translate" => Matrix::translation(args[0], args[1], args[2]),
+        "scale" => Matrix::scaling(args[0], args[1], args[2]),
+        "rotate-x" => Matrix::rotation(args[0], RotationAxis::X),
+        "rotate-y" => Matrix::rotation(args[0], RotationAxis::Y),
+        "rotate-z" => Matrix::rotation(args[0], RotationAxis::Z),
+        _ => Matrix::shearing(args[0], args[1], args[2], args[3], args[4], args[5])
+    })
+}
+
+// Colors default to the material's color, roughness to 0, metalness to 1, ior to 1.5 and tint to
+// white.
+fn bsdf_from_node(node: &Node, material_color: Tuple) -> Result<Bsdf, SceneError> {
+    let number = |key: &str, default: f32| match node.get(key) {
+        Some(n) => {
+            let value = n.as_f32()?;
+            match parameter_problem(key, value) {
+                Some(problem) => Err(SceneError::parse(n.line, &problem)),
+                None => Ok(value)
+            }
+        },
+        None => Ok(default)
+    };
+    let color_or = |key: &str, default: Tuple| node.get(key).map_or(Ok(default), color);
+    let kind = required(node, "type")?;
+    match kind.as_str()? {
+        "lambert" => {
+            check_keys(node, "lambert bsdf", &["type", "albedo"])?;
+            Ok(Bsdf::Lambert { albedo: color_or("albedo", material_color)? })
+        },
+        "conductor" => {
+            check_keys(node, "conductor bsdf", &["type", "color", "roughness", "metalness"])?;
+            Ok(Bsdf::Conductor {
+                color: color_or("color", material_color)?,
+                roughness: number("roughness", 0.0)?,
+                metalness: number("metalness", 1.0)?
+            })
+        },
+        "dielectric" => {
+            check_keys(node, "dielectric bsdf", &["type", "ior", "roughness", "tint"])?;
+            Ok(Bsdf::Dielectric {
+                ior: number("ior", 1.5)?,
+                roughness: number("roughness", 0.0)?,
+                tint: color_or("tint", Tuple::color(1.0, 1.0, 1.0))?
+            })
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown bsdf '{}'", other)))
+    }
+}
+
+fn required<'a>(item: &'a Node, key: &str) -> Result<&'a Node, SceneError> {
+    item.get(key).ok_or_else(|| SceneError::parse(item.line, &format!("missing '{}'", key)))
+}
+
+// Rejects keys that aren't in allowed, so that typos don't get silently ignored.
+fn check_keys(item: &Node, what: &str, allowed: &[&str]) -> Result<(), SceneError> {
+    for (key, value) in item.as_mapping()? {
+        if !allowed.contains(&key.as_str()) {
+            return Err(SceneError::parse(value.line, &format!("unknown key '{}' for {}", key, what)));
+        }
+    }
+    Ok(())
+}
+
+fn triple(node: &Node) -> Result<[f32; 3], SceneError> {
+    let values = node.as_f32_list()?;
+    if values.len() != 3 {
+        return Err(SceneError::parse(node.line, &format!("expected 3 numbers, found {}", values.len())));
+    }
+    Ok([values[0], values[1], values[2]])
+}
+
+fn point(node: &Node) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(node)?;
+    Ok(Tuple::point(x, y, z))
+}
+
+fn vector(node: &Node) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(node)?;
+    Ok(Tuple::vector(x, y, z))
+}
+
+fn color(node: &Node) -> Result<Tuple, SceneError> {
+    let [r, g, b] = triple(node)?;
+    Ok(Tuple::color(r, g, b))
+}
+
+fn steps(node: &Node) -> Result<usize, SceneError> {
+    match node.as_usize()? {
+        0 => Err(SceneError::parse(node.line, "the number of steps must be at least 1")),
+        n => Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+    use crate::ray_tracer::enums::light_types::LightTypes;
+    use crate::ray_tracer::enums::object_types::ObjectTypes;
+    use std::f32::consts::PI;
+
+    const CAMERA: &str = "- add: camera\n  width: 100\n  height: 50\n  field-of-view: 0.785\n  from: [ 0, 1.5, -5 ]\n  to: [ 0, 1, 0 ]\n  up: [ 0, 1, 0 ]\n";
+
+    #[test]
+    fn load_camera() {
+        let scene = Scene::parse(CAMERA).unwrap();
+        let expected = Matrix::view_transform(Tuple::point(0.0, 1.5, -5.0), Tuple::point(0.0, 1.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0));
+        assert!(
+            scene.camera.hsize == 100 && scene.camera.vsize == 50 && f_equal(scene.camera.field_of_view, 0.785) &&
+            scene.camera.transform == expected,
+            "The camera was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn scene_without_camera_is_an_error() {
+        let result = Scene::parse("- add: sphere\n");
+        assert!(
+            result.is_err(),
+            "A scene without a camera should be an error."
+        )
+    }
+
+    #[test]
+    fn load_lights() {
+        let source = format!("{}{}", CAMERA,
+            "- add: light\n  at: [ -10, 10, -10 ]\n  intensity: [ 1, 1, 1 ]\n\
+             - add: light\n  type: spot\n  at: [ 0, 5, 0 ]\n  direction: [ 0, -1, 0 ]\n  inner-angle: 0.2\n  outer-angle: 0.4\n  intensity: [ 1, 0.5, 0.5 ]\n  attenuation: [ 1, 0, 0.5 ]\n");
+        let scene = Scene::parse(&source).unwrap();
+        let lights = &scene.world.lights;
+        assert!(
+            lights.len() == 2 &&
+            lights[0] == Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(-10.0, 10.0, -10.0)) &&
+            lights[1].light_type == LightTypes::Spot {
+                direction: Tuple::vector(0.0, -1.0, 0.0), inner_angle: 0.2, outer_angle: 0.4
+            } &&
+            lights[1].attenuation == Attenuation::new(1.0, 0.0, 0.5),
+            "The lights were not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn transforms_apply_in_order() {
+        let source = format!("{}{}", CAMERA,
+            "- add: sphere\n  transform:\n    - [ rotate-x, 1.5707964 ]\n    - [ scale, 5, 5, 5 ]\n    - [ translate, 10, 5, 7 ]\n");
+        let scene = Scene::parse(&source).unwrap();
+        let p = Tuple::point(1.0, 0.0, 1.0);
+        let result = scene.world.objects[0].get_transform() * &p;
+        assert!(
+            result == Tuple::point(15.0, 0.0, 7.0),
+            "The transforms were not applied in order. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn define_that_includes_itself_is_an_error() {
+        let source = format!("{}{}", CAMERA,
+            "- define: a\n  value:\n    - b\n- define: b\n  value:\n    - [ scale, 2, 2, 2 ]\n    - a\n\
+             - add: sphere\n  transform:\n    - a\n");
+        let direct = format!("{}{}", CAMERA, "- define: a\n  value: [ a ]\n- add: sphere\n  transform: [ a ]\n");
+        assert!(
+            Scene::parse(&source).err() == Some(SceneError::parse(14, "the define 'a' includes itself: a -> b -> a")) &&
+            Scene::parse(&direct).err() == Some(SceneError::parse(9, "the define 'a' includes itself: a -> a")),
+            "A define that includes itself should be reported."
+        )
+    }
+
+    #[test]
+    fn defines_and_extend() {
+        let source = format!("{}{}", CAMERA,
+            "- define: white\n  value:\n    color: [ 1, 1, 1 ]\n    diffuse: 0.7\n    ambient: 0.2\n\
+             - define: blue\n  extend: white\n  value:\n    color: [ 0.5, 0.5, 1 ]\n\
+             - define: standard-transform\n  value:\n    - [ translate, 1, -1, 1 ]\n    - [ scale, 0.5, 0.5, 0.5 ]\n\
+             - add: sphere\n  material: blue\n  transform:\n    - standard-transform\n    - [ rotate-y, 3.1415927 ]\n");
+        let scene = Scene::parse(&source).unwrap();
+        let sphere = &scene.world.objects[0];
+        let material = sphere.get_material();
+        let expected = &(&Matrix::rotation(PI, RotationAxis::Y) * &Matrix::scaling(0.5, 0.5, 0.5)) *
+            &Matrix::translation(1.0, -1.0, 1.0);
+        assert!(
+            material.color == Tuple::color(0.5, 0.5, 1.0) && f_equal(material.diffuse, 0.7) &&
+            f_equal(material.ambient, 0.2) && *sphere.get_transform() == expected,
+            "The defines were not resolved correctly."
+        )
+    }
+
+    #[test]
+    fn unknown_key_reports_line() {
+        let source = format!("{}{}", CAMERA, "- add: sphere\n  materail: blue\n");
+        let result = Scene::parse(&source);
+        assert!(
+            matches!(result, Err(SceneError::Parse { line: 9, .. })),
+            "The unknown key was not reported on line 9."
+        )
+    }
+
+    #[test]
+    fn undefined_name_reports_line() {
+        let source = format!("{}{}", CAMERA, "- add: sphere\n  material: missing\n");
+        let result = Scene::parse(&source);
+        assert!(
+            matches!(result, Err(SceneError::Parse { line: 9, ref message }) if message == "'missing' hasn't been defined"),
+            "The undefined name was not reported correctly."
+        )
+    }
+
+    #[test]
+    fn wrong_number_of_transform_arguments() {
+        let source = format!("{}{}", CAMERA, "- add: sphere\n  transform:\n    - [ translate, 1, 2 ]\n");
+        let result = Scene::parse(&source);
+        assert!(
+            result.as_ref().err() == Some(&SceneError::parse(10, "'translate' takes 3 numbers, found 2")),
+            "The transform error was not reported correctly."
+        )
+    }
+
+    #[test]
+    fn load_example_scene() {
+        let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/three_spheres.yml")).unwrap();
+        assert!(
+            scene.world.objects.len() == 4 && scene.world.lights.len() == 1 && scene.camera.hsize == 400,
+            "The example scene was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let result = Scene::load("does/not/exist.yml");
+        assert!(
+            matches!(result, Err(SceneError::Io(_))),
+            "A missing file should be reported as an IO error."
+        )
+    }
+
+    #[test]
+    fn load_path_tracing_camera() {
+        let source = format!("{}  integrator: path\n  max-depth: 5\n", CAMERA);
+        let scene = Scene::parse(&source).unwrap();
+        assert!(
+            scene.camera.integrator == Integrator::PathTracer(PathTracerSettings::new(5, 3)),
+            "The integrator was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn depth_without_path_integrator_is_an_error() {
+        let phong = format!("{}  max-depth: 5\n", CAMERA);
+        let explicit = format!("{}  integrator: phong\n  roulette-depth: 2\n", CAMERA);
+        assert!(
+            Scene::parse(&phong).err() == Some(SceneError::parse(8, "max-depth only applies to the path integrator")) &&
+            Scene::parse(&explicit).err() == Some(SceneError::parse(9, "roulette-depth only applies to the path integrator")),
+            "A depth on a Phong camera should be reported."
+        )
+    }
+
+    #[test]
+    fn inverted_spot_cone_is_an_error() {
+        let source = format!("{}{}", CAMERA,
+            "- add: light\n  type: spot\n  at: [ 0, 5, 0 ]\n  direction: [ 0, -1, 0 ]\n  inner-angle: 0.5\n  outer-angle: 0.3\n  intensity: [ 1, 1, 1 ]\n");
+        assert!(
+            Scene::parse(&source).err() == Some(SceneError::parse(12, "the inner angle can't be wider than the outer angle")),
+            "A spot light whose inner cone is wider than its outer cone should be reported. Result: {:?}", Scene::parse(&source).err()
+        )
+    }
+
+    #[test]
+    fn load_emissive_triangle() {
+        let source = format!("{}{}", CAMERA,
+            "- add: triangle\n  p1: [ 0, 1, 0 ]\n  p2: [ -1, 0, 0 ]\n  p3: [ 1, 0, 0 ]\n  material:\n    emission: [ 5, 5, 4 ]\n");
+        let scene = Scene::parse(&source).unwrap();
+        let object = &scene.world.objects[0];
+        assert!(
+            object.get_object_type() == ObjectTypes::Triangle {
+                p1: Tuple::point(0.0, 1.0, 0.0), p2: Tuple::point(-1.0, 0.0, 0.0), p3: Tuple::point(1.0, 0.0, 0.0)
+            } &&
+            object.get_material().emission == Tuple::color(5.0, 5.0, 4.0),
+            "The emissive triangle was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn load_mesh() {
+        let source = format!("{}{}", CAMERA,
+            "- add: mesh\n  vertices:\n    - [ 0, 0, 0 ]\n    - [ 1, 0, 0 ]\n    - [ 1, 0, 1 ]\n    - [ 0, 0, 1 ]\n  faces:\n    - [ 0, 1, 2 ]\n    - [ 0, 2, 3 ]\n  transform:\n    - [ translate, 0, 2, 0 ]\n");
+        let scene = Scene::parse(&source).unwrap();
+        let objects = &scene.world.objects;
+        assert!(
+            objects.len() == 2 && objects[1].get_object_type() == ObjectTypes::Triangle {
+                p1: Tuple::point(0.0, 0.0, 0.0), p2: Tuple::point(1.0, 0.0, 1.0), p3: Tuple::point(0.0, 0.0, 1.0)
+            } &&
+            objects[1].get_transform() == &Matrix::translation(0.0, 2.0, 0.0),
+            "The mesh was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn mesh_face_out_of_range() {
+        let source = format!("{}{}", CAMERA,
+            "- add: mesh\n  vertices:\n    - [ 0, 0, 0 ]\n    - [ 1, 0, 0 ]\n    - [ 1, 0, 1 ]\n  faces:\n    - [ 0, 1, 3 ]\n");
+        let result = Scene::parse(&source);
+        assert!(
+            result.err() == Some(SceneError::parse(14, "vertex 3 doesn't exist, the mesh has 3 vertices")),
+            "The bad face index was not reported."
+        )
+    }
+
+    #[test]
+    fn flat_triangles_are_errors() {
+        let mesh = format!("{}{}", CAMERA,
+            "- add: mesh\n  vertices:\n    - [ 0, 0, 0 ]\n    - [ 1, 0, 0 ]\n    - [ 2, 0, 0 ]\n  faces:\n    - [ 0, 1, 2 ]\n");
+        let triangle = format!("{}{}", CAMERA, "- add: triangle\n  p1: [ 0, 0, 0 ]\n  p2: [ 1, 1, 1 ]\n  p3: [ 1, 1, 1 ]\n");
+        assert!(
+            Scene::parse(&mesh).err() == Some(SceneError::parse(14, "the face's corners are in a line, so it has no area")) &&
+            Scene::parse(&triangle).err() == Some(SceneError::parse(8, "the triangle's corners are in a line, so it has no area")),
+            "Triangles without an area should be reported."
+        )
+    }
+
+    #[test]
+    fn load_bsdf() {
+        let source = format!("{}{}", CAMERA,
+            "- add: sphere\n  material:\n    color: [ 0.9, 0.6, 0.2 ]\n    bsdf:\n      type: conductor\n      roughness: 0.3\n\
+             - add: sphere\n  material:\n    bsdf:\n      type: dielectric\n      ior: 1.33\n");
+        let scene = Scene::parse(&source).unwrap();
+        let objects = &scene.world.objects;
+        assert!(
+            objects[0].get_material().bsdf == Some(Bsdf::Conductor {
+                color: Tuple::color(0.9, 0.6, 0.2), roughness: 0.3, metalness: 1.0
+            }) &&
+            objects[1].get_material().bsdf == Some(Bsdf::Dielectric {
+                ior: 1.33, roughness: 0.0, tint: Tuple::color(1.0, 1.0, 1.0)
+            }),
+            "The BSDFs were not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn bsdf_numbers_out_of_range_are_errors() {
+        let conductor = format!("{}{}", CAMERA, "- add: sphere\n  material:\n    bsdf:\n      type: conductor\n      metalness: 1.5\n");
+        let dielectric = format!("{}{}", CAMERA,
+            "- add: sphere\n  material:\n    bsdf:\n      type: dielectric\n      ior: 1.5\n      roughness: -0.1\n");
+        let ior = format!("{}{}", CAMERA, "- add: sphere\n  material:\n    bsdf:\n      type: dielectric\n      ior: 0\n");
+        assert!(
+            Scene::parse(&conductor).err() == Some(SceneError::parse(12, "metalness must be between 0 and 1, found 1.5")) &&
+            Scene::parse(&dielectric).err() == Some(SceneError::parse(13, "roughness can't be negative, found -0.1")) &&
+            Scene::parse(&ior).err() == Some(SceneError::parse(12, "ior must be greater than 0, found 0")),
+            "The BSDF numbers out of range were not reported."
+        )
+    }
+
+    #[test]
+    fn unknown_bsdf_is_an_error() {
+        let source = format!("{}{}", CAMERA, "- add: sphere\n  material:\n    bsdf:\n      type: velvet\n");
+        let result = Scene::parse(&source);
+        assert!(
+            result.err() == Some(SceneError::parse(11, "unknown bsdf 'velvet'")),
+            "The unknown BSDF was not reported."
+        )
+    }
+
+    #[test]
+    fn load_lens() {
+        let source = format!("{}{}", CAMERA, "  aperture: 0.1\n  blades: 6\n  blade-rotation: 0.5\n");
+        let scene = Scene::parse(&source).unwrap();
+        let with_focal_distance = format!("{}{}", CAMERA, "  aperture: 0.1\n  focal-distance: 2.5\n");
+        let focused = Scene::parse(&with_focal_distance).unwrap();
+        assert!(
+            f_equal(scene.camera.aperture, 0.1) && f_equal(scene.camera.focal_distance, 25.25_f32.sqrt()) &&
+            scene.camera.aperture_shape == ApertureShape::Polygon { blades: 6, rotation: 0.5 } &&
+            f_equal(focused.camera.focal_distance, 2.5) && focused.camera.aperture_shape == ApertureShape::Circle,
+            "The lens was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn too_few_blades_is_an_error() {
+        let source = format!("{}{}", CAMERA, "  blades: 2\n");
+        assert!(
+            Scene::parse(&source).err() == Some(SceneError::parse(8, "an aperture needs at least 3 blades")),
+            "An aperture with 2 blades should be rejected."
+        )
+    }
+
+    #[test]
+    fn load_motion() {
+        let source = format!("{}{}", CAMERA, concat!(
+            "  shutter-close: 0.5\n",
+            "- add: sphere\n",
+            "  motion:\n",
+            "    - time: 0\n",
+            "      transform:\n",
+            "        - [ translate, 0, 1, 0 ]\n",
+            "    - time: 1\n",
+            "      transform:\n",
+            "        - [ translate, 2, 1, 0 ]\n"
+        ));
+        let scene = Scene::parse(&source).unwrap();
+        let sphere = &scene.world.objects[0];
+        assert!(
+            scene.camera.shutter_open == 0.0 && scene.camera.shutter_close == 0.5 &&
+            sphere.transform_at(0.5) == Matrix::translation(1.0, 1.0, 0.0),
+            "The shutter and the keyframes were not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn transform_and_motion_is_an_error() {
+        let source = format!("{}{}", CAMERA, "- add: sphere\n  transform: []\n  motion: []\n");
+        assert!(
+            Scene::parse(&source).err() == Some(SceneError::parse(10, "an object can't have both a transform and a motion")),
+            "An object with a transform and a motion should be rejected."
+        )
+    }
+
+    #[test]
+    fn motion_into_a_mirror_image_is_an_error() {
+        let source = format!("{}{}", CAMERA,
+            "- add: sphere\n  motion:\n    - time: 0\n      transform:\n        - [ scale, 1, 1, 1 ]\n    \
+             - time: 1\n      transform:\n        - [ scale, -1, 1, 1 ]\n");
+        let result = Scene::parse(&source).err();
+        assert!(
+            result == Some(SceneError::parse(13,
+                "the sphere's keyframe mirrors it when the one before doesn't, so it would be squashed flat in between")),
+            "A motion that is squashed flat between its keyframes should be rejected. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn transform_that_cant_be_inverted_is_an_error() {
+        let flat = format!("{}{}", CAMERA, "- add: sphere\n  transform:\n    - [ scale, 0, 1, 1 ]\n");
+        let moving = format!("{}{}", CAMERA,
+            "- add: mesh\n  vertices: []\n  faces: []\n  motion:\n    - time: 0\n      transform: []\n    - time: 1\n      transform:\n        - [ scale, 1, 0, 1 ]\n");
+        let camera = CAMERA.replace("to: [ 0, 1, 0 ]", "to: [ 0, 1.5, -5 ]");
+        assert!(
+            Scene::parse(&flat).err() == Some(SceneError::parse(8, "the sphere's transform can't be inverted, it scales something to nothing")) &&
+            Scene::parse(&moving).err() == Some(SceneError::parse(16, "the mesh's keyframe transform can't be inverted, it scales something to nothing")) &&
+            Scene::parse(&camera).err() == Some(SceneError::parse(1, "the camera's from, to and up don't give it a direction to look in")),
+            "Transforms that can't be inverted should be reported on their line."
+        )
+    }
+
+    #[test]
+    fn load_animated_values() {
+        let source = concat!(
+            "- add: camera\n",
+            "  width: 100\n",
+            "  height: 50\n",
+            "  field-of-view: 0.785\n",
+            "  from:\n",
+            "    keyframes:\n",
+            "      - time: 0\n",
+            "        value: [ 0, 1, -5 ]\n",
+            "        interpolation: smooth\n",
+            "      - time: 2\n",
+            "        value: [ 4, 1, -5 ]\n",
+            "  to: [ 0, 1, 0 ]\n",
+            "  up: [ 0, 1, 0 ]\n",
+            "- define: spin\n",
+            "  value:\n",
+            "    keyframes:\n",
+            "      - time: 0\n",
+            "        value:\n",
+            "          - [ rotate-y, 0 ]\n",
+            "      - time: 2\n",
+            "        value:\n",
+            "          - [ rotate-y, 3.1415927 ]\n",
+            "- add: sphere\n",
+            "  material:\n",
+            "    diffuse:\n",
+            "      keyframes:\n",
+            "        - time: 1\n",
+            "          value: 0.2\n",
+            "        - time: 3\n",
+            "          value: 0.6\n",
+            "  transform:\n",
+            "    - [ translate, 1, 0, 0 ]\n",
+            "    - spin\n"
+        );
+        let scene = Scene::parse_at(source, 0.5).unwrap();
+        let from = Tuple::point(4.0 * Interpolation::Smooth.ease(0.25), 1.0, -5.0);
+        let sphere = &scene.world.objects[0];
+        let start = Scene::parse(source).unwrap();
+        assert!(
+            scene.camera.transform == Matrix::view_transform(from, Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)) &&
+            *sphere.get_transform() == &Matrix::rotation(PI / 4.0, RotationAxis::Y) * &Matrix::translation(1.0, 0.0, 0.0) &&
+            f_equal(sphere.get_material().diffuse, 0.2) &&
+            f_equal(Scene::parse_at(source, 2.0).unwrap().world.objects[0].get_material().diffuse, 0.4) &&
+            *start.world.objects[0].get_transform() == Matrix::translation(1.0, 0.0, 0.0),
+            "The animated values were not resolved for the time correctly."
+        )
+    }
+
+    #[test]
+    fn animated_whole_numbers_are_rounded() {
+        let source = format!("{}{}", CAMERA, "  samples:\n    keyframes:\n      - time: 0\n        value: 4\n      - time: 1\n        value: 11\n\
+            - add: sphere\n  material:\n    diffuse:\n      keyframes:\n        - time: 0\n          value: 0\n        - time: 1\n          value: 1\n");
+        let scene = Scene::parse_at(&source, 0.5).unwrap();
+        assert!(
+            scene.camera.samples_per_pixel == 8 && f_equal(scene.world.objects[0].get_material().diffuse, 0.5),
+            "Whole numbers should be rounded between keyframes, and other numbers left alone."
+        )
+    }
+
+    #[test]
+    fn shutter_opens_at_the_scene_time() {
+        let source = format!("{}{}", CAMERA, "  shutter-close: 0.5\n");
+        let camera = Scene::parse_at(&source, 2.0).unwrap().camera;
+        assert!(
+            camera.shutter_open == 2.0 && camera.shutter_close == 2.5,
+            "The shutter times should be counted from the time the scene was loaded at."
+        )
+    }
+
+    #[test]
+    fn bad_keyframes() {
+        let empty = format!("{}{}", CAMERA, "- add: sphere\n  material:\n    diffuse:\n      keyframes: []\n");
+        let interpolation = format!("{}{}", CAMERA,
+            "- add: sphere\n  material:\n    diffuse:\n      keyframes:\n        - time: 0\n          value: 1\n          interpolation: cubic\n");
+        assert!(
+            Scene::parse(&empty).err() == Some(SceneError::parse(11, "an animated value needs at least one keyframe")) &&
+            Scene::parse(&interpolation).err() == Some(SceneError::parse(14, "unknown interpolation 'cubic'")),
+            "Bad keyframes should be rejected."
+        )
+    }
+
+    #[test]
+    fn load_gradient_background() {
+        let source = format!("{}{}", CAMERA, "- add: background\n  type: gradient\n  bottom: [1, 1, 1]\n  top: [0.5, 0.7, 1]\n");
+        let scene = Scene::parse(&source).unwrap();
+        assert!(
+            scene.world.background.color(Tuple::vector(0.0, 1.0, 0.0)) == Tuple::color(0.5, 0.7, 1.0),
+            "The gradient background was not loaded correctly."
+        )
+    }
+
+    #[test]
+    fn load_environment_map() {
+        // The sky map sits next to the scene, so it is found relative to the scene file.
+        let scene = Scene::load(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/outdoors.yml")).unwrap();
+        let loaded = match &scene.world.background {
+            Background::Map(map) => map.image.width == 128 && map.image.height == 64 && map.path.is_some(),
+            _ => false
+        };
+        assert!(
+            loaded,
+            "The environment map was not loaded from next to the scene file."
+        )
+    }
+
+    #[test]
+    fn missing_environment_map_reports_line() {
+        let source = format!("{}{}", CAMERA, "- add: background\n  type: map\n  file: does/not/exist.hdr\n");
+        let result = Scene::parse(&source);
+        assert!(
+            matches!(result, Err(SceneError::Parse { line: 10, .. })),
+            "The missing environment map was not reported on its line. Result: {:?}", result.err()
+        )
+    }
+}
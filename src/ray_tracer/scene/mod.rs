@@ -0,0 +1,5 @@
+pub mod error;
+pub mod yaml;
+pub mod loader;
+pub mod json;
+pub mod schema;
@@ -0,0 +1,911 @@
+use crate::ray_tracer::scene::error::SceneError;
+use crate::ray_tracer::scene::json::{self, Json};
+use crate::ray_tracer::scene::loader::Scene;
+use crate::ray_tracer::world::World;
+use crate::ray_tracer::camera::Camera;
+use crate::ray_tracer::light::{Light, AreaLight, Attenuation};
+use crate::ray_tracer::enums::light_types::LightTypes;
+use crate::ray_tracer::enums::object_types::ObjectTypes;
+use crate::ray_tracer::traits::object::Object;
+use crate::ray_tracer::material::Material;
+use crate::ray_tracer::bsdf::{Bsdf, parameter_problem};
+use crate::ray_tracer::matrix::{Matrix, MatrixError};
+use crate::ray_tracer::motion::Motion;
+use crate::ray_tracer::sphere::Sphere;
+use crate::ray_tracer::triangle::Triangle;
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::sampling::{SamplePattern, PixelFilter, ApertureShape};
+use crate::ray_tracer::integrator::{Integrator, PathTracerSettings};
+use crate::ray_tracer::environment::{Background, EnvironmentMap};
+use crate::ray_tracer::scene::json::JsonValue;
+use std::fs;
+use std::path::Path;
+
+// JSON representation of a World, and optionally the Camera looking at it. Every field of every
+// struct is written out as it is stored, so exporting and importing gives back exactly the same
+// values:
+//
+//   {
+//     "version": 1,
+//     "camera": { "hsize", "vsize", "field_of_view", "transform", "samples_per_pixel",
+//                 "sample_pattern", "filter",
+//                 "integrator": { "type": "phong" } or
+//                               { "type": "path", "max_depth", "roulette_depth" },
+//                 "aperture", "focal_distance", "shutter_open", "shutter_close",
+//                 "aperture_shape": { "type": "circle" } or
+//                                   { "type": "polygon", "blades", "rotation" } },
+//     "lights": [ { "type": "point" | "area" | "directional" | "spot", "intensity", "position",
+//                   "attenuation": [constant, linear, quadratic], "direct_scale",
+//                   ...type specific fields } ],
+//     "objects": [ { "type": "sphere", "origin", "transform", "material", "motion" } or
+//                  { "type": "triangle", "p1", "p2", "p3", "transform", "material", "motion" } ],
+//     "background": { "type": "solid", "color" } or { "type": "gradient", "bottom", "top" } or
+//                   { "type": "map", "file", "intensity", "rotation" }
+//   }
+//
+// Materials hold "color", "ambient", "diffuse", "specular", "shininess", "emission" and optionally
+// a "bsdf": { "type": "lambert", "albedo" },
+// { "type": "conductor", "color", "roughness", "metalness" } or
+// { "type": "dielectric", "ior", "roughness", "tint" }.
+//
+// Points, vectors and colors are arrays of 3 numbers and matrices are arrays of 4 rows. Unlike the
+// scene file format, an area light's uvec and vvec are the edges of a single cell. Unknown fields
+// are errors. An object's "motion" is null when it holds still, or a list of
+// { "time", "transform" } keyframes. An environment map is stored as the path of its image,
+// relative to the document when it is loaded with Scene::load_json. The path is null for a map
+// that wasn't loaded from a file, and such documents can't be read back.
+//
+// SCHEMA_VERSION changes whenever a change would stop older files from loading, so fields added
+// later, like the camera's integrator and lens or a light's direct_scale, are optional when
+// reading.
+pub const SCHEMA_VERSION: usize = 1;
+
+impl Scene {
+    pub fn to_json(&self) -> String {
+        document(&self.world, Some(&self.camera)).to_pretty_string()
+    }
+
+    // Files the document refers to are looked for relative to the current directory.
+    pub fn from_json(source: &str) -> Result<Scene, SceneError> {
+        Scene::from_json_in(source, Path::new(""))
+    }
+
+    // Files the document refers to are looked for relative to its directory.
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let source = fs::read_to_string(path.as_ref())?;
+        Scene::from_json_in(&source, path.as_ref().parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    fn from_json_in(source: &str, directory: &Path) -> Result<Scene, SceneError> {
+        let (world, camera, line) = read_document(source, directory)?;
+        match camera {
+            Some(camera) => Ok(Scene { world, camera }),
+            None => Err(SceneError::parse(line, "missing field 'camera'"))
+        }
+    }
+}
+
+pub fn world_to_json(world: &World) -> String {
+    document(world, None).to_pretty_string()
+}
+
+// Loads the world from a document. A camera in the document is checked, but not returned.
+pub fn world_from_json(source: &str) -> Result<World, SceneError> {
+    let (world, _, _) = read_document(source, Path::new(""))?;
+    Ok(world)
+}
+
+fn document(world: &World, camera: Option<&Camera>) -> Json {
+    let mut fields = vec![("version", Json::integer(SCHEMA_VERSION))];
+    if let Some(camera) = camera {
+        fields.push(("camera", camera_to_json(camera)));
+    }
+    fields.push(("lights", Json::array(world.lights.iter().map(light_to_json).collect())));
+    fields.push(("objects", Json::array(world.objects.iter().map(|o| object_to_json(o.as_ref())).collect())));
+    fields.push(("background", background_to_json(&world.background)));
+    Json::object(fields)
+}
+
+fn read_document(source: &str, directory: &Path) -> Result<(World, Option<Camera>, usize), SceneError> {
+    let doc = json::parse(source)?;
+    doc.check_fields("the document", &["version", "camera", "lights", "objects", "background"])?;
+
+    let version = doc.field("version")?;
+    match version.as_usize()? {
+        SCHEMA_VERSION => {},
+        v => return Err(SceneError::parse(
+            version.line,
+            &format!("unsupported schema version {}, expected {}", v, SCHEMA_VERSION)
+        ))
+    }
+
+    let mut world = World::empty();
+    for light in doc.field("lights")?.as_array()? {
+        world.lights.push(light_from_json(light)?);
+    }
+    for (i, object) in doc.field("objects")?.as_array()?.iter().enumerate() {
+        world.objects.push(object_from_json(object, i)?);
+    }
+    if let Some(background) = doc.get("background") {
+        world.background = background_from_json(background, directory)?;
+    }
+    let camera = match doc.get("camera") {
+        Some(camera) => Some(camera_from_json(camera)?),
+        None => None
+    };
+    Ok((world, camera, doc.line))
+}
+
+fn tuple_to_json(t: &Tuple) -> Json {
+    Json::numbers(&[t.x, t.y, t.z])
+}
+
+fn triple(value: &Json) -> Result<[f32; 3], SceneError> {
+    let values = value.as_f32_array()?;
+    if values.len() != 3 {
+        return Err(SceneError::parse(value.line, &format!("expected 3 numbers, found {}", values.len())));
+    }
+    Ok([values[0], values[1], values[2]])
+}
+
+fn point(value: &Json) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(value)?;
+    Ok(Tuple::point(x, y, z))
+}
+
+fn vector(value: &Json) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(value)?;
+    Ok(Tuple::vector(x, y, z))
+}
+
+fn color(value: &Json) -> Result<Tuple, SceneError> {
+    let [r, g, b] = triple(value)?;
+    Ok(Tuple::color(r, g, b))
+}
+
+fn matrix_to_json(m: &Matrix) -> Json {
+    Json::array(m.value.iter().map(|row| Json::numbers(row)).collect())
+}
+
+fn matrix_from_json(value: &Json) -> Result<Matrix, SceneError> {
+    let rows = value.as_array()?;
+    if rows.len() != 4 {
+        return Err(SceneError::parse(value.line, &format!("expected 4 rows, found {}", rows.len())));
+    }
+    let numbers = rows.iter().map(|row| row.as_f32_array()).collect::<Result<Vec<Vec<f32>>, SceneError>>()?;
+    Matrix::from_rows(&numbers).map_err(|e| match e {
+        MatrixError::WrongRowLength { row, length } => {
+            SceneError::parse(rows[row].line, &format!("expected 4 numbers, found {}", length))
+        },
+        _ => SceneError::parse(value.line, &format!("expected 4 rows, found {}", rows.len()))
+    })
+}
+
+// Objects and the camera need a transform that can be inverted. what names its owner.
+fn invertible_matrix_from_json(value: &Json, what: &str) -> Result<Matrix, SceneError> {
+    let matrix = matrix_from_json(value)?;
+    match matrix.try_inverse() {
+        Ok(_) => Ok(matrix),
+        Err(_) => Err(SceneError::parse(value.line, &format!("the transform of {} can't be inverted", what)))
+    }
+}
+
+fn material_to_json(m: &Material) -> Json {
+    let mut fields = vec![
+        ("color", tuple_to_json(&m.color)),
+        ("ambient", Json::number(m.ambient)),
+        ("diffuse", Json::number(m.diffuse)),
+        ("specular", Json::number(m.specular)),
+        ("shininess", Json::number(m.shininess)),
+        ("emission", tuple_to_json(&m.emission))
+    ];
+    if let Some(bsdf) = &m.bsdf {
+        fields.push(("bsdf", bsdf_to_json(bsdf)));
+    }
+    Json::object(fields)
+}
+
+fn material_from_json(value: &Json) -> Result<Material, SceneError> {
+    value.check_fields("material", &["color", "ambient", "diffuse", "specular", "shininess", "emission", "bsdf"])?;
+    Ok(Material {
+        color: color(value.field("color")?)?,
+        ambient: value.field("ambient")?.as_f32()?,
+        diffuse: value.field("diffuse")?.as_f32()?,
+        specular: value.field("specular")?.as_f32()?,
+        shininess: value.field("shininess")?.as_f32()?,
+        emission: match value.get("emission") {
+            Some(emission) => color(emission)?,
+            None => Tuple::color(0.0, 0.0, 0.0)
+        },
+        bsdf: value.get("bsdf").map(bsdf_from_json).transpose()?
+    })
+}
+
+fn bsdf_to_json(bsdf: &Bsdf) -> Json {
+    match bsdf {
+        Bsdf::Lambert { albedo } => Json::object(vec![
+            ("type", Json::string("lambert")),
+            ("albedo", tuple_to_json(albedo))
+        ]),
+        Bsdf::Conductor { color, roughness, metalness } => Json::object(vec![
+            ("type", Json::string("conductor")),
+            ("color", tuple_to_json(color)),
+            ("roughness", Json::number(*roughness)),
+            ("metalness", Json::number(*metalness))
+        ]),
+        Bsdf::Dielectric { ior, roughness, tint } => Json::object(vec![
+            ("type", Json::string("dielectric")),
+            ("ior", Json::number(*ior)),
+            ("roughness", Json::number(*roughness)),
+            ("tint", tuple_to_json(tint))
+        ])
+    }
+}
+
+fn bsdf_from_json(value: &Json) -> Result<Bsdf, SceneError> {
+    let kind = value.field("type")?;
+    match kind.as_str()? {
+        "lambert" => {
+            value.check_fields("lambert bsdf", &["type", "albedo"])?;
+            Ok(Bsdf::Lambert { albedo: color(value.field("albedo")?)? })
+        },
+        "conductor" => {
+            value.check_fields("conductor bsdf", &["type", "color", "roughness", "metalness"])?;
+            Ok(Bsdf::Conductor {
+                color: color(value.field("color")?)?,
+                roughness: bsdf_number(value, "roughness")?,
+                metalness: bsdf_number(value, "metalness")?
+            })
+        },
+        "dielectric" => {
+            value.check_fields("dielectric bsdf", &["type", "ior", "roughness", "tint"])?;
+            Ok(Bsdf::Dielectric {
+                ior: bsdf_number(value, "ior")?,
+                roughness: bsdf_number(value, "roughness")?,
+                tint: color(value.field("tint")?)?
+            })
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown bsdf '{}'", other)))
+    }
+}
+
+fn bsdf_number(value: &Json, name: &str) -> Result<f32, SceneError> {
+    let field = value.field(name)?;
+    let number = field.as_f32()?;
+    match parameter_problem(name, number) {
+        Some(problem) => Err(SceneError::parse(field.line, &problem)),
+        None => Ok(number)
+    }
+}
+
+fn background_to_json(background: &Background) -> Json {
+    match background {
+        Background::Solid(color) => Json::object(vec![
+            ("type", Json::string("solid")),
+            ("color", tuple_to_json(color))
+        ]),
+        Background::Gradient { bottom, top } => Json::object(vec![
+            ("type", Json::string("gradient")),
+            ("bottom", tuple_to_json(bottom)),
+            ("top", tuple_to_json(top))
+        ]),
+        Background::Map(map) => Json::object(vec![
+            ("type", Json::string("map")),
+            ("file", match &map.path {
+                Some(path) => Json::string(&path.to_string_lossy()),
+                None => Json::new(JsonValue::Null)
+            }),
+            ("intensity", Json::number(map.intensity)),
+            ("rotation", Json::number(map.rotation))
+        ])
+    }
+}
+
+fn background_from_json(value: &Json, directory: &Path) -> Result<Background, SceneError> {
+    let kind = value.field("type")?;
+    match kind.as_str()? {
+        "solid" => {
+            value.check_fields("solid background", &["type", "color"])?;
+            Ok(Background::Solid(color(value.field("color")?)?))
+        },
+        "gradient" => {
+            value.check_fields("gradient background", &["type", "bottom", "top"])?;
+            Ok(Background::Gradient { bottom: color(value.field("bottom")?)?, top: color(value.field("top")?)? })
+        },
+        "map" => {
+            value.check_fields("map background", &["type", "file", "intensity", "rotation"])?;
+            let file = value.field("file")?;
+            if file.value == JsonValue::Null {
+                return Err(SceneError::parse(file.line, "the environment map wasn't saved to a file"));
+            }
+            let mut map = EnvironmentMap::load(directory.join(file.as_str()?))
+                .map_err(|e| SceneError::parse(file.line, &format!("can't read '{}': {}", file.as_str().unwrap_or(""), e)))?;
+            map.intensity = value.field("intensity")?.as_f32()?;
+            map.rotation = value.field("rotation")?.as_f32()?;
+            Ok(Background::Map(Box::new(map)))
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown background '{}'", other)))
+    }
+}
+
+fn light_to_json(light: &Light) -> Json {
+    let a = &light.attenuation;
+    let mut fields = vec![
+        ("type", Json::string(match light.light_type {
+            LightTypes::Point => "point",
+            LightTypes::Area(_) => "area",
+            LightTypes::Directional { .. } => "directional",
+            LightTypes::Spot { .. } => "spot"
+        })),
+        ("intensity", tuple_to_json(&light.intensity)),
+        ("position", tuple_to_json(&light.position)),
+        ("attenuation", Json::numbers(&[a.constant, a.linear, a.quadratic])),
+        ("direct_scale", Json::number(light.direct_scale))
+    ];
+    match &light.light_type {
+        LightTypes::Point => {},
+        LightTypes::Area(area) => {
+            fields.push(("corner", tuple_to_json(&area.corner)));
+            fields.push(("uvec", tuple_to_json(&area.uvec)));
+            fields.push(("usteps", Json::integer(area.usteps())));
+            fields.push(("vvec", tuple_to_json(&area.vvec)));
+            fields.push(("vsteps", Json::integer(area.vsteps())));
+            fields.push(("jitter", Json::boolean(area.jitter)));
+        },
+        LightTypes::Directional { direction } => {
+            fields.push(("direction", tuple_to_json(direction)));
+        },
+        LightTypes::Spot { direction, inner_angle, outer_angle } => {
+            fields.push(("direction", tuple_to_json(direction)));
+            fields.push(("inner_angle", Json::number(*inner_angle)));
+            fields.push(("outer_angle", Json::number(*outer_angle)));
+        }
+    }
+    Json::object(fields)
+}
+
+fn light_from_json(value: &Json) -> Result<Light, SceneError> {
+    let common = ["type", "intensity", "position", "attenuation", "direct_scale"];
+    let kind = value.field("type")?;
+    let light_type = match kind.as_str()? {
+        "point" => {
+            value.check_fields("point light", &common)?;
+            LightTypes::Point
+        },
+        "area" => {
+            value.check_fields("area light", &[&common[..], &["corner", "uvec", "usteps", "vvec", "vsteps", "jitter"]].concat())?;
+            let mut area = AreaLight::from_cells(
+                point(value.field("corner")?)?,
+                vector(value.field("uvec")?)?,
+                steps(value.field("usteps")?)?,
+                vector(value.field("vvec")?)?,
+                steps(value.field("vsteps")?)?
+            );
+            area.jitter = value.field("jitter")?.as_bool()?;
+            LightTypes::Area(area)
+        },
+        "directional" => {
+            value.check_fields("directional light", &[&common[..], &["direction"]].concat())?;
+            LightTypes::Directional { direction: vector(value.field("direction")?)? }
+        },
+        "spot" => {
+            value.check_fields("spot light", &[&common[..], &["direction", "inner_angle", "outer_angle"]].concat())?;
+            LightTypes::Spot {
+                direction: vector(value.field("direction")?)?,
+                inner_angle: value.field("inner_angle")?.as_f32()?,
+                outer_angle: value.field("outer_angle")?.as_f32()?
+            }
+        },
+        other => return Err(SceneError::parse(kind.line, &format!("unknown light type '{}'", other)))
+    };
+    let [constant, linear, quadratic] = triple(value.field("attenuation")?)?;
+    Ok(Light {
+        intensity: color(value.field("intensity")?)?,
+        position: point(value.field("position")?)?,
+        light_type,
+        attenuation: Attenuation::new(constant, linear, quadratic),
+        direct_scale: value.get("direct_scale").map_or(Ok(1.0), |d| d.as_f32())?
+    })
+}
+
+fn steps(value: &Json) -> Result<usize, SceneError> {
+    match value.as_usize()? {
+        0 => Err(SceneError::parse(value.line, "the number of steps must be at least 1")),
+        n => Ok(n)
+    }
+}
+
+fn object_to_json(object: &dyn Object) -> Json {
+    let mut fields = match object.get_object_type() {
+        ObjectTypes::Sphere => vec![
+            ("type", Json::string("sphere")),
+            ("origin", tuple_to_json(object.get_origin()))
+        ],
+        ObjectTypes::Triangle { p1, p2, p3 } => vec![
+            ("type", Json::string("triangle")),
+            ("p1", tuple_to_json(&p1)),
+            ("p2", tuple_to_json(&p2)),
+            ("p3", tuple_to_json(&p3))
+        ]
+    };
+    fields.push(("transform", matrix_to_json(object.get_transform())));
+    fields.push(("material", material_to_json(object.get_material())));
+    fields.push(("motion", match object.get_motion() {
+        Some(motion) => Json::array(motion.keyframes.iter().map(|(time, transform)| Json::object(vec![
+            ("time", Json::number(*time)),
+            ("transform", matrix_to_json(transform))
+        ])).collect()),
+        None => Json::new(JsonValue::Null)
+    }));
+    Json::object(fields)
+}
+
+fn motion_from_json(value: &Json, what: &str) -> Result<Option<Motion>, SceneError> {
+    if value.value == JsonValue::Null {
+        return Ok(None);
+    }
+    let keyframes = value.as_array()?;
+    if keyframes.is_empty() {
+        return Err(SceneError::parse(value.line, "a motion needs at least one keyframe"));
+    }
+    let keyframes = keyframes.iter().map(|keyframe| {
+        keyframe.check_fields("keyframe", &["time", "transform"])?;
+        let time = keyframe.field("time")?.as_f32()?;
+        let what = format!("{}'s keyframe at time {}", what, time);
+        Ok((time, invertible_matrix_from_json(keyframe.field("transform")?, &what)?))
+    }).collect::<Result<Vec<_>, SceneError>>()?;
+    let motion = Motion::from_keyframes(keyframes);
+    // Every keyframe can be inverted, but the blend between them can't always be.
+    match motion.flips_at() {
+        Some(time) => Err(SceneError::parse(value.line, &format!(
+            "the keyframe of {} at time {} mirrors it when the one before doesn't, so it would be squashed flat in between",
+            what, time
+        ))),
+        None => Ok(Some(motion))
+    }
+}
+
+// index is the object's place in the list, for reporting which one is broken.
+fn object_from_json(value: &Json, index: usize) -> Result<Box<dyn Object>, SceneError> {
+    let kind = value.field("type")?;
+    let what = format!("object {} (a {})", index, kind.as_str()?);
+    let motion = |value: &Json| value.get("motion").map(|m| motion_from_json(m, &what)).transpose().map(Option::flatten);
+    match kind.as_str()? {
+        "sphere" => {
+            value.check_fields("sphere", &["type", "origin", "transform", "material", "motion"])?;
+            let mut sphere = Sphere::with_transform(invertible_matrix_from_json(value.field("transform")?, &what)?);
+            sphere.origin = point(value.field("origin")?)?;
+            sphere.material = material_from_json(value.field("material")?)?;
+            sphere.motion = motion(value)?;
+            Ok(Box::new(sphere))
+        },
+        "triangle" => {
+            value.check_fields("triangle", &["type", "p1", "p2", "p3", "transform", "material", "motion"])?;
+            let mut triangle = Triangle::new(
+                point(value.field("p1")?)?,
+                point(value.field("p2")?)?,
+                point(value.field("p3")?)?
+            );
+            if triangle.is_degenerate() {
+                return Err(SceneError::parse(value.line, &format!("the corners of {} are in a line, so it has no area", what)));
+            }
+            triangle.set_transform(invertible_matrix_from_json(value.field("transform")?, &what)?);
+            triangle.material = material_from_json(value.field("material")?)?;
+            triangle.motion = motion(value)?;
+            Ok(Box::new(triangle))
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown object type '{}'", other)))
+    }
+}
+
+fn camera_to_json(camera: &Camera) -> Json {
+    Json::object(vec![
+        ("hsize", Json::integer(camera.hsize)),
+        ("vsize", Json::integer(camera.vsize)),
+        ("field_of_view", Json::number(camera.field_of_view)),
+        ("transform", matrix_to_json(&camera.transform)),
+        ("samples_per_pixel", Json::integer(camera.samples_per_pixel)),
+        ("sample_pattern", Json::string(match camera.sample_pattern {
+            SamplePattern::Regular => "regular",
+            SamplePattern::Jittered => "jittered",
+            SamplePattern::Halton => "halton"
+        })),
+        ("filter", Json::string(match camera.filter {
+            PixelFilter::Box => "box",
+            PixelFilter::Tent => "tent",
+            PixelFilter::Gaussian => "gaussian",
+            PixelFilter::Mitchell => "mitchell"
+        })),
+        ("integrator", integrator_to_json(&camera.integrator)),
+        ("aperture", Json::number(camera.aperture)),
+        ("focal_distance", Json::number(camera.focal_distance)),
+        ("shutter_open", Json::number(camera.shutter_open)),
+        ("shutter_close", Json::number(camera.shutter_close)),
+        ("aperture_shape", match camera.aperture_shape {
+            ApertureShape::Circle => Json::object(vec![("type", Json::string("circle"))]),
+            ApertureShape::Polygon { blades, rotation } => Json::object(vec![
+                ("type", Json::string("polygon")),
+                ("blades", Json::integer(blades)),
+                ("rotation", Json::number(rotation))
+            ])
+        })
+    ])
+}
+
+fn aperture_shape_from_json(value: &Json) -> Result<ApertureShape, SceneError> {
+    let kind = value.field("type")?;
+    match kind.as_str()? {
+        "circle" => {
+            value.check_fields("circle aperture", &["type"])?;
+            Ok(ApertureShape::Circle)
+        },
+        "polygon" => {
+            value.check_fields("polygon aperture", &["type", "blades", "rotation"])?;
+            Ok(ApertureShape::Polygon {
+                blades: value.field("blades")?.as_usize()?,
+                rotation: value.field("rotation")?.as_f32()?
+            })
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown aperture shape '{}'", other)))
+    }
+}
+
+fn integrator_to_json(integrator: &Integrator) -> Json {
+    match integrator {
+        Integrator::Phong => Json::object(vec![("type", Json::string("phong"))]),
+        Integrator::PathTracer(settings) => Json::object(vec![
+            ("type", Json::string("path")),
+            ("max_depth", Json::integer(settings.max_depth)),
+            ("roulette_depth", Json::integer(settings.roulette_depth))
+        ])
+    }
+}
+
+fn integrator_from_json(value: &Json) -> Result<Integrator, SceneError> {
+    let kind = value.field("type")?;
+    match kind.as_str()? {
+        "phong" => {
+            value.check_fields("phong integrator", &["type"])?;
+            Ok(Integrator::Phong)
+        },
+        "path" => {
+            value.check_fields("path integrator", &["type", "max_depth", "roulette_depth"])?;
+            Ok(Integrator::PathTracer(PathTracerSettings {
+                max_depth: value.field("max_depth")?.as_usize()?,
+                roulette_depth: value.field("roulette_depth")?.as_usize()?
+            }))
+        },
+        other => Err(SceneError::parse(kind.line, &format!("unknown integrator '{}'", other)))
+    }
+}
+
+fn camera_from_json(value: &Json) -> Result<Camera, SceneError> {
+    value.check_fields("camera", &["hsize", "vsize", "field_of_view", "transform", "samples_per_pixel",
+        "sample_pattern", "filter", "integrator", "aperture", "focal_distance", "aperture_shape", "shutter_open", "shutter_close"])?;
+    let pattern = value.field("sample_pattern")?;
+    let filter = value.field("filter")?;
+    let shutter_open = value.get("shutter_open").map_or(Ok(0.0), |t| t.as_f32())?;
+    let shutter_close = match value.get("shutter_close") {
+        Some(close) => match close.as_f32()? {
+            t if t >= shutter_open => t,
+            _ => return Err(SceneError::parse(close.line, "the shutter can't close before it opens"))
+        },
+        None => shutter_open
+    };
+    Ok(Camera {
+        hsize: value.field("hsize")?.as_usize()?,
+        vsize: value.field("vsize")?.as_usize()?,
+        field_of_view: value.field("field_of_view")?.as_f32()?,
+        transform: invertible_matrix_from_json(value.field("transform")?, "the camera")?,
+        samples_per_pixel: value.field("samples_per_pixel")?.as_usize()?,
+        sample_pattern: match pattern.as_str()? {
+            "regular" => SamplePattern::Regular,
+            "jittered" => SamplePattern::Jittered,
+            "halton" => SamplePattern::Halton,
+            other => return Err(SceneError::parse(pattern.line, &format!("unknown sample pattern '{}'", other)))
+        },
+        filter: match filter.as_str()? {
+            "box" => PixelFilter::Box,
+            "tent" => PixelFilter::Tent,
+            "gaussian" => PixelFilter::Gaussian,
+            "mitchell" => PixelFilter::Mitchell,
+            other => return Err(SceneError::parse(filter.line, &format!("unknown pixel filter '{}'", other)))
+        },
+        integrator: match value.get("integrator") {
+            Some(integrator) => integrator_from_json(integrator)?,
+            None => Integrator::Phong
+        },
+        aperture: value.get("aperture").map_or(Ok(0.0), |a| a.as_f32())?,
+        focal_distance: value.get("focal_distance").map_or(Ok(1.0), |f| f.as_f32())?,
+        aperture_shape: value.get("aperture_shape").map(aperture_shape_from_json).transpose()?.unwrap_or_default(),
+        shutter_open,
+        shutter_close
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::matrix::RotationAxis;
+    use crate::ray_tracer::canvas::Canvas;
+
+    fn example_scene() -> Scene {
+        let mut world = World::empty();
+        world.lights.push(Light::new(Tuple::color(1.0, 0.9, 0.8), Tuple::point(-10.0, 10.0, -10.0)));
+        world.lights.push(Light::area(
+            Tuple::color(1.5, 1.5, 1.5),
+            AreaLight::new(Tuple::point(-1.0, 2.0, 4.0), Tuple::vector(2.0, 0.0, 0.0), 3, Tuple::vector(0.0, 2.0, 0.0), 7)
+        ));
+        world.lights.push(Light::directional(Tuple::color(0.3, 0.3, 0.3), Tuple::vector(1.0, -1.0, 0.3)));
+        world.lights.push(
+            Light::spot(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 5.0, 0.0), Tuple::vector(0.1, -1.0, 0.0), 0.2, 0.4)
+                .with_power(100.0)
+        );
+        let mut sphere = Sphere::new();
+        sphere.set_transform(&Matrix::rotation(0.7, RotationAxis::Y) * &Matrix::scaling(0.33, 1.0 / 3.0, 0.1));
+        sphere.material.color = Tuple::color(0.1, 0.2, 0.3);
+        sphere.material.shininess = 17.25;
+        sphere.material.bsdf = Some(Bsdf::Conductor { color: Tuple::color(0.9, 0.6, 0.2), roughness: 0.35, metalness: 1.0 });
+        world.objects.push(Box::new(sphere));
+        let mut triangle = Triangle::new(Tuple::point(-1.0, 3.0, 0.0), Tuple::point(1.0, 3.0, 0.0), Tuple::point(0.0, 3.0, 1.5));
+        triangle.set_transform(Matrix::translation(0.0, 0.5, 0.0));
+        triangle.material.emission = Tuple::color(4.0, 3.5, 3.0);
+        triangle.motion = Some(Motion::new(Matrix::translation(0.0, 0.5, 0.0), Matrix::translation(0.25, 0.5, 0.0)));
+        world.objects.push(Box::new(triangle));
+        world.background = Background::Gradient { bottom: Tuple::color(1.0, 1.0, 1.0), top: Tuple::color(0.5, 0.7, 1.0) };
+
+        let mut camera = Camera::new(320, 240, std::f32::consts::FRAC_PI_3);
+        camera.transform = Matrix::view_transform(Tuple::point(0.0, 1.5, -5.0), Tuple::point(0.0, 1.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0));
+        camera.samples_per_pixel = 4;
+        camera.sample_pattern = SamplePattern::Halton;
+        camera.filter = PixelFilter::Mitchell;
+        camera.integrator = Integrator::PathTracer(PathTracerSettings::new(12, 4));
+        camera.aperture = 0.05;
+        camera.focal_distance = 5.1;
+        camera.aperture_shape = ApertureShape::Polygon { blades: 7, rotation: 0.3 };
+        camera.shutter_close = 0.5;
+        Scene { world, camera }
+    }
+
+    fn bits(values: &[f32]) -> Vec<u32> {
+        values.iter().map(|v| v.to_bits()).collect()
+    }
+
+    #[test]
+    fn round_trip_is_lossless() {
+        let scene = example_scene();
+        let text = scene.to_json();
+        let result = Scene::from_json(&text).unwrap();
+        // Compare the bits, since the PartialEq impls allow for small differences.
+        let m1: Vec<f32> = scene.world.objects[0].get_transform().value.concat();
+        let m2: Vec<f32> = result.world.objects[0].get_transform().value.concat();
+        assert!(
+            result.to_json() == text && bits(&m1) == bits(&m2) &&
+            result.camera.field_of_view.to_bits() == scene.camera.field_of_view.to_bits() &&
+            result.world.lights == scene.world.lights &&
+            result.camera.sample_pattern == SamplePattern::Halton && result.camera.filter == PixelFilter::Mitchell &&
+            result.camera.integrator == scene.camera.integrator &&
+            result.camera.aperture_shape == scene.camera.aperture_shape &&
+            result.world.objects[1].get_object_type() == scene.world.objects[1].get_object_type() &&
+            result.world.objects[1].get_motion() == scene.world.objects[1].get_motion() &&
+            result.world.objects[0].get_motion().is_none() &&
+            result.world.objects[1].get_material() == scene.world.objects[1].get_material() &&
+            result.world.objects[0].get_material() == scene.world.objects[0].get_material(),
+            "Exporting and importing the scene changed it. Result: {}", result.to_json()
+        )
+    }
+
+    #[test]
+    fn world_round_trip() {
+        let scene = example_scene();
+        let text = world_to_json(&scene.world);
+        let world = world_from_json(&text).unwrap();
+        assert!(
+            world_to_json(&world) == text && !text.contains("camera") &&
+            world.objects.len() == 2 && world.lights.len() == 4,
+            "Exporting and importing the world changed it. Result: {}", text
+        )
+    }
+
+    #[test]
+    fn scene_requires_camera() {
+        let text = world_to_json(&example_scene().world);
+        let result = Scene::from_json(&text);
+        assert!(
+            result.as_ref().err() == Some(&SceneError::parse(1, "missing field 'camera'")),
+            "A document without a camera should not load as a scene."
+        )
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let text = world_to_json(&example_scene().world).replace("\"shininess\"", "\"shine\"");
+        let result = world_from_json(&text);
+        assert!(
+            matches!(result, Err(SceneError::Parse { ref message, .. }) if message == "unknown field 'shine' in material"),
+            "The unknown field was not reported. Result: {:?}", result.err()
+        )
+    }
+
+    #[test]
+    fn area_light_needs_steps() {
+        let text = world_to_json(&example_scene().world).replace("\"usteps\": 3", "\"usteps\": 0");
+        let line = text.lines().position(|l| l.contains("\"usteps\": 0")).unwrap() + 1;
+        let result = world_from_json(&text);
+        assert!(
+            result.err() == Some(SceneError::parse(line, "the number of steps must be at least 1")),
+            "An area light without steps should be rejected."
+        )
+    }
+
+    #[test]
+    fn bsdf_metalness_out_of_range_is_rejected() {
+        let text = world_to_json(&example_scene().world).replace("\"metalness\": 1", "\"metalness\": 2");
+        let line = text.lines().position(|l| l.contains("\"metalness\": 2")).unwrap() + 1;
+        let result = world_from_json(&text);
+        assert!(
+            result.err() == Some(SceneError::parse(line, "metalness must be between 0 and 1, found 2")),
+            "A metalness above 1 should be rejected."
+        )
+    }
+
+    #[test]
+    fn shutter_cant_close_before_it_opens() {
+        let text = example_scene().to_json().replace("\"shutter_close\": 0.5", "\"shutter_close\": -1");
+        let line = text.lines().position(|l| l.contains("\"shutter_close\": -1")).unwrap() + 1;
+        let result = Scene::from_json(&text);
+        assert!(
+            result.err() == Some(SceneError::parse(line, "the shutter can't close before it opens")),
+            "A shutter that closes before it opens should be rejected."
+        )
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let result = world_from_json("{\n  \"version\": 2,\n  \"lights\": [],\n  \"objects\": []\n}");
+        assert!(
+            result.err() == Some(SceneError::parse(2, "unsupported schema version 2, expected 1")),
+            "A newer schema version should be rejected."
+        )
+    }
+
+    #[test]
+    fn missing_version_is_rejected() {
+        let result = world_from_json("{ \"lights\": [], \"objects\": [] }");
+        assert!(
+            result.err() == Some(SceneError::parse(1, "missing field 'version'")),
+            "A document without a version should be rejected."
+        )
+    }
+
+    #[test]
+    fn bad_matrix_reports_line() {
+        let text = "{\n  \"version\": 1,\n  \"lights\": [],\n  \"objects\": [\n    {\n      \"type\": \"sphere\",\n      \"origin\": [0, 0, 0],\n      \"transform\": [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0]],\n      \"material\": { \"color\": [1, 1, 1], \"ambient\": 0.1, \"diffuse\": 0.9, \"specular\": 0.9, \"shininess\": 200 }\n    }\n  ]\n}";
+        let result = world_from_json(text);
+        assert!(
+            result.err() == Some(SceneError::parse(8, "expected 4 rows, found 3")),
+            "The bad matrix was not reported on the right line."
+        )
+    }
+
+    #[test]
+    fn singular_transform_names_the_object() {
+        let text = "{\n  \"version\": 1,\n  \"lights\": [],\n  \"objects\": [\n    {\n      \"type\": \"sphere\",\n      \"origin\": [0, 0, 0],\n      \"transform\": [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]],\n      \"material\": { \"color\": [1, 1, 1], \"ambient\": 0.1, \"diffuse\": 0.9, \"specular\": 0.9, \"shininess\": 200 }\n    },\n    {\n      \"type\": \"sphere\",\n      \"origin\": [0, 0, 0],\n      \"transform\": [[0, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]],\n      \"material\": { \"color\": [1, 1, 1], \"ambient\": 0.1, \"diffuse\": 0.9, \"specular\": 0.9, \"shininess\": 200 }\n    }\n  ]\n}";
+        let result = world_from_json(text);
+        assert!(
+            result.as_ref().err() == Some(&SceneError::parse(14, "the transform of object 1 (a sphere) can't be inverted")),
+            "The object with the singular transform was not reported. Result: {:?}", result.err()
+        )
+    }
+
+    #[test]
+    fn motion_into_a_mirror_image_is_rejected() {
+        let mirrored = [[-1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+        let mut world = World::empty();
+        let mut sphere = Sphere::new();
+        sphere.motion = Some(Motion::new(Matrix::identity(), Matrix::new(mirrored)));
+        world.objects.push(Box::new(sphere));
+        let text = world_to_json(&world);
+        let line = text.lines().position(|l| l.contains("\"motion\"")).unwrap() + 1;
+        let result = world_from_json(&text);
+        assert!(
+            result.as_ref().err() == Some(&SceneError::parse(line,
+                "the keyframe of object 0 (a sphere) at time 1 mirrors it when the one before doesn't, so it would be squashed flat in between")),
+            "A motion that is squashed flat between its keyframes should be rejected. Result: {:?}", result.err()
+        )
+    }
+
+    #[test]
+    fn material_without_emission_is_black() {
+        let text = "{\n  \"version\": 1,\n  \"lights\": [],\n  \"objects\": [\n    {\n      \"type\": \"sphere\",\n      \"origin\": [0, 0, 0],\n      \"transform\": [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]],\n      \"material\": { \"color\": [1, 1, 1], \"ambient\": 0.1, \"diffuse\": 0.9, \"specular\": 0.9, \"shininess\": 200 }\n    }\n  ]\n}";
+        let world = world_from_json(text).unwrap();
+        assert!(
+            world.objects[0].get_material() == &Material::default(),
+            "A material without an emission should not glow."
+        )
+    }
+
+    #[test]
+    fn light_power_round_trip() {
+        let scene = example_scene();
+        let result = Scene::from_json(&scene.to_json()).unwrap();
+        let text = "{ \"version\": 1, \"objects\": [], \"lights\": [ { \"type\": \"point\", \"intensity\": [1, 1, 1], \"position\": [0, 0, 0], \"attenuation\": [1, 0, 0] } ] }";
+        let older = world_from_json(text).unwrap();
+        assert!(
+            scene.world.lights[3].direct_scale != 1.0 &&
+            result.world.lights[3].direct_scale.to_bits() == scene.world.lights[3].direct_scale.to_bits() &&
+            older.lights[0].direct_scale == 1.0,
+            "A light's power should survive exporting and importing, and default to 1 when missing."
+        )
+    }
+
+    #[test]
+    fn camera_without_lens_is_a_pinhole() {
+        let text = example_scene().to_json();
+        let start = text.find(",\n    \"aperture\"").unwrap();
+        let end = start + text[start..].find("\n  }").unwrap();
+        let older = format!("{}{}", &text[..start], &text[end..]);
+        let result = Scene::from_json(&older).unwrap();
+        assert!(
+            result.camera.aperture == 0.0 && result.camera.aperture_shape == ApertureShape::Circle,
+            "A camera without a lens should be a pinhole camera. Document: {}", older
+        )
+    }
+
+    #[test]
+    fn camera_without_integrator_uses_phong() {
+        let text = example_scene().to_json();
+        let start = text.find(",\n    \"integrator\"").unwrap();
+        let end = start + text[start..].find('}').unwrap() + 1;
+        let older = format!("{}{}", &text[..start], &text[end..]);
+        let result = Scene::from_json(&older).unwrap();
+        assert!(
+            result.camera.integrator == Integrator::Phong,
+            "A camera without an integrator should use Phong. Document: {}", older
+        )
+    }
+
+    #[test]
+    fn environment_map_round_trip() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/sky.hdr");
+        let mut map = EnvironmentMap::load(path).unwrap();
+        map.rotation = 1.25;
+        let mut world = World::empty();
+        world.background = Background::Map(Box::new(map));
+        let text = world_to_json(&world);
+        let result = world_from_json(&text).unwrap();
+        let loaded = match &result.background {
+            Background::Map(map) => map.rotation == 1.25 && map.intensity == 1.0 && map.image.width == 128,
+            _ => false
+        };
+        assert!(
+            loaded && world_to_json(&result) == text,
+            "The environment map was not exported and imported correctly. Result: {}", text
+        )
+    }
+
+    #[test]
+    fn environment_map_is_found_next_to_the_document() {
+        let mut text = example_scene().to_json();
+        let start = text.find("\"background\"").unwrap();
+        let end = start + text[start..].find('}').unwrap() + 1;
+        text.replace_range(start..end, "\"background\": { \"type\": \"map\", \"file\": \"sky.hdr\", \"intensity\": 1, \"rotation\": 0 }");
+        let result = Scene::from_json_in(&text, Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes")));
+        assert!(
+            matches!(result.map(|s| s.world.background), Ok(Background::Map(_))),
+            "The environment map should be looked for in the document's directory."
+        )
+    }
+
+    #[test]
+    fn map_without_file_cannot_be_read() {
+        let mut world = World::empty();
+        world.background = Background::Map(Box::new(EnvironmentMap::new(Canvas::new(2, 1))));
+        let result = world_from_json(&world_to_json(&world));
+        assert!(
+            matches!(result, Err(SceneError::Parse { .. })),
+            "A map that wasn't loaded from a file should not be readable."
+        )
+    }
+}
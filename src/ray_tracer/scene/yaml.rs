@@ -0,0 +1,349 @@
+use crate::ray_tracer::scene::error::SceneError;
+
+// Parser for the small subset of YAML used by scene files: block sequences ("- item"), block
+// mappings ("key: value"), flow sequences ("[ 1, 2, 3 ]"), scalars and # comments. Every node
+// remembers the line it started on so that the loader can point at mistakes.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(String, Node)>)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub value: Value,
+    pub line: usize
+}
+
+struct Line {
+    indent: usize,
+    text: String,
+    number: usize
+}
+
+impl Node {
+    pub fn new(value: Value, line: usize) -> Self {
+        Node {
+            value,
+            line
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&str, SceneError> {
+        match &self.value {
+            Value::Scalar(s) => Ok(s),
+            _ => Err(SceneError::parse(self.line, "expected a single value"))
+        }
+    }
+
+    pub fn as_f32(&self) -> Result<f32, SceneError> {
+        let s = self.as_str()?;
+        s.parse::<f32>().map_err(|_| SceneError::parse(self.line, &format!("expected a number, found '{}'", s)))
+    }
+
+    pub fn as_usize(&self) -> Result<usize, SceneError> {
+        let s = self.as_str()?;
+        s.parse::<usize>()
+            .map_err(|_| SceneError::parse(self.line, &format!("expected a whole number, found '{}'", s)))
+    }
+
+    pub fn as_bool(&self) -> Result<bool, SceneError> {
+        match self.as_str()? {
+            "true" | "yes" => Ok(true),
+            "false" | "no" => Ok(false),
+            s => Err(SceneError::parse(self.line, &format!("expected true or false, found '{}'", s)))
+        }
+    }
+
+    pub fn as_sequence(&self) -> Result<&Vec<Node>, SceneError> {
+        match &self.value {
+            Value::Sequence(items) => Ok(items),
+            _ => Err(SceneError::parse(self.line, "expected a list"))
+        }
+    }
+
+    pub fn as_mapping(&self) -> Result<&Vec<(String, Node)>, SceneError> {
+        match &self.value {
+            Value::Mapping(entries) => Ok(entries),
+            _ => Err(SceneError::parse(self.line, "expected a set of 'key: value' pairs"))
+        }
+    }
+
+    // Returns the value of key when this node is a mapping that contains it.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match &self.value {
+            Value::Mapping(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    // A flow sequence of numbers, like [ 1, 2, 3 ].
+    pub fn as_f32_list(&self) -> Result<Vec<f32>, SceneError> {
+        self.as_sequence()?.iter().map(|n| n.as_f32()).collect()
+    }
+}
+
+pub fn parse(source: &str) -> Result<Node, SceneError> {
+    let mut lines = Vec::new();
+    for (i, raw) in source.lines().enumerate() {
+        let leading = &raw[..raw.len() - raw.trim_start().len()];
+        if leading.contains('\t') {
+            return Err(SceneError::parse(i + 1, "tabs can't be used for indentation"));
+        }
+        let text = strip_comment(raw).trim_end();
+        if text.trim().is_empty() || text.trim() == "---" {
+            continue;
+        }
+        let indent = text.len() - text.trim_start().len();
+        lines.push(Line { indent, text: text.trim_start().to_owned(), number: i + 1 });
+    }
+
+    if lines.is_empty() {
+        return Ok(Node::new(Value::Sequence(Vec::new()), 1));
+    }
+
+    let mut pos = 0;
+    let indent = lines[0].indent;
+    let node = parse_block(&mut lines, &mut pos, indent)?;
+    if pos < lines.len() {
+        return Err(SceneError::parse(lines[pos].number, "unexpected indentation"));
+    }
+    Ok(node)
+}
+
+// Parses the lines starting at pos that are indented by exactly `indent`.
+fn parse_block(lines: &mut Vec<Line>, pos: &mut usize, indent: usize) -> Result<Node, SceneError> {
+    let first = lines[*pos].number;
+    if is_sequence_item(&lines[*pos].text) {
+        let mut items = Vec::new();
+        while *pos < lines.len() && lines[*pos].indent == indent && is_sequence_item(&lines[*pos].text) {
+            let number = lines[*pos].number;
+            let rest = lines[*pos].text[1..].trim_start().to_owned();
+            if rest.is_empty() {
+                // The item is the indented block on the following lines.
+                *pos += 1;
+                if *pos < lines.len() && lines[*pos].indent > indent {
+                    let child_indent = lines[*pos].indent;
+                    items.push(parse_block(lines, pos, child_indent)?);
+                } else {
+                    items.push(Node::new(Value::Scalar(String::new()), number));
+                }
+            } else {
+                // Treat the text after "- " as if it started its own line, indented past the dash.
+                // That way a mapping that starts on the same line as its dash continues on the
+                // lines below it.
+                let child_indent = indent + (lines[*pos].text.len() - rest.len());
+                lines[*pos] = Line { indent: child_indent, text: rest, number };
+                items.push(parse_block(lines, pos, child_indent)?);
+            }
+        }
+        Ok(Node::new(Value::Sequence(items), first))
+    } else if split_key(&lines[*pos].text).is_some() {
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        while *pos < lines.len() && lines[*pos].indent == indent && !is_sequence_item(&lines[*pos].text) {
+            let number = lines[*pos].number;
+            let (key, rest) = match split_key(&lines[*pos].text) {
+                Some(kv) => kv,
+                None => return Err(SceneError::parse(number, "expected 'key: value'"))
+            };
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(SceneError::parse(number, &format!("'{}' is defined more than once", key)));
+            }
+            *pos += 1;
+            let value = if rest.is_empty() {
+                // A nested block is either indented further, or a list at the same indentation.
+                if *pos < lines.len() && (lines[*pos].indent > indent ||
+                    (lines[*pos].indent == indent && is_sequence_item(&lines[*pos].text))) {
+                    let child_indent = lines[*pos].indent;
+                    parse_block(lines, pos, child_indent)?
+                } else {
+                    Node::new(Value::Scalar(String::new()), number)
+                }
+            } else {
+                parse_inline(&rest, number)?
+            };
+            entries.push((key, value));
+        }
+        Ok(Node::new(Value::Mapping(entries), first))
+    } else {
+        let line = &lines[*pos];
+        let node = parse_inline(&line.text, line.number)?;
+        *pos += 1;
+        Ok(node)
+    }
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+// Splits "key: value" into its parts. Flow sequences and quoted strings are never keys.
+fn split_key(text: &str) -> Option<(String, String)> {
+    if text.starts_with('[') || text.starts_with('"') || text.starts_with('\'') {
+        return None;
+    }
+    let i = text.find(':')?;
+    let rest = &text[i + 1..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((text[..i].trim().to_owned(), rest.trim().to_owned()))
+}
+
+// Parses a value written on a single line: a flow sequence or a scalar.
+fn parse_inline(text: &str, line: usize) -> Result<Node, SceneError> {
+    let text = text.trim();
+    if text.starts_with('[') {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        let node = parse_flow_sequence(&chars, &mut i, line)?;
+        if chars[i..].iter().any(|c| !c.is_whitespace()) {
+            return Err(SceneError::parse(line, "unexpected text after ']'"));
+        }
+        Ok(node)
+    } else {
+        Ok(Node::new(Value::Scalar(unquote(text)), line))
+    }
+}
+
+fn parse_flow_sequence(chars: &[char], i: &mut usize, line: usize) -> Result<Node, SceneError> {
+    // Skip the opening bracket.
+    *i += 1;
+    let mut items = Vec::new();
+    let mut current = String::new();
+    loop {
+        if *i >= chars.len() {
+            return Err(SceneError::parse(line, "missing ']'"));
+        }
+        match chars[*i] {
+            '[' => {
+                items.push(parse_flow_sequence(chars, i, line)?);
+                continue;
+            },
+            ',' => {
+                if !current.trim().is_empty() {
+                    items.push(Node::new(Value::Scalar(unquote(current.trim())), line));
+                }
+                current.clear();
+            },
+            ']' => {
+                if !current.trim().is_empty() {
+                    items.push(Node::new(Value::Scalar(unquote(current.trim())), line));
+                }
+                *i += 1;
+                return Ok(Node::new(Value::Sequence(items), line));
+            },
+            c => current.push(c)
+        }
+        *i += 1;
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let quoted = text.len() >= 2 &&
+        ((text.starts_with('"') && text.ends_with('"')) || (text.starts_with('\'') && text.ends_with('\'')));
+    if quoted {
+        text[1..text.len() - 1].to_owned()
+    } else {
+        text.to_owned()
+    }
+}
+
+// Removes a trailing # comment, ignoring any # inside of quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) if i == 0 || line[..i].ends_with(' ') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sequence_of_mappings() {
+        let source = "- add: light\n  at: [ 1, 2.5, -3 ]\n\n# a comment\n- add: sphere # trailing comment\n";
+        let doc = parse(source).unwrap();
+        let items = doc.as_sequence().unwrap();
+        assert!(
+            items.len() == 2 &&
+            items[0].get("add").unwrap().as_str().unwrap() == "light" &&
+            items[0].get("at").unwrap().as_f32_list().unwrap() == vec![1.0, 2.5, -3.0] &&
+            items[1].get("add").unwrap().as_str().unwrap() == "sphere" &&
+            items[1].line == 5,
+            "The document was not parsed correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn parse_nested_blocks() {
+        let source = "- define: m\n  value:\n    color: [ 1, 0, 0 ]\n    diffuse: 0.5\n- add: sphere\n  transform:\n    - [ scale, 2, 2, 2 ]\n    - standard\n";
+        let doc = parse(source).unwrap();
+        let items = doc.as_sequence().unwrap();
+        let value = items[0].get("value").unwrap();
+        let transform = items[1].get("transform").unwrap().as_sequence().unwrap();
+        assert!(
+            value.get("diffuse").unwrap().as_f32().unwrap() == 0.5 &&
+            value.get("color").unwrap().line == 3 &&
+            transform.len() == 2 &&
+            transform[0].as_sequence().unwrap()[0].as_str().unwrap() == "scale" &&
+            transform[1].as_str().unwrap() == "standard",
+            "The nested blocks were not parsed correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn parse_nested_flow_sequences() {
+        let doc = parse("rows: [ [ 1, 2 ], [ 3, 4 ] ]").unwrap();
+        let rows = doc.get("rows").unwrap().as_sequence().unwrap();
+        assert!(
+            rows.len() == 2 && rows[1].as_f32_list().unwrap() == vec![3.0, 4.0],
+            "The nested flow sequence was not parsed correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn parse_quoted_strings() {
+        let doc = parse("name: \"a # not a comment\"").unwrap();
+        assert!(
+            doc.get("name").unwrap().as_str().unwrap() == "a # not a comment",
+            "The quoted string was not parsed correctly. Result: {:?}", doc
+        )
+    }
+
+    #[test]
+    fn missing_bracket_reports_line() {
+        let result = parse("- add: light\n  at: [ 1, 2, 3\n");
+        assert!(
+            result == Err(SceneError::parse(2, "missing ']'")),
+            "The error did not point at the right line. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn duplicate_key_reports_line() {
+        let result = parse("- add: light\n  at: [ 1, 2, 3 ]\n  at: [ 1, 2, 3 ]\n");
+        assert!(
+            result == Err(SceneError::parse(3, "'at' is defined more than once")),
+            "The error did not point at the right line. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn bad_indentation_reports_line() {
+        let result = parse("- add: light\n  at: [ 1, 2, 3 ]\n      intensity: [ 1, 1, 1 ]\n");
+        assert!(
+            result.is_err(),
+            "Unexpected indentation should be an error."
+        )
+    }
+}
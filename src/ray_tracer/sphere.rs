@@ -1,8 +1,12 @@
 use crate::ray_tracer::tuple::Tuple;
 use crate::ray_tracer::traits::object::Object;
 use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::motion::Motion;
 use crate::ray_tracer::material::Material;
 use crate::ray_tracer::enums::object_types::ObjectTypes;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::intersection::Intersection;
+use std::f32::consts::PI;
 
 #[cfg(test)]
 use crate::ray_tracer::matrix::RotationAxis;
@@ -10,8 +14,14 @@ use crate::ray_tracer::matrix::RotationAxis;
 #[derive(PartialEq)]
 pub struct Sphere {
     pub origin: Tuple,
-    pub transform: Matrix,
+    // Set with set_transform, which keeps the inverses in step, so that rendering never has to
+    // invert a matrix for a sphere that holds still.
+    transform: Matrix,
+    inverse: Matrix,
+    inverse_transpose: Matrix,
     pub material: Material,
+    // Makes the sphere move while the shutter is open, in place of transform.
+    pub motion: Option<Motion>
 }
 
 impl Sphere {
@@ -19,9 +29,22 @@ impl Sphere {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn with_transform(transform: Matrix) -> Self {
+        let mut sphere = Sphere::new();
+        sphere.set_transform(transform);
+        sphere
+    }
+
+    // Panics if the transform can't be inverted, which Matrix::try_inverse can check first.
+    pub fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+        self.inverse_transpose = self.inverse.transpose();
+    }
 }
 
-impl <'a> Object for &'a Sphere {
+impl Object for &Sphere {
 
     // Access methods for when Sphere gets boxed as part of an object in a world.objects
     fn get_origin(&self) -> &Tuple {
@@ -32,6 +55,14 @@ impl <'a> Object for &'a Sphere {
         &self.transform
     }
 
+    fn get_inverse(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn get_inverse_transpose(&self) -> &Matrix {
+        &self.inverse_transpose
+    }
+
     fn get_material(&self) -> &Material {
         &self.material
     }
@@ -40,16 +71,19 @@ impl <'a> Object for &'a Sphere {
         ObjectTypes::Sphere
     }
 
-    // Find the normal vector at a given point on the object. This is the perpendicular vector from
-    // that point on the surface.
-    fn normal_at(&self, world_point: Tuple) -> Tuple {
-        let transform_inverse = self.transform.inverse();
-        let object_point = &transform_inverse * &world_point;
-        let object_normal = &object_point - &Tuple::point(0.0, 0.0, 0.0);
-        let mut world_normal = &transform_inverse.transpose() * &object_normal;
-        world_normal.w = Some(0);
-        
-        world_normal.norm()
+    fn get_motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn normal_at_time(&self, world_point: Tuple, time: f32) -> Tuple {
+        (*self).normal_at_time(world_point, time)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        ray.intersect(self)
+    }
+    fn sample_surface(&self, u1: f32, u2: f32, time: f32) -> (Tuple, Tuple, f32) {
+        (*self).sample_surface(u1, u2, time)
     }
 }
 
@@ -64,6 +98,14 @@ impl Object for Sphere {
         &self.transform
     }
 
+    fn get_inverse(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn get_inverse_transpose(&self) -> &Matrix {
+        &self.inverse_transpose
+    }
+
     fn get_material(&self) -> &Material {
         &self.material
     }
@@ -72,17 +114,39 @@ impl Object for Sphere {
         ObjectTypes::Sphere
     }
 
+    fn get_motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
     // Find the normal vector at a given point on the object. This is the perpendicular vector from
     // that point on the surface.
-    fn normal_at(&self, world_point: Tuple) -> Tuple {
-        let transform_inverse = self.transform.inverse();
-        let object_point = &transform_inverse * &world_point;
+    fn normal_at_time(&self, world_point: Tuple, time: f32) -> Tuple {
+        let object_point = &self.inverse_at(time) * &world_point;
         let object_normal = &object_point - &Tuple::point(0.0, 0.0, 0.0);
-        let mut world_normal = &transform_inverse.transpose() * &object_normal;
+        let mut world_normal = &self.inverse_transpose_at(time) * &object_normal;
         world_normal.w = Some(0);
         
         world_normal.norm()
     }
+
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        ray.intersect(self)
+    }
+    // Uniform over the untransformed sphere. The transform stretches the surface around the point
+    // by |det(M)| * |M^-T n|, which divides the density of 1 / 4pi.
+    fn sample_surface(&self, u1: f32, u2: f32, time: f32) -> (Tuple, Tuple, f32) {
+        let transform = self.transform_at(time);
+        let z = 1.0 - 2.0 * u1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let object_normal = Tuple::vector(r * phi.cos(), r * phi.sin(), z);
+        let world_point = &transform * &(&self.origin + &object_normal);
+
+        let mut world_normal = &self.inverse_transpose_at(time) * &object_normal;
+        world_normal.w = Some(0);
+        let stretch = transform.determinant().abs() * world_normal.mag();
+        (world_point, world_normal.norm(), 1.0 / (4.0 * PI * stretch))
+    }
 }
 
 // There are instances where I want to instantiate a sphere with some but not all default values.
@@ -91,7 +155,10 @@ impl Default for Sphere {
         Sphere {
             origin: Tuple::point(0.0, 0.0, 0.0),
             transform: Matrix::identity(),
-            material: Material::default()
+            inverse: Matrix::identity(),
+            inverse_transpose: Matrix::identity(),
+            material: Material::default(),
+            motion: None
         }
     }
 }
@@ -99,6 +166,7 @@ impl Default for Sphere {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ray_tracer::common::f_equal;
 
     #[test]
     fn default_transformation() {
@@ -112,13 +180,36 @@ mod tests {
     #[test]
     fn change_transformation() {
         let mut s = Sphere::new();
-        s.transform = Matrix::translation(2.0, 3.0, 4.0);
+        s.set_transform(Matrix::translation(2.0, 3.0, 4.0));
         assert!(
             s.transform == Matrix::translation(2.0, 3.0, 4.0),
             "The default transform for the sphere was not set correctly on construction."
         )
     }
 
+    #[test]
+    fn setting_transform_updates_inverses() {
+        let transform = &Matrix::translation(2.0, 3.0, 4.0) * &Matrix::scaling(1.0, 2.0, 4.0);
+        let mut s = Sphere::new();
+        s.set_transform(transform);
+        assert!(
+            *s.get_inverse() == transform.inverse() && *s.get_inverse_transpose() == transform.inverse().transpose() &&
+            s.inverse_at(0.0) == transform.inverse(),
+            "Setting the transform should work out its inverse and inverse transpose."
+        )
+    }
+
+    #[test]
+    fn moving_sphere_inverts_its_transform_at_the_time() {
+        let mut s = Sphere::new();
+        s.motion = Some(Motion::new(Matrix::translation(0.0, 0.0, 0.0), Matrix::translation(2.0, 0.0, 0.0)));
+        assert!(
+            s.inverse_at(0.5) == Matrix::translation(-1.0, 0.0, 0.0) &&
+            s.inverse_transpose_at(0.5) == Matrix::translation(-1.0, 0.0, 0.0).transpose(),
+            "A moving sphere should use the inverse of its transform at the time."
+        )
+    }
+
     #[test]
     fn normal_at_point_on_x() {
         let s = Sphere::new();
@@ -170,8 +261,9 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_of_translated_sphere() {
-        let s = Sphere { transform: Matrix::translation(0.0, 1.0, 0.0), ..Default::default() };
+        let s = Sphere::with_transform(Matrix::translation(0.0, 1.0, 0.0));
         let result = s.normal_at(Tuple::point(0.0, 1.70711, -0.70711));
         assert!(
             result == Tuple::vector(0.0, 0.70711, -0.70711),
@@ -182,9 +274,8 @@ mod tests {
 
     #[test]
     fn normal_of_transformed_sphere() {
-        let s = Sphere { transform: &Matrix::scaling(1.0, 0.5, 1.0) * 
-                                    &Matrix::rotation(std::f32::consts::PI/5.0, RotationAxis::Z),
-                                    ..Default::default() };
+        let s = Sphere::with_transform(&Matrix::scaling(1.0, 0.5, 1.0) *
+                                       &Matrix::rotation(std::f32::consts::PI/5.0, RotationAxis::Z));
         let result = s.normal_at(Tuple::point(0.0, 2.0_f32.sqrt() / 2.0, -2.0_f32.sqrt() / 2.0));
         assert!(
             result == Tuple::vector(0.0, 0.97014, -0.24254),
@@ -211,4 +302,33 @@ mod tests {
             "The default object type of sphere was not set correctly."
         )
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn surface_samples_of_scaled_sphere() {
+        let s = Sphere::with_transform(&Matrix::translation(1.0, 0.0, 0.0) * &Matrix::scaling(2.0, 2.0, 2.0));
+        let (point, normal, pdf) = s.sample_surface(0.0, 0.0, 0.0);
+        let (_, _, pdf2) = s.sample_surface(0.3, 0.7, 0.0);
+        let expected = 1.0 / (16.0 * std::f32::consts::PI);
+        assert!(
+            point == Tuple::point(1.0, 0.0, 2.0) && normal == Tuple::vector(0.0, 0.0, 1.0) &&
+            f_equal(pdf, expected) && f_equal(pdf2, expected),
+            "The surface sample of the scaled sphere was not correct. Result: {:?} {:?} {}", point, normal, pdf
+        )
+    }
+
+    #[test]
+    fn moving_sphere_is_hit_where_it_is_at_ray_time() {
+        let s = Sphere {
+            motion: Some(Motion::new(Matrix::translation(0.0, 0.0, 0.0), Matrix::translation(0.0, 0.0, 4.0))),
+            ..Default::default()
+        };
+        let early = Ray::with_time(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0.0);
+        let late = Ray::with_time(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0.5);
+        let (t_early, t_late) = (s.intersect(&early).unwrap()[0].t, s.intersect(&late).unwrap()[0].t);
+        assert!(
+            t_early == 4.0 && t_late == 6.0 &&
+            s.normal_at_time(Tuple::point(0.0, 0.0, 1.0), 0.5) == Tuple::vector(0.0, 0.0, -1.0),
+            "The moving sphere should be intersected where it is at the ray's time. Result: {} and {}", t_early, t_late
+        )
+    }
+}
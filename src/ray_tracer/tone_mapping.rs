@@ -0,0 +1,188 @@
+use crate::ray_tracer::tuple::Tuple;
+
+// Operators for compressing the unbounded linear radiance stored in a Canvas into the [0, 1]
+// range that an image file can hold.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    // Values above 1.0 are cut off. This matches the original export behavior.
+    Clamp,
+    // c / (1 + c), applied per channel. Never fully saturates.
+    Reinhard,
+    // Krzysztof Narkowicz's fit of the ACES filmic reference curve.
+    AcesFilmic
+}
+
+// Transfer functions used to encode the tone mapped linear values for display.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransferFunction {
+    // Write linear values directly. This matches the original export behavior.
+    Linear,
+    // The piecewise sRGB curve (IEC 61966-2-1).
+    Srgb,
+    // A pure power curve, encoded as c^(1 / gamma).
+    Gamma(f32)
+}
+
+// Everything that happens to a linear color between the Canvas and the image file. The Canvas
+// itself is never modified, so the same render can be exported with different settings.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExportSettings {
+    pub operator: ToneMapOperator,
+    // Exposure in stops (EV). Every +1.0 doubles the brightness before tone mapping.
+    pub exposure: f32,
+    pub transfer: TransferFunction
+}
+
+impl ExportSettings {
+    pub fn new(operator: ToneMapOperator, exposure: f32, transfer: TransferFunction) -> Self {
+        ExportSettings {
+            operator,
+            exposure,
+            transfer
+        }
+    }
+
+    // Maps a linear color to a display encoded color with every channel in [0, 1].
+    pub fn apply(&self, color: Tuple) -> Tuple {
+        let scale = 2.0_f32.powf(self.exposure);
+        let map = |c: f32| {
+            let mapped = self.operator.map(c * scale);
+            self.transfer.encode(mapped)
+        };
+
+        Tuple::color(map(color.x), map(color.y), map(color.z))
+    }
+}
+
+// The default settings reproduce the original naive export: clamp in linear space.
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            transfer: TransferFunction::Linear
+        }
+    }
+}
+
+impl ToneMapOperator {
+    // Maps a single linear channel into [0, 1].
+    pub fn map(&self, c: f32) -> f32 {
+        // Negative light doesn't exist, and NaN from a bad sample shouldn't poison the export.
+        let c = if c.is_nan() { 0.0 } else { c.max(0.0) };
+        match self {
+            ToneMapOperator::Clamp => c.min(1.0),
+            ToneMapOperator::Reinhard => c / (1.0 + c),
+            ToneMapOperator::AcesFilmic => {
+                let a = 2.51;
+                let b = 0.03;
+                let d = 2.43;
+                let e = 0.59;
+                let f = 0.14;
+                ((c * (a * c + b)) / (c * (d * c + e) + f)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl TransferFunction {
+    // Encodes a linear channel in [0, 1] for display.
+    pub fn encode(&self, c: f32) -> f32 {
+        match self {
+            TransferFunction::Linear => c,
+            TransferFunction::Srgb => linear_to_srgb(c),
+            TransferFunction::Gamma(gamma) => c.powf(1.0 / gamma)
+        }
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    #[test]
+    fn default_settings_match_naive_export() {
+        let s = ExportSettings::default();
+        let result = s.apply(Tuple::color(1.5, 0.5, -0.5));
+        assert!(
+            result == Tuple::color(1.0, 0.5, 0.0),
+            "The default export settings should only clamp. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn reinhard_compresses_highlights() {
+        let op = ToneMapOperator::Reinhard;
+        assert!(
+            f_equal(op.map(1.0), 0.5) && f_equal(op.map(3.0), 0.75) && op.map(1000.0) < 1.0,
+            "The Reinhard operator did not map the values correctly."
+        )
+    }
+
+    #[test]
+    fn aces_filmic_stays_in_range() {
+        let op = ToneMapOperator::AcesFilmic;
+        assert!(
+            f_equal(op.map(0.0), 0.0) && op.map(0.5) > 0.0 && op.map(0.5) < 1.0 && f_equal(op.map(100.0), 1.0),
+            "The ACES filmic operator produced values outside of [0, 1]."
+        )
+    }
+
+    #[test]
+    fn exposure_doubles_per_stop() {
+        let s = ExportSettings::new(ToneMapOperator::Clamp, 1.0, TransferFunction::Linear);
+        let result = s.apply(Tuple::color(0.25, 0.1, 0.0));
+        assert!(
+            result == Tuple::color(0.5, 0.2, 0.0),
+            "One stop of exposure should double the color. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn srgb_encoding() {
+        assert!(
+            f_equal(linear_to_srgb(0.0), 0.0) && f_equal(linear_to_srgb(1.0), 1.0) &&
+            (linear_to_srgb(0.214_041) - 0.5).abs() < 0.0001,
+            "The sRGB transfer function did not encode the values correctly."
+        )
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for i in 0..=10 {
+            let c = i as f32 / 10.0;
+            let result = srgb_to_linear(linear_to_srgb(c));
+            assert!(
+                (result - c).abs() < 0.0001,
+                "Decoding an sRGB encoded value should return the original. Result: {}, Expected: {}", result, c
+            )
+        }
+    }
+
+    #[test]
+    fn nan_is_exported_as_black() {
+        let s = ExportSettings::new(ToneMapOperator::AcesFilmic, 0.0, TransferFunction::Srgb);
+        let result = s.apply(Tuple::color(f32::NAN, 0.0, 0.0));
+        assert!(
+            result == Tuple::color(0.0, 0.0, 0.0),
+            "NaN channels should be exported as black. Result: {:?}", result
+        )
+    }
+}
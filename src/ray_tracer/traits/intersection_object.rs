@@ -2,5 +2,5 @@ use crate::ray_tracer::traits::object::Object;
 
 pub trait IntersectionObject {
     fn get_t(&self) -> f32;
-    fn get_object(&self) -> &Box<dyn Object + '_>;
+    fn get_object(&self) -> &dyn Object;
 }
\ No newline at end of file
@@ -1,14 +1,60 @@
 use crate::ray_tracer::tuple::Tuple;
 use crate::ray_tracer::material::Material;
 use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::motion::Motion;
 use crate::ray_tracer::enums::object_types::ObjectTypes;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::intersection::Intersection;
 
-pub trait Object {
+// Send + Sync so that a World can be shared between render threads.
+pub trait Object: Send + Sync {
     // Getters for when an object gets boxed as part of world.objects Train Object
     // These properties should be present for every type of object.
     fn get_origin(&self) -> &Tuple;
     fn get_transform(&self) -> &Matrix;
+    // The transform's inverse and its transpose, kept up to date with the transform so that they
+    // don't have to be worked out for every ray.
+    fn get_inverse(&self) -> &Matrix;
+    fn get_inverse_transpose(&self) -> &Matrix;
     fn get_material(&self) -> &Material;
     fn get_object_type(&self) -> ObjectTypes;
-    fn normal_at(&self, point: Tuple) -> Tuple;
+    // None for objects that hold still.
+    fn get_motion(&self) -> Option<&Motion>;
+    // The normal of the object at time, for moving objects.
+    fn normal_at_time(&self, point: Tuple, time: f32) -> Tuple;
+    // Intersects a ray in world space with the object, as it is at the ray's time. Lets a World
+    // intersect its objects without knowing their concrete types.
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>>;
+    // Maps two uniform random numbers to a point on the surface in world space, as it is at time.
+    // Returns the point, the normal there and the probability density of picking it, per unit of
+    // world space area. Used to aim shadow rays at objects that emit light.
+    fn sample_surface(&self, u1: f32, u2: f32, time: f32) -> (Tuple, Tuple, f32);
+
+    // Where the object is at time: its motion's transform if it moves, its transform otherwise.
+    fn transform_at(&self, time: f32) -> Matrix {
+        match self.get_motion() {
+            Some(motion) => motion.transform_at(time),
+            None => *self.get_transform()
+        }
+    }
+
+    // Takes world space to object space at time. Only moving objects invert a matrix here.
+    fn inverse_at(&self, time: f32) -> Matrix {
+        match self.get_motion() {
+            Some(motion) => motion.inverse_at(time),
+            None => *self.get_inverse()
+        }
+    }
+
+    // Takes normals from object space to world space at time.
+    fn inverse_transpose_at(&self, time: f32) -> Matrix {
+        match self.get_motion() {
+            Some(motion) => motion.inverse_at(time).transpose(),
+            None => *self.get_inverse_transpose()
+        }
+    }
+
+    fn normal_at(&self, point: Tuple) -> Tuple {
+        self.normal_at_time(point, 0.0)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+use crate::ray_tracer::tuple::Tuple;
+use crate::ray_tracer::traits::object::Object;
+use crate::ray_tracer::matrix::Matrix;
+use crate::ray_tracer::motion::Motion;
+use crate::ray_tracer::material::Material;
+use crate::ray_tracer::enums::object_types::ObjectTypes;
+use crate::ray_tracer::ray::Ray;
+use crate::ray_tracer::intersection::Intersection;
+use crate::ray_tracer::common::EPSILON;
+
+// A flat triangle between three points in object space. The edges and the normal are worked out
+// once when the triangle is created, so the points shouldn't be changed afterwards. A mesh is
+// just a set of triangles, see the scene loader's 'add: mesh'.
+#[derive(PartialEq)]
+pub struct Triangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+    // Set with set_transform, which keeps the inverses in step.
+    transform: Matrix,
+    inverse: Matrix,
+    inverse_transpose: Matrix,
+    pub material: Material,
+    // Makes the triangle move while the shutter is open, in place of transform.
+    pub motion: Option<Motion>
+}
+
+impl Triangle {
+    // The normal follows the right hand rule: it points towards a viewer that sees p1, p2, p3
+    // going clockwise.
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple) -> Self {
+        let e1 = &p2 - &p1;
+        let e2 = &p3 - &p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).norm(),
+            transform: Matrix::identity(),
+            inverse: Matrix::identity(),
+            inverse_transpose: Matrix::identity(),
+            material: Material::default(),
+            motion: None
+        }
+    }
+
+    // The corners are in a line, or on top of each other, so the triangle has no area and no
+    // normal to light or sample it with.
+    pub fn is_degenerate(&self) -> bool {
+        !(self.normal.x.is_finite() && self.normal.y.is_finite() && self.normal.z.is_finite())
+    }
+
+    // Panics if the transform can't be inverted, which Matrix::try_inverse can check first.
+    pub fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+        self.inverse_transpose = self.inverse.transpose();
+    }
+}
+
+impl Object for &Triangle {
+    fn get_origin(&self) -> &Tuple {
+        (*self).get_origin()
+    }
+
+    fn get_transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn get_inverse(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn get_inverse_transpose(&self) -> &Matrix {
+        &self.inverse_transpose
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_object_type(&self) -> ObjectTypes {
+        (*self).get_object_type()
+    }
+
+    fn get_motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn normal_at_time(&self, world_point: Tuple, time: f32) -> Tuple {
+        (*self).normal_at_time(world_point, time)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        (*self).intersect(ray)
+    }
+
+    fn sample_surface(&self, u1: f32, u2: f32, time: f32) -> (Tuple, Tuple, f32) {
+        (*self).sample_surface(u1, u2, time)
+    }
+}
+
+impl Object for Triangle {
+    // A triangle has no center, so its first point stands in for its origin.
+    fn get_origin(&self) -> &Tuple {
+        &self.p1
+    }
+
+    fn get_transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn get_inverse(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn get_inverse_transpose(&self) -> &Matrix {
+        &self.inverse_transpose
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_object_type(&self) -> ObjectTypes {
+        ObjectTypes::Triangle { p1: self.p1, p2: self.p2, p3: self.p3 }
+    }
+
+    fn get_motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    // The same everywhere on the triangle.
+    fn normal_at_time(&self, _world_point: Tuple, time: f32) -> Tuple {
+        let mut world_normal = &self.inverse_transpose_at(time) * &self.normal;
+        world_normal.w = Some(0);
+
+        world_normal.norm()
+    }
+
+    // Möller-Trumbore, in object space.
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let ray = ray.transform(self.inverse_at(ray.time));
+        let dir_cross_e2 = ray.direction.cross(self.e2);
+        let det = &self.e1 * &dir_cross_e2;
+        // The ray runs parallel to the triangle.
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = &ray.origin - &self.p1;
+        let u = f * (&p1_to_origin * &dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let origin_cross_e1 = p1_to_origin.cross(self.e1);
+        let v = f * (&ray.direction * &origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = f * (&self.e2 * &origin_cross_e1);
+        Some(vec![Intersection::new(t, Box::new(self))])
+    }
+
+    // Uniform over the triangle. The density is one over the transformed triangle's area.
+    fn sample_surface(&self, u1: f32, u2: f32, time: f32) -> (Tuple, Tuple, f32) {
+        let transform = self.transform_at(time);
+        let s = u1.sqrt();
+        let (b1, b2) = (s * (1.0 - u2), s * u2);
+        let object_point = &(&self.p1 + &(&self.e1 * b1)) + &(&self.e2 * b2);
+        let world_point = &transform * &object_point;
+
+        let e1 = &transform * &self.e1;
+        let e2 = &transform * &self.e2;
+        let area = 0.5 * e1.cross(e2).mag();
+        (world_point, self.normal_at_time(world_point, time), 1.0 / area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracer::common::f_equal;
+
+    fn triangle() -> Triangle {
+        Triangle::new(Tuple::point(0.0, 1.0, 0.0), Tuple::point(-1.0, 0.0, 0.0), Tuple::point(1.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn create_triangle() {
+        let t = triangle();
+        assert!(
+            t.e1 == Tuple::vector(-1.0, -1.0, 0.0) && t.e2 == Tuple::vector(1.0, -1.0, 0.0) &&
+            t.normal == Tuple::vector(0.0, 0.0, -1.0),
+            "The edges and normal of the triangle were not computed correctly."
+        )
+    }
+
+    #[test]
+    fn triangle_without_area_is_degenerate() {
+        let flat = Triangle::new(Tuple::point(0.0, 0.0, 0.0), Tuple::point(1.0, 1.0, 0.0), Tuple::point(2.0, 2.0, 0.0));
+        assert!(
+            flat.is_degenerate() && !triangle().is_degenerate(),
+            "A triangle with its corners in a line should be degenerate."
+        )
+    }
+
+    #[test]
+    fn ray_parallel_to_triangle() {
+        let r = Ray::new(Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(
+            triangle().intersect(&r).is_none(),
+            "A ray parallel to the triangle should miss it."
+        )
+    }
+
+    #[test]
+    fn ray_misses_edges() {
+        let t = triangle();
+        let misses = [
+            Ray::new(Tuple::point(1.0, 1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(-1.0, 1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0))
+        ];
+        assert!(
+            misses.iter().all(|r| t.intersect(r).is_none()),
+            "Rays passing beyond the edges should miss the triangle."
+        )
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = triangle();
+        let r = Ray::new(Tuple::point(0.0, 0.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = t.intersect(&r).unwrap();
+        assert!(
+            xs.len() == 1 && xs[0].t == 2.0,
+            "The ray should hit the triangle once at t = 2. Result: {:?}", xs.iter().map(|i| i.t).collect::<Vec<f32>>()
+        )
+    }
+
+    #[test]
+    fn transformed_triangle() {
+        let mut t = triangle();
+        t.set_transform(&Matrix::translation(0.0, 0.0, 3.0) * &Matrix::scaling(2.0, 2.0, 2.0));
+        let r = Ray::new(Tuple::point(0.0, 1.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = t.intersect(&r).unwrap();
+        assert!(
+            xs[0].t == 5.0 && t.normal_at(Tuple::point(0.0, 1.0, 3.0)) == Tuple::vector(0.0, 0.0, -1.0),
+            "The transformed triangle was not intersected correctly."
+        )
+    }
+
+    #[test]
+    fn surface_samples_lie_on_triangle() {
+        let mut t = triangle();
+        t.set_transform(Matrix::scaling(2.0, 3.0, 1.0));
+        let samples = [(0.5, 0.5), (0.99, 0.01), (0.99, 0.98), (0.25, 0.5), (0.01, 0.3)];
+        let on_triangle = samples.iter().all(|(u1, u2)| {
+            let (point, normal, pdf) = t.sample_surface(*u1, *u2, 0.0);
+            let r = Ray::new(&point + &Tuple::vector(0.0, 0.0, -1.0), Tuple::vector(0.0, 0.0, 1.0));
+            t.intersect(&r).is_some() && normal == Tuple::vector(0.0, 0.0, -1.0) && f_equal(pdf, 1.0 / 6.0)
+        });
+        assert!(
+            on_triangle,
+            "Samples should lie on the triangle with a density of one over its area."
+        )
+    }
+}
@@ -259,12 +259,7 @@ impl PartialEq for Tuple {
     fn eq(&self, other: &Tuple) -> bool {
         match (self.w, other.w) {
             (None, None) | (Some(0), Some(0)) | (Some(1), Some(1)) => {
-                if f_equal(self.x, other.x) & f_equal(self.y, other.y) &
-                    f_equal(self.z, other.z) {
-                        true
-                    } else {
-                        false
-                    }  
+                f_equal(self.x, other.x) & f_equal(self.y, other.y) & f_equal(self.z, other.z)
             }
         _ => false         
         }   
@@ -326,8 +321,8 @@ mod tests {
         let a = Tuple::vector(1.000001, 2.0, 3.0);
         let b = Tuple::vector(1.0, 2.0, 3.0);
         let x: bool = a == b;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The vectors a and b should be equal = true, value was {}", x
         )
     }
@@ -337,8 +332,8 @@ mod tests {
         let c1 = Tuple::color(0.5, 0.2, 0.3);
         let c2 = Tuple::color(0.5, 0.2, 0.3);
         let x: bool = c1 == c2;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The colors a and b should be equal = true, value was {}", x
         )
     }
@@ -349,8 +344,8 @@ mod tests {
         let v = Tuple::vector(-2.0, 3.0, 1.0);
         let y: Tuple = &p + &v;
         let x: bool = Tuple::point(1.0, 1.0, 6.0) == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The sum of the point and vector should equal (1, 1, 6, 1), value was {:#?}", y
         )
     }
@@ -361,8 +356,8 @@ mod tests {
         let c2 = Tuple::color(0.5, 0.2, 0.3);
         let c3 = &c1 + &c2;
         let x = Tuple::color(1.0, 0.4, 0.6) == c3;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The sum of the colors equal (1.0, 0.4, 0.6), value was {:#?}", x
         )
     }
@@ -374,8 +369,8 @@ mod tests {
         let y: Tuple = &p1 - &p2;
         let expected = Tuple::vector(-2.0, -4.0, -6.0);
         let x: bool = expected == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The difference between the two points should equal {:#?}, value was {:#?}", expected, y
         )
     }
@@ -386,8 +381,8 @@ mod tests {
         let c2 = Tuple::color(0.3, 0.1, 0.1);
         let c3 = &c1 - &c2;
         let x = Tuple::color(0.2, 0.1, 0.2) == c3;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The difference of the colors equal (1.0, 0.4, 0.6), value was {:#?}", x
         )
     }
@@ -399,8 +394,8 @@ mod tests {
         let y: Tuple = &p - &v;
         let expected: Tuple = Tuple::point(-2.0, -4.0, -6.0);
         let x: bool = expected == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The difference between the two points should equal {:#?}, value was {:#?}", expected, y
         )
     }
@@ -412,8 +407,8 @@ mod tests {
         let y: Tuple = &p1 - &p2;
         let expected: Tuple = Tuple::vector(-2.0, -4.0, -6.0);
         let x: bool = expected == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The difference between the two points should equal {:#?}, value was {:#?}", expected, y
         )
     }
@@ -424,8 +419,8 @@ mod tests {
         let expected = Tuple::point(-3.0, 2.0, -1.0);
         let y: Tuple = -&p;
         let x: bool = expected == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The negation of the tuple should equal {:#?}, value was {:#?}", expected, y
         )
     }
@@ -436,8 +431,8 @@ mod tests {
         let expected = Tuple::point(-3.0, 2.0, -1.0);
         let y: Tuple = -&p;
         let x: bool = expected == y;
-        assert_eq!(
-            true, x,
+        assert!(
+            x,
             "The negation of the tuple should equal {:#?}, value was {:#?}", expected, y
         )
     }
@@ -448,8 +443,8 @@ mod tests {
         let expected = Tuple::point(10.5, -7.0, 3.5);
         let output: Tuple = &p * 3.5;
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The multiplication of the tuple and scalar should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -460,8 +455,8 @@ mod tests {
         let expected = Tuple::point(1.5, -1.0, 0.5);
         let output: Tuple = &p * 0.5;
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The multiplication of the tuple and fraction should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -472,8 +467,8 @@ mod tests {
         let expected = Tuple::color(1.4, 0.8, 0.6);
         let output: Tuple = &c * 2.0;
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The multiplication of the color and scalar should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -484,8 +479,8 @@ mod tests {
         let expected = Tuple::point(6.0, -4.0, 2.0);
         let output: Tuple = &p / 0.5;
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The division of the tuple and scalar should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -496,8 +491,8 @@ mod tests {
         let expected = 1.0;
         let output: f32 = v.mag();
         let r: bool =  f_equal(expected, output);
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The magnitude of the vector should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -508,8 +503,8 @@ mod tests {
         let expected = 3.74165;
         let output: f32 = v.mag();
         let r: bool = f_equal(expected, output);
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The magnitude of the vector should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -520,8 +515,8 @@ mod tests {
         let expected = Tuple::vector(1.0, 0.0, 0.0);
         let output: Tuple = v.norm();
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The normalized vector should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -532,8 +527,8 @@ mod tests {
         let expected = Tuple::vector(0.26726, 0.53452, 0.80178);
         let output: Tuple = v.norm();
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The normalized vector should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -545,8 +540,8 @@ mod tests {
         let nv = v.norm();
         let output: f32 = nv.mag();
         let r: bool = f_equal(expected, output);
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The magnitude of the vector should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -558,8 +553,8 @@ mod tests {
         let expected = 20.0;
         let output = &v1 * &v2;
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The dot product of the vectors should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -571,8 +566,8 @@ mod tests {
         let expected = Tuple::color(2.0, 6.0, 12.0);
         let output = c1.hadamard_product(c2);
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The dot product of the vectors should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -584,8 +579,8 @@ mod tests {
         let expected = Tuple::vector(-1.0, 2.0, -1.0);
         let output = v1.cross(v2);
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The cross product of the vectors should equal {:#?}, value was {:#?}", expected, output
         )
     }
@@ -597,8 +592,8 @@ mod tests {
         let expected = Tuple::vector(1.0, -2.0, 1.0);
         let output = v2.cross(v1);
         let r: bool = expected == output;
-        assert_eq!(
-            true, r,
+        assert!(
+            r,
             "The cross product of the vectors should equal {:#?}, value was {:#?}", expected, output
         )
     }
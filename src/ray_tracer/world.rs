@@ -5,11 +5,16 @@ use crate::ray_tracer::material::Material;
 use crate::ray_tracer::matrix::Matrix;
 use crate::ray_tracer::traits::object::Object;
 use crate::ray_tracer::ray::Ray;
-use crate::ray_tracer::traits::intersection_object::IntersectionObject;
+use crate::ray_tracer::intersection::{Intersection, Computations};
+use crate::ray_tracer::random::Rng;
+use crate::ray_tracer::environment::Background;
+use std::cmp::Ordering;
 
 pub struct World {
-    light: Light,
-    objects: Vec<Box<dyn Object>>
+    pub lights: Vec<Light>,
+    pub objects: Vec<Box<dyn Object>>,
+    // What rays that miss every object see. Black unless set.
+    pub background: Background
 }
 
 impl World {
@@ -17,8 +22,90 @@ impl World {
         Default::default()
     }
 
-    pub fn intersect(ray: Ray) -> Option<Vec<Box<dyn IntersectionObject>>> {
-        None
+    // A world without any lights or objects.
+    pub fn empty() -> Self {
+        World {
+            lights: Vec::new(),
+            objects: Vec::new(),
+            background: Background::default()
+        }
+    }
+
+    // Returns every intersection of the ray with every object in the world, sorted by t.
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs = Vec::new();
+        for object in &self.objects {
+            if let Some(mut i) = object.intersect(ray) {
+                xs.append(&mut i);
+            }
+        }
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
+        xs
+    }
+
+    // Sums the contribution of every light, on top of the light the surface gives off itself. Only
+    // the shadow rays start from over_point, the surface is still lit at the actual point. Glowing
+    // objects don't light anything else here, only the path tracer handles that.
+    pub fn shade_hit(&self, comps: &Computations) -> Tuple {
+        let mut rng = Rng::from_point(&comps.point);
+        let mut color = comps.object.get_material().emission;
+        for light in self.lights.iter() {
+            let visibility = self.visible_fraction(light, comps.over_point, comps.time, &mut rng);
+            let contribution = light.lighting_with_visibility(comps.object.get_material(), comps.point, comps.eyev,
+                                                              comps.normalv, visibility);
+            color = &color + &contribution;
+        }
+        color
+    }
+
+    // The color seen along the ray, the background's when the ray doesn't hit anything.
+    pub fn color_at(&self, ray: &Ray) -> Tuple {
+        let xs = self.intersect(ray);
+        match Ray::hit(&xs) {
+            None => self.background.color(ray.direction.norm()),
+            Some(hit) => {
+                let comps = hit.prepare_computations(ray);
+                self.shade_hit(&comps)
+            }
+        }
+    }
+
+    // Whether anything blocks the light from reaching point. Only the first shadow ray of an area
+    // light is tested, use intensity_at to find how much of it is blocked.
+    pub fn is_shadowed(&self, light: &Light, point: Tuple) -> bool {
+        let mut rng = Rng::from_point(&point);
+        match light.shadow_rays(point, &mut rng).first() {
+            Some((direction, distance)) => self.is_occluded(point, *direction, *distance, 0.0),
+            None => false
+        }
+    }
+
+    // Fraction of the light's shadow rays that reach it from point. Always 0.0 or 1.0 for point,
+    // spot and directional lights, anything in between for an area light.
+    pub fn intensity_at(&self, light: &Light, point: Tuple, rng: &mut Rng) -> f32 {
+        self.visible_fraction(light, point, 0.0, rng)
+    }
+
+    // intensity_at, with the shadow rays traced at time.
+    fn visible_fraction(&self, light: &Light, point: Tuple, time: f32, rng: &mut Rng) -> f32 {
+        let rays = light.shadow_rays(point, rng);
+        let visible = rays.iter().filter(|(direction, distance)| !self.is_occluded(point, *direction, *distance, time)).count();
+        visible as f32 / rays.len() as f32
+    }
+
+    // Whether a ray from point in direction, traced at time, hits anything closer than distance.
+    pub fn is_occluded(&self, point: Tuple, direction: Tuple, distance: f32, time: f32) -> bool {
+        let r = Ray::with_time(point, direction, time);
+        let xs = self.intersect(&r);
+        match Ray::hit(&xs) {
+            Some(hit) => hit.t < distance,
+            None => false
+        }
+    }
+
+    // Every object that gives off light, in the order they appear in objects.
+    pub fn emitters(&self) -> Vec<&dyn Object> {
+        self.objects.iter().map(|o| o.as_ref()).filter(|o| o.get_material().is_emissive()).collect()
     }
 }
 
@@ -28,12 +115,14 @@ impl Default for World {
         m1.color = Tuple::color(0.8, 1.0, 0.6);
         m1.diffuse = 0.7;
         m1.specular = 0.2;
-        let s1 = Sphere { material: m1, ..Default::default() };
-        let s2 = Sphere { transform: Matrix::scaling(0.5, 0.5, 0.5), ..Default::default() };
+        let mut s1 = Sphere::new();
+        s1.material = m1;
+        let s2 = Sphere::with_transform(Matrix::scaling(0.5, 0.5, 0.5));
         World {
-            light: Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(-10.0, 10.0, -10.0)),
+            lights: vec![Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(-10.0, 10.0, -10.0))],
             // s1 and s2 are treated as Trait Objects, as required by the type of objects in the Struct
-            objects: vec![Box::new(s1), Box::new(s2)]
+            objects: vec![Box::new(s1), Box::new(s2)],
+            background: Background::default()
         }
     }
 }
@@ -41,6 +130,7 @@ impl Default for World {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ray_tracer::light::AreaLight;
 
     #[test]
     fn default_world() {
@@ -49,11 +139,12 @@ mod tests {
         m1.color = Tuple::color(0.8, 1.0, 0.6);
         m1.diffuse = 0.7;
         m1.specular = 0.2;
-        let s1 = Sphere { material: m1, ..Default::default() };
-        let s2 = Sphere { transform: Matrix::scaling(0.5, 0.5, 0.5), ..Default::default() };
+        let mut s1 = Sphere::new();
+        s1.material = m1;
+        let s2 = Sphere::with_transform(Matrix::scaling(0.5, 0.5, 0.5));
         let w = World::new();
         assert!(
-            w.light == light &&
+            w.lights == vec![light] &&
             w.objects[0].get_material() == s1.get_material() &&
             w.objects[0].get_object_type() == s1.get_object_type() &&
             w.objects[0].get_transform() == s1.get_transform() &&
@@ -61,8 +152,259 @@ mod tests {
             w.objects[1].get_material() == s2.get_material() &&
             w.objects[1].get_object_type() == s2.get_object_type() &&
             w.objects[1].get_transform() == s2.get_transform() &&
-            w.objects[1].get_origin() == s2.get_origin(),            
+            w.objects[1].get_origin() == s2.get_origin(),
             "The default world was not created correctly."
         )
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn intersect_world_with_ray() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.intersect(&r);
+        let ts: Vec<f32> = xs.iter().map(|i| i.t).collect();
+        assert!(
+            ts == vec![4.0, 4.5, 5.5, 6.0],
+            "The intersections were not found or sorted correctly. Result: {:?}", ts
+        )
+    }
+
+    #[test]
+    fn shade_intersection() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.objects[0].intersect(&r).unwrap();
+        let comps = xs[0].prepare_computations(&r);
+        let result = w.shade_hit(&comps);
+        assert!(
+            result == Tuple::color(0.38066, 0.47583, 0.2855),
+            "The intersection was not shaded correctly. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn shade_intersection_from_inside() {
+        let mut w = World::new();
+        w.lights = vec![Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.25, 0.0))];
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.objects[1].intersect(&r).unwrap();
+        let comps = xs[1].prepare_computations(&r);
+        let result = w.shade_hit(&comps);
+        assert!(
+            result == Tuple::color(0.90498, 0.90498, 0.90498),
+            "The intersection was not shaded correctly. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(
+            w.color_at(&r) == Tuple::color(0.0, 0.0, 0.0),
+            "A ray that misses everything should be black."
+        )
+    }
+
+    #[test]
+    fn missed_rays_see_background() {
+        let mut w = World::new();
+        w.background = Background::Gradient { bottom: Tuple::color(1.0, 1.0, 1.0), top: Tuple::color(0.2, 0.4, 1.0) };
+        let up = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 2.0, 0.0));
+        let hit = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(
+            w.color_at(&up) == Tuple::color(0.2, 0.4, 1.0) && w.color_at(&hit) == Tuple::color(0.38066, 0.47583, 0.2855),
+            "Rays that miss should see the background, rays that hit the object."
+        )
+    }
+
+    #[test]
+    fn color_when_ray_hits() {
+        let w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let result = w.color_at(&r);
+        assert!(
+            result == Tuple::color(0.38066, 0.47583, 0.2855),
+            "The color of the hit was not correct. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn color_with_intersection_behind_ray() {
+        let mut w = World::new();
+        let mut outer = Sphere::new();
+        outer.material.color = Tuple::color(0.8, 1.0, 0.6);
+        outer.material.ambient = 1.0;
+        let mut inner = Sphere::with_transform(Matrix::scaling(0.5, 0.5, 0.5));
+        inner.material.ambient = 1.0;
+        w.objects = vec![
+            Box::new(outer),
+            Box::new(inner)
+        ];
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0));
+        let result = w.color_at(&r);
+        assert!(
+            result == Tuple::color(1.0, 1.0, 1.0),
+            "The color should be the inner sphere's color. Result: {:?}", result
+        )
+    }
+
+    #[test]
+    fn no_shadow_when_nothing_collinear() {
+        let w = World::new();
+        assert!(
+            !w.is_shadowed(&w.lights[0], Tuple::point(0.0, 10.0, 0.0)),
+            "The point should not be in shadow."
+        )
+    }
+
+    #[test]
+    fn shadow_when_object_between_point_and_light() {
+        let w = World::new();
+        assert!(
+            w.is_shadowed(&w.lights[0], Tuple::point(10.0, -10.0, 10.0)),
+            "The point should be in shadow."
+        )
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_light() {
+        let w = World::new();
+        assert!(
+            !w.is_shadowed(&w.lights[0], Tuple::point(-20.0, 20.0, -20.0)),
+            "The point should not be in shadow."
+        )
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_point() {
+        let w = World::new();
+        assert!(
+            !w.is_shadowed(&w.lights[0], Tuple::point(-2.0, 2.0, -2.0)),
+            "The point should not be in shadow."
+        )
+    }
+
+    #[test]
+    fn point_light_intensity() {
+        let w = World::new();
+        let mut rng = Rng::new(0);
+        let light = Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(-10.0, 10.0, -10.0));
+        let cases = [
+            (Tuple::point(0.0, 1.0001, 0.0), 1.0),
+            (Tuple::point(-1.0001, 0.0, 0.0), 1.0),
+            (Tuple::point(0.0, 0.0, -1.0001), 1.0),
+            (Tuple::point(0.0, 0.0, 1.0001), 0.0),
+            (Tuple::point(1.0001, 0.0, 0.0), 0.0),
+            (Tuple::point(0.0, -1.0001, 0.0), 0.0),
+            (Tuple::point(0.0, 0.0, 0.0), 0.0)
+        ];
+        for (point, expected) in cases.iter() {
+            let result = w.intensity_at(&light, *point, &mut rng);
+            assert!(
+                result == *expected,
+                "The intensity at {:?} was {}, expected {}.", point, result, expected
+            )
+        }
+    }
+
+    #[test]
+    fn area_light_intensity() {
+        let w = World::new();
+        let mut rng = Rng::new(0);
+        let mut area = AreaLight::new(Tuple::point(-0.5, -0.5, -5.0), Tuple::vector(1.0, 0.0, 0.0), 2,
+                                      Tuple::vector(0.0, 1.0, 0.0), 2);
+        area.jitter = false;
+        let light = Light::area(Tuple::color(1.0, 1.0, 1.0), area);
+        let cases = [
+            (Tuple::point(0.0, 0.0, 2.0), 0.0),
+            (Tuple::point(1.0, -1.0, 2.0), 0.25),
+            (Tuple::point(1.5, 0.0, 2.0), 0.5),
+            (Tuple::point(1.25, 1.25, 3.0), 0.75),
+            (Tuple::point(0.0, 0.0, -2.0), 1.0)
+        ];
+        for (point, expected) in cases.iter() {
+            let result = w.intensity_at(&light, *point, &mut rng);
+            assert!(
+                result == *expected,
+                "The intensity at {:?} was {}, expected {}.", point, result, expected
+            )
+        }
+    }
+
+    #[test]
+    fn area_light_produces_penumbra() {
+        let mut w = World::empty();
+        let area = AreaLight::new(Tuple::point(-1.0, 5.0, -1.0), Tuple::vector(2.0, 0.0, 0.0), 8,
+                                  Tuple::vector(0.0, 0.0, 2.0), 8);
+        w.lights = vec![Light::area(Tuple::color(1.0, 1.0, 1.0), area)];
+        w.objects = vec![Box::new(Sphere::with_transform(Matrix::translation(0.0, 2.0, 0.0)))];
+        let mut rng = Rng::new(0);
+        // Directly under the sphere is the umbra, further out only part of the light is blocked.
+        let umbra = w.intensity_at(&w.lights[0], Tuple::point(0.0, 0.0, 0.0), &mut rng);
+        let penumbra = w.intensity_at(&w.lights[0], Tuple::point(1.6, 0.0, 0.0), &mut rng);
+        let lit = w.intensity_at(&w.lights[0], Tuple::point(10.0, 0.0, 0.0), &mut rng);
+        assert!(
+            umbra == 0.0 && penumbra > 0.0 && penumbra < 1.0 && lit == 1.0,
+            "The area light did not produce a soft shadow. Umbra: {}, penumbra: {}, lit: {}", umbra, penumbra, lit
+        )
+    }
+
+    #[test]
+    fn directional_light_casts_parallel_shadows() {
+        let mut w = World::empty();
+        w.lights = vec![Light::directional(Tuple::color(1.0, 1.0, 1.0), Tuple::vector(0.0, -1.0, 0.0))];
+        w.objects = vec![Box::new(Sphere::new())];
+        assert!(
+            w.is_shadowed(&w.lights[0], Tuple::point(0.9, -100.0, 0.0)) &&
+            !w.is_shadowed(&w.lights[0], Tuple::point(1.1, -100.0, 0.0)),
+            "The shadow of a directional light should not grow with distance."
+        )
+    }
+
+    #[test]
+    fn shade_hit_sums_lights() {
+        let mut w = World::new();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let single = w.color_at(&r);
+        w.lights.push(Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(-10.0, 10.0, -10.0)));
+        let double = w.color_at(&r);
+        assert!(
+            double == &single * 2.0,
+            "Two identical lights should double the color. Result: {:?}", double
+        )
+    }
+
+    #[test]
+    fn spot_light_only_lights_its_cone() {
+        let mut w = World::empty();
+        w.lights = vec![Light::spot(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0),
+                                    Tuple::vector(0.0, 0.0, 1.0), 0.02, 0.05)];
+        w.objects = vec![Box::new(Sphere::new())];
+        let center = w.color_at(&Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)));
+        let side = w.color_at(&Ray::new(Tuple::point(0.0, 0.9, -5.0), Tuple::vector(0.0, 0.0, 1.0)));
+        assert!(
+            center.x > 0.9 && side == Tuple::color(0.1, 0.1, 0.1),
+            "Only the center of the sphere should be inside of the spot light's cone. Center: {:?}, side: {:?}",
+            center, side
+        )
+    }
+
+    #[test]
+    fn shade_hit_in_shadow() {
+        let mut w = World::empty();
+        w.lights = vec![Light::new(Tuple::color(1.0, 1.0, 1.0), Tuple::point(0.0, 0.0, -10.0))];
+        w.objects = vec![
+            Box::new(Sphere::new()),
+            Box::new(Sphere::with_transform(Matrix::translation(0.0, 0.0, 10.0)))
+        ];
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.objects[1].intersect(&r).unwrap();
+        let comps = xs[0].prepare_computations(&r);
+        let result = w.shade_hit(&comps);
+        assert!(
+            result == Tuple::color(0.1, 0.1, 0.1),
+            "A hit in shadow should only have the ambient color. Result: {:?}", result
+        )
+    }
+}